@@ -40,7 +40,7 @@ impl exports::brio::core::event_handler::Guest for Component {
             );
 
             // Strategic Logic (Simulated for Prototype)
-            // In a real system, this would call `brio::core::inference::chat(...)`
+            // In a real system, this would call `brio::ai::inference::chat(...)`
 
             let milestones = [
                 "Phase 1: Setup Workspace",
@@ -1,6 +1,8 @@
 use anyhow::Result;
 use brio_kernel::host::BrioHostState;
-use brio_kernel::inference::{ChatRequest, ChatResponse, InferenceError, LLMProvider};
+use brio_kernel::inference::{
+    CassetteMode, CassetteProvider, ChatRequest, ChatResponse, InferenceError, LLMProvider,
+};
 use brio_kernel::mesh::{MeshMessage, Payload};
 use sqlx::Row;
 use std::collections::HashSet;
@@ -80,6 +82,7 @@ impl LLMProvider for MockProvider {
     async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse, InferenceError> {
         Ok(ChatResponse {
             content: "Mock".to_string(),
+            tool_calls: Vec::new(),
             usage: None,
         })
     }
@@ -382,13 +385,14 @@ async fn smart_agent_logic(
     mut rx: mpsc::Receiver<MeshMessage>,
     path: String,
 ) {
-    use brio_kernel::inference::{Message, Role};
+    use brio_kernel::inference::Message;
 
     while let Some(msg) = rx.recv().await {
         if msg.method == "fix" {
             // 1. Begin Session (Sandboxed)
             let session_id = host
                 .begin_session(path.clone())
+                .await
                 .expect("Failed to begin session");
             let session_path = std::env::temp_dir().join("brio").join(&session_id);
             let file_path = session_path.join("dummy_bug.txt");
@@ -404,19 +408,13 @@ async fn smart_agent_logic(
                 content
             );
 
-            let request = ChatRequest {
-                model: "mistralai/devstral-2512:free".to_string(), // Reliable free model
-                messages: vec![
-                    Message {
-                        role: Role::System,
-                        content: "You are a precise code editor.".into(),
-                    },
-                    Message {
-                        role: Role::User,
-                        content: prompt,
-                    },
+            let request = ChatRequest::new(
+                "mistralai/devstral-2512:free", // Reliable free model
+                vec![
+                    Message::system("You are a precise code editor."),
+                    Message::user(prompt),
                 ],
-            };
+            );
 
             let response = host
                 .inference()
@@ -431,6 +429,7 @@ async fn smart_agent_logic(
 
             // 5. Commit
             host.commit_session(session_id)
+                .await
                 .expect("Failed to commit session");
             let _ = msg
                 .reply_tx
@@ -444,11 +443,13 @@ async fn agent_logic(host: Arc<BrioHostState>, mut rx: mpsc::Receiver<MeshMessag
         if msg.method == "fix" {
             let session = host
                 .begin_session(path.clone())
+                .await
                 .expect("Failed to begin session");
             let session_path = std::env::temp_dir().join("brio").join(&session);
             std::fs::write(session_path.join("dummy_bug.txt"), "fixed")
                 .expect("Failed to write fix");
             host.commit_session(session)
+                .await
                 .expect("Failed to commit session");
             let _ = msg.reply_tx.send(Ok(Payload::Json("fixed".into())));
         }
@@ -461,25 +462,43 @@ async fn agent_logic(host: Arc<BrioHostState>, mut rx: mpsc::Receiver<MeshMessag
 
 #[tokio::test(flavor = "multi_thread")]
 async fn manifesto_scenario_real_ai() -> Result<()> {
-    // 1. Check for Key (Conditional Execution)
-    let api_key = match std::env::var("OPENROUTER_API_KEY") {
-        Ok(k) => k,
+    // 1. Choose the provider: the real API when a key is set (recording the
+    // cassette with BRIO_RECORD_CASSETTES=1), otherwise an offline replay
+    let cassette = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/cassettes/manifesto_real_ai.json"
+    );
+    let provider: Box<dyn LLMProvider> = match std::env::var("OPENROUTER_API_KEY") {
+        Ok(api_key) => {
+            // 2. Setup Real Provider
+            // Clean Code: Secure config
+            let config = brio_kernel::inference::OpenAIConfig::new(
+                secrecy::SecretString::new(api_key.into()),
+                reqwest::Url::parse("https://openrouter.ai/api/v1/")?,
+            );
+            let provider = brio_kernel::inference::OpenAIProvider::new(config);
+            let mode = if std::env::var_os("BRIO_RECORD_CASSETTES").is_some() {
+                CassetteMode::Record
+            } else {
+                CassetteMode::Replay
+            };
+            Box::new(CassetteProvider::open(
+                cassette,
+                mode,
+                Some(Arc::new(provider)),
+            )?)
+        }
+        Err(_) if std::path::Path::new(cassette).exists() => {
+            Box::new(CassetteProvider::replay_only(cassette)?)
+        }
         Err(_) => {
-            println!("Skipping real AI test: OPENROUTER_API_KEY not set");
+            println!("Skipping real AI test: OPENROUTER_API_KEY not set and no cassette");
             return Ok(());
         }
     };
 
-    // 2. Setup Real Provider
-    // Clean Code: Secure config
-    let config = brio_kernel::inference::OpenAIConfig::new(
-        secrecy::SecretString::new(api_key.into()),
-        reqwest::Url::parse("https://openrouter.ai/api/v1/")?,
-    );
-    let provider = brio_kernel::inference::OpenAIProvider::new(config);
-
     // 3. Arrange Environment
-    let mut env = TestEnvironment::setup_with_provider(Box::new(provider)).await?;
+    let mut env = TestEnvironment::setup_with_provider(provider).await?;
     let project_file = env.root.join("dummy_bug.txt");
     std::fs::write(&project_file, "bug")?;
 
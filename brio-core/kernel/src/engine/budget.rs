@@ -0,0 +1,227 @@
+//! CPU budgets for guest invocations.
+//!
+//! Every store is armed with fuel and an epoch deadline before guest code runs,
+//! so a plugin spinning inside `run` or `handle-event` traps instead of pinning
+//! a tokio worker forever.
+
+use anyhow::Result;
+use serde::Deserialize;
+use std::fmt;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::debug;
+use wasmtime::{Engine, Store, Trap, UpdateDeadline};
+
+/// Fuel assigned when no fuel limit is configured.
+/// Wasmtime tracks fuel as an `i64`, larger values are not accounted exactly.
+const UNLIMITED_FUEL: u64 = i64::MAX as u64;
+/// Epoch ticks assigned when no deadline is configured.
+/// Halved so that `current_epoch + ticks` cannot overflow.
+const UNLIMITED_EPOCH_TICKS: u64 = u64::MAX / 2;
+
+/// Per-invocation CPU budget for a plugin.
+///
+/// `None` on either dimension means the dimension is unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub struct CpuBudget {
+    /// Maximum fuel (roughly, executed WASM instructions) per invocation.
+    #[serde(default)]
+    pub fuel: Option<u64>,
+    /// Maximum ticks of the epoch ticker the guest may run for per
+    /// invocation. Ticks that pass while the guest awaits a host call
+    /// (inference, mesh calls, SQL) count as at most one.
+    #[serde(default)]
+    pub epoch_ticks: Option<u64>,
+}
+
+impl CpuBudget {
+    /// Creates a budget with no limits.
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Sets the fuel limit
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    /// Sets the epoch deadline in ticks
+    pub fn with_epoch_ticks(mut self, ticks: u64) -> Self {
+        self.epoch_ticks = Some(ticks);
+        self
+    }
+
+    /// Arms the store with this budget. Must be called before any guest code runs.
+    ///
+    /// # Errors
+    /// Returns error if the engine was created without fuel metering.
+    pub fn apply<T: 'static>(&self, store: &mut Store<T>) -> Result<()> {
+        store.set_fuel(self.initial_fuel())?;
+        match self.epoch_ticks {
+            // The deadline stays one tick ahead and is renewed when guest code
+            // reaches it, so a host call spanning many ticks uses up one
+            Some(ticks) => {
+                let mut remaining = ticks;
+                store.set_epoch_deadline(1);
+                store.epoch_deadline_callback(move |_| {
+                    remaining = remaining.saturating_sub(1);
+                    if remaining == 0 {
+                        Err(Trap::Interrupt.into())
+                    } else {
+                        Ok(UpdateDeadline::Yield(1))
+                    }
+                });
+            }
+            None => {
+                store.set_epoch_deadline(UNLIMITED_EPOCH_TICKS);
+                store.epoch_deadline_trap();
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the fuel consumed in the store since `apply` was called.
+    pub fn consumed<T>(&self, store: &Store<T>) -> u64 {
+        let remaining = store.get_fuel().unwrap_or(0);
+        self.initial_fuel().saturating_sub(remaining)
+    }
+
+    /// Maps a guest trap caused by this budget into a [`BudgetExhausted`] error.
+    /// Any other error is returned untouched.
+    pub fn classify(&self, error: anyhow::Error, fuel_consumed: u64) -> anyhow::Error {
+        let kind = match error.downcast_ref::<Trap>() {
+            Some(Trap::OutOfFuel) => BudgetKind::Fuel,
+            Some(Trap::Interrupt) => BudgetKind::Epoch,
+            _ => return error,
+        };
+
+        BudgetExhausted {
+            kind,
+            fuel_consumed,
+            budget: *self,
+        }
+        .into()
+    }
+
+    fn initial_fuel(&self) -> u64 {
+        self.fuel.unwrap_or(UNLIMITED_FUEL)
+    }
+}
+
+/// Which dimension of a [`CpuBudget`] ran out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetKind {
+    Fuel,
+    Epoch,
+}
+
+impl fmt::Display for BudgetKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetKind::Fuel => f.write_str("fuel"),
+            BudgetKind::Epoch => f.write_str("epoch deadline"),
+        }
+    }
+}
+
+/// A guest invocation was aborted because it exceeded its CPU budget.
+#[derive(Debug, thiserror::Error)]
+#[error("CPU budget exhausted ({kind}): consumed {fuel_consumed} fuel")]
+pub struct BudgetExhausted {
+    pub kind: BudgetKind,
+    pub fuel_consumed: u64,
+    pub budget: CpuBudget,
+}
+
+/// A guest invocation ran within its budget but returned an error.
+#[derive(Debug, thiserror::Error)]
+#[error("Agent execution failed: {message}")]
+pub struct GuestFailed {
+    pub message: String,
+    pub fuel_consumed: u64,
+}
+
+/// The fuel a failed invocation consumed, if the guest ran at all.
+pub fn fuel_consumed(error: &anyhow::Error) -> Option<u64> {
+    error.chain().find_map(|cause| {
+        cause
+            .downcast_ref::<BudgetExhausted>()
+            .map(|e| e.fuel_consumed)
+            .or_else(|| cause.downcast_ref::<GuestFailed>().map(|e| e.fuel_consumed))
+    })
+}
+
+/// Output of a guest invocation together with its CPU accounting.
+#[derive(Debug)]
+pub struct Metered<T> {
+    pub output: T,
+    pub fuel_consumed: u64,
+}
+
+/// Spawns a background task advancing the engine epoch every `interval`.
+///
+/// Epoch deadlines are measured in ticks of this task, so it must be running
+/// for `CpuBudget::epoch_ticks` to have any effect.
+pub fn spawn_epoch_ticker(engine: Engine, interval: Duration) -> JoinHandle<()> {
+    debug!(
+        interval_ms = interval.as_millis() as u64,
+        "Starting epoch ticker"
+    );
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            engine.increment_epoch();
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_builders() {
+        let budget = CpuBudget::unlimited().with_fuel(1_000).with_epoch_ticks(5);
+        assert_eq!(budget.fuel, Some(1_000));
+        assert_eq!(budget.epoch_ticks, Some(5));
+        assert_eq!(CpuBudget::unlimited().initial_fuel(), UNLIMITED_FUEL);
+    }
+
+    #[test]
+    fn test_classify_leaves_other_errors_untouched() {
+        let budget = CpuBudget::unlimited();
+        let error = budget.classify(anyhow::anyhow!("boom"), 0);
+        assert!(error.downcast_ref::<BudgetExhausted>().is_none());
+        assert_eq!(error.to_string(), "boom");
+    }
+
+    #[test]
+    fn test_classify_out_of_fuel() {
+        let budget = CpuBudget::unlimited().with_fuel(10);
+        let error = budget.classify(anyhow::Error::new(Trap::OutOfFuel), 10);
+        let exhausted = error
+            .downcast_ref::<BudgetExhausted>()
+            .expect("expected BudgetExhausted");
+        assert_eq!(exhausted.kind, BudgetKind::Fuel);
+        assert_eq!(exhausted.fuel_consumed, 10);
+    }
+
+    #[test]
+    fn test_fuel_consumed_of_errors() {
+        let exhausted = CpuBudget::unlimited()
+            .with_fuel(10)
+            .classify(anyhow::Error::new(Trap::OutOfFuel), 10);
+        assert_eq!(fuel_consumed(&exhausted), Some(10));
+
+        let failed = anyhow::Error::new(GuestFailed {
+            message: "no".to_string(),
+            fuel_consumed: 7,
+        })
+        .context("Calling agent");
+        assert_eq!(fuel_consumed(&failed), Some(7));
+        assert_eq!(fuel_consumed(&anyhow::anyhow!("boom")), None);
+    }
+}
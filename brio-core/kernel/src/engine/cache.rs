@@ -0,0 +1,218 @@
+//! Compiled component cache.
+//!
+//! Components are keyed by the SHA-256 of their bytes, so an unchanged `.wasm`
+//! is compiled by Cranelift once per process (and once per machine when a disk
+//! cache directory is configured).
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tracing::{debug, warn};
+use wasmtime::Engine;
+use wasmtime::component::Component;
+
+/// Extension used for serialized components in the disk cache.
+const CACHE_EXTENSION: &str = "cwasm";
+
+pub struct ComponentCache {
+    engine: Engine,
+    components: RwLock<HashMap<String, Component>>,
+    disk_dir: Option<PathBuf>,
+}
+
+impl ComponentCache {
+    /// Creates an in-memory cache.
+    pub fn new(engine: Engine) -> Self {
+        Self {
+            engine,
+            components: RwLock::new(HashMap::new()),
+            disk_dir: None,
+        }
+    }
+
+    /// Persists precompiled components under `dir` so restarts skip compilation.
+    pub fn with_disk_cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.disk_dir = Some(dir.into());
+        self
+    }
+
+    /// Loads the component at `path`, compiling it only on a cache miss.
+    pub fn load(&self, path: &Path) -> Result<Component> {
+        self.load_keyed(path).map(|(_, component)| component)
+    }
+
+    /// Like [`load`](Self::load), also returning the cache key for [`evict`](Self::evict).
+    pub fn load_keyed(&self, path: &Path) -> Result<(String, Component)> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read component from {:?}", path))?;
+        let key = self.cache_key(&bytes);
+
+        {
+            let components = self.components.read().expect("RwLock poisoned");
+            if let Some(component) = components.get(&key) {
+                debug!(path = ?path, key = %key, "Component cache hit");
+                return Ok((key, component.clone()));
+            }
+        }
+
+        let component = match self.load_from_disk(&key) {
+            Some(component) => component,
+            None => self.compile(&key, &bytes, path)?,
+        };
+
+        let mut components = self.components.write().expect("RwLock poisoned");
+        components.insert(key.clone(), component.clone());
+        Ok((key, component))
+    }
+
+    /// Drops a compiled component from memory. The disk artifact is kept.
+    pub fn evict(&self, key: &str) {
+        let mut components = self.components.write().expect("RwLock poisoned");
+        if components.remove(key).is_some() {
+            debug!(key = %key, "Evicted compiled component");
+        }
+    }
+
+    /// Number of compiled components held in memory.
+    pub fn len(&self) -> usize {
+        self.components.read().expect("RwLock poisoned").len()
+    }
+
+    /// Returns true if no components are cached in memory.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Content hash of the component combined with the engine's compatibility
+    /// hash, so artifacts from a differently configured engine never match.
+    fn cache_key(&self, bytes: &[u8]) -> String {
+        let mut engine_hasher = DefaultHasher::new();
+        self.engine
+            .precompile_compatibility_hash()
+            .hash(&mut engine_hasher);
+
+        let mut hasher = Sha256::new();
+        hasher.update(bytes);
+        format!(
+            "{}-{:016x}",
+            hex::encode(hasher.finalize()),
+            engine_hasher.finish()
+        )
+    }
+
+    fn disk_path(&self, key: &str) -> Option<PathBuf> {
+        self.disk_dir
+            .as_ref()
+            .map(|dir| dir.join(key).with_extension(CACHE_EXTENSION))
+    }
+
+    fn load_from_disk(&self, key: &str) -> Option<Component> {
+        let path = self.disk_path(key)?;
+        if !path.exists() {
+            return None;
+        }
+
+        // SAFETY: files in the cache directory are only ever written by
+        // `compile` below from `Engine::precompile_component` output.
+        match unsafe { Component::deserialize_file(&self.engine, &path) } {
+            Ok(component) => {
+                debug!(path = ?path, "Loaded precompiled component from disk cache");
+                Some(component)
+            }
+            Err(e) => {
+                warn!(path = ?path, error = %e, "Discarding unusable cached component");
+                let _ = std::fs::remove_file(&path);
+                None
+            }
+        }
+    }
+
+    fn compile(&self, key: &str, bytes: &[u8], source: &Path) -> Result<Component> {
+        debug!(path = ?source, key = %key, "Compiling component");
+        let serialized = self
+            .engine
+            .precompile_component(bytes)
+            .with_context(|| format!("Failed to compile component from {:?}", source))?;
+
+        if let Some(path) = self.disk_path(key)
+            && let Err(e) = write_atomic(&path, &serialized)
+        {
+            warn!(path = ?path, error = %e, "Failed to persist compiled component");
+        }
+
+        // SAFETY: `serialized` was produced by `precompile_component` on this engine.
+        unsafe { Component::deserialize(&self.engine, &serialized) }
+            .context("Failed to load compiled component")
+    }
+}
+
+/// Writes through a temporary file so readers never observe a partial artifact.
+fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension(format!("{}.{}", CACHE_EXTENSION, uuid::Uuid::new_v4()));
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::linker::create_engine_config;
+    use tempfile::tempdir;
+
+    fn engine() -> Engine {
+        Engine::new(&create_engine_config()).expect("engine")
+    }
+
+    #[test]
+    fn test_cache_reuses_compiled_component() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("empty.wasm");
+        std::fs::write(&path, "(component)")?;
+
+        let cache = ComponentCache::new(engine());
+        cache.load(&path)?;
+        cache.load(&path)?;
+        assert_eq!(cache.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_persists_to_disk() -> Result<()> {
+        let dir = tempdir()?;
+        let cache_dir = dir.path().join("cache");
+        let path = dir.path().join("empty.wasm");
+        std::fs::write(&path, "(component)")?;
+
+        let engine = engine();
+        ComponentCache::new(engine.clone())
+            .with_disk_cache(&cache_dir)
+            .load(&path)?;
+
+        let artifacts = std::fs::read_dir(&cache_dir)?.count();
+        assert_eq!(artifacts, 1);
+
+        // A fresh cache picks the artifact up instead of recompiling.
+        let cache = ComponentCache::new(engine).with_disk_cache(&cache_dir);
+        cache.load(&path)?;
+        assert_eq!(cache.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_rejects_invalid_component() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("broken.wasm");
+        std::fs::write(&path, b"not wasm")?;
+
+        let cache = ComponentCache::new(engine());
+        assert!(cache.load(&path).is_err());
+        assert!(cache.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+//! Memory and table limits for plugin stores.
+//!
+//! The engine reserves address space for every linear memory, but only the
+//! limiter decides how much of it a plugin may actually commit.
+
+use crate::infrastructure::audit::{self, AuditEvent};
+use anyhow::Result;
+use serde::Deserialize;
+use tracing::warn;
+use wasmtime::{DEFAULT_INSTANCE_LIMIT, DEFAULT_MEMORY_LIMIT, DEFAULT_TABLE_LIMIT};
+
+/// Per-store resource limits for a plugin.
+///
+/// `None` falls back to Wasmtime's defaults (unbounded growth for memories
+/// and tables, 10,000 instances).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub struct ResourceLimits {
+    /// Maximum size of any single linear memory, in bytes.
+    #[serde(default)]
+    pub max_memory_bytes: Option<usize>,
+    /// Maximum number of elements in any single table.
+    #[serde(default)]
+    pub max_table_elements: Option<usize>,
+    /// Maximum number of core instances in the store.
+    #[serde(default)]
+    pub max_instances: Option<usize>,
+}
+
+impl ResourceLimits {
+    /// Creates limits that defer to Wasmtime's defaults.
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Sets the linear memory limit in bytes
+    pub fn with_max_memory_bytes(mut self, bytes: usize) -> Self {
+        self.max_memory_bytes = Some(bytes);
+        self
+    }
+
+    /// Sets the table element limit
+    pub fn with_max_table_elements(mut self, elements: usize) -> Self {
+        self.max_table_elements = Some(elements);
+        self
+    }
+
+    /// Sets the instance count limit
+    pub fn with_max_instances(mut self, instances: usize) -> Self {
+        self.max_instances = Some(instances);
+        self
+    }
+}
+
+/// `ResourceLimiter` installed in every `Store<BrioHostState>`.
+/// Denied growth requests are reported as audit events.
+#[derive(Debug, Clone)]
+pub struct PluginLimiter {
+    plugin_id: String,
+    limits: ResourceLimits,
+}
+
+impl PluginLimiter {
+    pub fn new(plugin_id: impl Into<String>, limits: ResourceLimits) -> Self {
+        Self {
+            plugin_id: plugin_id.into(),
+            limits,
+        }
+    }
+
+    pub fn limits(&self) -> ResourceLimits {
+        self.limits
+    }
+
+    /// Emits an audit event if `error` was raised by the instance count limit.
+    /// Wasmtime enforces that limit internally, so the limiter is never asked.
+    pub fn audit_instantiation_error(&self, error: &anyhow::Error) {
+        if let Some(limit) = self.limits.max_instances
+            && let Some(count) = instance_count_exceeded(error)
+        {
+            self.report("instances", count, limit);
+        }
+    }
+
+    fn report(&self, resource: &str, requested: usize, limit: usize) {
+        warn!(
+            plugin_id = %self.plugin_id,
+            resource,
+            requested,
+            limit,
+            "Plugin resource limit exceeded"
+        );
+        audit::log_audit(AuditEvent::ResourceLimitExceeded {
+            plugin_id: self.plugin_id.clone(),
+            resource: resource.to_string(),
+            requested,
+            limit,
+        });
+    }
+}
+
+/// The instance count a store reached, if `error` is Wasmtime refusing it.
+/// Wasmtime raises the count limits as untyped errors of the form
+/// `resource limit exceeded: instance count too high at <count>`.
+fn instance_count_exceeded(error: &anyhow::Error) -> Option<usize> {
+    error
+        .chain()
+        .find_map(|cause| {
+            cause
+                .to_string()
+                .strip_prefix("resource limit exceeded: instance count too high at ")
+                .map(str::to_string)
+        })?
+        .parse()
+        .ok()
+}
+
+impl Default for PluginLimiter {
+    fn default() -> Self {
+        Self::new("kernel", ResourceLimits::unlimited())
+    }
+}
+
+impl wasmtime::ResourceLimiter for PluginLimiter {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> Result<bool> {
+        match self.limits.max_memory_bytes {
+            Some(limit) if desired > limit => {
+                self.report("memory", desired, limit);
+                Ok(false)
+            }
+            _ => Ok(true),
+        }
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> Result<bool> {
+        match self.limits.max_table_elements {
+            Some(limit) if desired > limit => {
+                self.report("table_elements", desired, limit);
+                Ok(false)
+            }
+            _ => Ok(true),
+        }
+    }
+
+    fn instances(&self) -> usize {
+        self.limits.max_instances.unwrap_or(DEFAULT_INSTANCE_LIMIT)
+    }
+
+    fn tables(&self) -> usize {
+        DEFAULT_TABLE_LIMIT
+    }
+
+    fn memories(&self) -> usize {
+        DEFAULT_MEMORY_LIMIT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmtime::ResourceLimiter;
+
+    #[test]
+    fn test_memory_growth_within_limit() -> Result<()> {
+        let mut limiter = PluginLimiter::new(
+            "agent",
+            ResourceLimits::unlimited().with_max_memory_bytes(1024),
+        );
+        assert!(limiter.memory_growing(0, 1024, None)?);
+        assert!(!limiter.memory_growing(1024, 2048, None)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_table_growth_within_limit() -> Result<()> {
+        let mut limiter = PluginLimiter::new(
+            "agent",
+            ResourceLimits::unlimited().with_max_table_elements(8),
+        );
+        assert!(limiter.table_growing(0, 8, None)?);
+        assert!(!limiter.table_growing(8, 9, None)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_instance_limit_error_reports_count() -> Result<()> {
+        let engine = wasmtime::Engine::default();
+        let module = wasmtime::Module::new(&engine, "(module)")?;
+        let mut store = wasmtime::Store::new(
+            &engine,
+            PluginLimiter::new("agent", ResourceLimits::unlimited().with_max_instances(2)),
+        );
+        store.limiter(|limiter| limiter);
+        for _ in 0..2 {
+            wasmtime::Instance::new(&mut store, &module, &[])?;
+        }
+        let error = wasmtime::Instance::new(&mut store, &module, &[]).unwrap_err();
+        assert_eq!(instance_count_exceeded(&error), Some(3));
+        assert_eq!(instance_count_exceeded(&anyhow::anyhow!("trap")), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unlimited_defers_to_wasmtime_defaults() -> Result<()> {
+        let mut limiter = PluginLimiter::default();
+        assert!(limiter.memory_growing(0, usize::MAX, None)?);
+        assert_eq!(limiter.instances(), DEFAULT_INSTANCE_LIMIT);
+        Ok(())
+    }
+}
@@ -1,17 +1,33 @@
 use crate::engine::brio;
 use crate::host::BrioHostState;
+use crate::inference::{ChatRequest, ChatStream};
 use crate::mesh::Payload;
+use crate::vfs::diff::{ChangeKind, FileDiff};
+use crate::vfs::files;
+use crate::vfs::merge::CommitError;
 use anyhow::Result;
-use wasmtime::component::{HasSelf, Linker};
+use futures_util::StreamExt;
+use wasmtime::component::{HasSelf, Linker, Resource};
 use wasmtime::{Config, Engine};
 
 impl brio::core::service_mesh::Host for BrioHostState {
-    fn call(
+    async fn call(
         &mut self,
         target: String,
         method: String,
         args: brio::core::service_mesh::Payload,
     ) -> Result<brio::core::service_mesh::Payload, String> {
+        self.call_metered(target, method, args)
+            .await
+            .map(|reply| reply.payload)
+    }
+
+    async fn call_metered(
+        &mut self,
+        target: String,
+        method: String,
+        args: brio::core::service_mesh::Payload,
+    ) -> Result<brio::core::service_mesh::MeteredReply, String> {
         self.check_permission("mesh:send")?;
 
         // Convert WASM payload to internal Payload
@@ -20,38 +36,34 @@ impl brio::core::service_mesh::Host for BrioHostState {
             brio::core::service_mesh::Payload::Binary(b) => Payload::Binary(b),
         };
 
-        // Bridge sync to async
-        let result = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current()
-                .block_on(async { self.mesh_call(&target, &method, internal_payload).await })
-        });
+        let result = self
+            .mesh_call_metered(&target, &method, internal_payload)
+            .await;
 
         // Convert result back to WASM payload
         result
-            .map(|p| match p {
-                Payload::Json(s) => brio::core::service_mesh::Payload::Json(s),
-                Payload::Binary(b) => brio::core::service_mesh::Payload::Binary(b),
+            .map(|reply| brio::core::service_mesh::MeteredReply {
+                payload: match reply.payload {
+                    Payload::Json(s) => brio::core::service_mesh::Payload::Json(s),
+                    Payload::Binary(b) => brio::core::service_mesh::Payload::Binary(b),
+                },
+                fuel_consumed: reply.fuel_consumed,
             })
             .map_err(|e| e.to_string())
     }
 }
 
 impl brio::core::sql_state::Host for BrioHostState {
-    fn query(
+    async fn query(
         &mut self,
         sql: String,
         params: Vec<String>,
     ) -> Result<Vec<brio::core::sql_state::Row>, String> {
         self.check_permission("storage:read")?;
+        let scope = self.sql_scope()?;
+        let store = self.get_store();
 
-        // Use a default scope for WASM guests
-        let scope = "wasm_guest";
-        let store = self.get_store(scope);
-
-        let result = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current()
-                .block_on(async { store.query(scope, &sql, params).await })
-        });
+        let result = store.query(scope, &sql, params).await;
 
         result
             .map(|rows| {
@@ -65,33 +77,176 @@ impl brio::core::sql_state::Host for BrioHostState {
             .map_err(|e| e.to_string())
     }
 
-    fn execute(&mut self, sql: String, params: Vec<String>) -> Result<u32, String> {
+    async fn execute(&mut self, sql: String, params: Vec<String>) -> Result<u32, String> {
         self.check_permission("storage:write")?;
-        let scope = "wasm_guest";
-        let store = self.get_store(scope);
-
-        let result = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current()
-                .block_on(async { store.execute(scope, &sql, params).await })
-        });
+        let scope = self.sql_scope()?;
+        let store = self.get_store();
 
-        result.map_err(|e| e.to_string())
+        store
+            .execute(scope, &sql, params)
+            .await
+            .map_err(|e| e.to_string())
     }
 }
 
 impl brio::core::session_fs::Host for BrioHostState {
-    fn begin_session(&mut self, base_path: String) -> Result<String, String> {
+    async fn begin_session(&mut self, base_path: String) -> Result<String, String> {
+        self.check_permission("fs:write")?;
+        let session_id = BrioHostState::begin_session(self, base_path).await?;
+        self.set_active_session(Some(session_id.clone()))?;
+        Ok(session_id)
+    }
+
+    async fn commit_session(
+        &mut self,
+        session_id: String,
+    ) -> Result<(), brio::core::session_fs::CommitError> {
+        self.check_session_owner(&session_id)
+            .map_err(brio::core::session_fs::CommitError::Failed)?;
+        BrioHostState::commit_session(self, session_id.clone())
+            .await
+            .map_err(to_wit_commit_error)?;
+        if self.active_session() == Some(session_id.as_str()) {
+            self.set_active_session(None)
+                .map_err(brio::core::session_fs::CommitError::Failed)?;
+        }
+        Ok(())
+    }
+
+    async fn diff(
+        &mut self,
+        session_id: String,
+    ) -> Result<Vec<brio::core::session_fs::FileDiff>, String> {
+        self.check_fs_read()?;
+        let diffs = self.diff_session(&session_id).await?;
+        Ok(diffs.into_iter().map(to_wit_file_diff).collect())
+    }
+
+    async fn read_file(&mut self, session_id: String, path: String) -> Result<Vec<u8>, String> {
+        self.check_fs_read()?;
+        files::read_file(&self.access_session(&session_id).await?, &path)
+    }
+
+    async fn write_file(
+        &mut self,
+        session_id: String,
+        path: String,
+        contents: Vec<u8>,
+    ) -> Result<(), String> {
         self.check_permission("fs:write")?;
-        BrioHostState::begin_session(self, base_path)
+        self.check_session_owner(&session_id)?;
+        files::write_file(&self.access_session(&session_id).await?, &path, &contents)
     }
 
-    fn commit_session(&mut self, session_id: String) -> Result<(), String> {
-        BrioHostState::commit_session(self, session_id)
+    async fn list_dir(
+        &mut self,
+        session_id: String,
+        path: String,
+    ) -> Result<Vec<brio::core::session_fs::DirEntry>, String> {
+        self.check_fs_read()?;
+        let entries = files::list_dir(&self.access_session(&session_id).await?, &path)?;
+        Ok(entries
+            .into_iter()
+            .map(|e| brio::core::session_fs::DirEntry {
+                name: e.name,
+                kind: to_wit_entry_kind(e.kind),
+                size: e.size,
+            })
+            .collect())
+    }
+
+    async fn stat(
+        &mut self,
+        session_id: String,
+        path: String,
+    ) -> Result<Option<brio::core::session_fs::FileStat>, String> {
+        self.check_fs_read()?;
+        let stat = files::stat(&self.access_session(&session_id).await?, &path)?;
+        Ok(stat.map(|s| brio::core::session_fs::FileStat {
+            kind: to_wit_entry_kind(s.kind),
+            size: s.size,
+            modified: s.modified,
+        }))
+    }
+
+    async fn delete(&mut self, session_id: String, path: String) -> Result<(), String> {
+        self.check_permission("fs:write")?;
+        self.check_session_owner(&session_id)?;
+        files::delete(&self.access_session(&session_id).await?, &path)
+    }
+
+    async fn rename(
+        &mut self,
+        session_id: String,
+        old_path: String,
+        new_path: String,
+    ) -> Result<(), String> {
+        self.check_permission("fs:write")?;
+        self.check_session_owner(&session_id)?;
+        files::rename(
+            &self.access_session(&session_id).await?,
+            &old_path,
+            &new_path,
+        )
+    }
+}
+
+impl BrioHostState {
+    /// Reviewers read sessions they do not write.
+    fn check_fs_read(&self) -> Result<(), String> {
+        self.check_permission("fs:read")
+            .or_else(|_| self.check_permission("fs:write"))
+    }
+}
+
+fn to_wit_entry_kind(kind: files::EntryKind) -> brio::core::session_fs::EntryKind {
+    match kind {
+        files::EntryKind::File => brio::core::session_fs::EntryKind::File,
+        files::EntryKind::Directory => brio::core::session_fs::EntryKind::Directory,
+    }
+}
+
+fn to_wit_file_diff(diff: FileDiff) -> brio::core::session_fs::FileDiff {
+    use brio::core::session_fs::ChangeKind as WitChangeKind;
+
+    brio::core::session_fs::FileDiff {
+        path: diff.path.to_string_lossy().into_owned(),
+        old_path: diff.old_path.map(|p| p.to_string_lossy().into_owned()),
+        kind: match diff.kind {
+            ChangeKind::Added => WitChangeKind::Added,
+            ChangeKind::Modified => WitChangeKind::Modified,
+            ChangeKind::Deleted => WitChangeKind::Deleted,
+            ChangeKind::Renamed => WitChangeKind::Renamed,
+        },
+        binary: diff.binary,
+        patch: diff.patch,
+    }
+}
+
+fn to_wit_commit_error(error: CommitError) -> brio::core::session_fs::CommitError {
+    use brio::core::session_fs::{ConflictKind, FileConflict};
+
+    match error {
+        CommitError::Conflict(report) => brio::core::session_fs::CommitError::Conflicts(
+            report
+                .conflicts
+                .into_iter()
+                .map(|c| FileConflict {
+                    path: c.path.to_string_lossy().into_owned(),
+                    kind: match c.kind {
+                        crate::vfs::merge::ConflictKind::Content => ConflictKind::Content,
+                        crate::vfs::merge::ConflictKind::Binary => ConflictKind::Binary,
+                        crate::vfs::merge::ConflictKind::Deleted => ConflictKind::Deleted,
+                    },
+                })
+                .collect(),
+        ),
+        CommitError::Failed(msg) => brio::core::session_fs::CommitError::Failed(msg),
     }
 }
 
 impl brio::core::pub_sub::Host for BrioHostState {
-    fn subscribe(&mut self, topic: String) -> Result<(), String> {
+    async fn subscribe(&mut self, topic: String) -> Result<(), String> {
         // Enforce: only plugins can subscribe
         let plugin_id = self
             .current_plugin_id()
@@ -102,7 +257,11 @@ impl brio::core::pub_sub::Host for BrioHostState {
         Ok(())
     }
 
-    fn publish(&mut self, topic: String, data: brio::core::pub_sub::Payload) -> Result<(), String> {
+    async fn publish(
+        &mut self,
+        topic: String,
+        data: brio::core::pub_sub::Payload,
+    ) -> Result<(), String> {
         // TODO: Enforce "mesh:send" permission here if needed.
 
         let subscribers = self.event_bus().subscribers(&topic);
@@ -123,10 +282,16 @@ impl brio::core::pub_sub::Host for BrioHostState {
         tokio::spawn(async move {
             if let Some(registry) = state.plugin_registry() {
                 let engine = registry.engine();
-                let runner = crate::engine::runner::AgentRunner::new(engine.clone());
 
                 for agent_id in subscribers {
-                    if let Some(metadata) = registry.get(&agent_id) {
+                    let handles_events = registry
+                        .get(&agent_id)
+                        .is_some_and(|m| m.world.handles_events());
+                    if handles_events {
+                        let runner = crate::engine::runner::AgentRunner::new(engine.clone())
+                            .with_budget(registry.cpu_budget(&agent_id))
+                            .with_limits(registry.resource_limits(&agent_id));
+
                         // Clone data for each subscriber
                         let payload_clone = match &payload_enum {
                             crate::engine::runner::EventPayload::Json(s) => {
@@ -138,15 +303,21 @@ impl brio::core::pub_sub::Host for BrioHostState {
                         };
 
                         // Run
-                        if let Err(e) = runner
-                            .run_event_handler(
-                                &metadata.path,
-                                state.clone(),
-                                topic.clone(),
-                                payload_clone,
-                            )
-                            .await
-                        {
+                        let delivery = async {
+                            let lease = registry.acquire(&agent_id)?;
+                            runner
+                                .run_event_handler(
+                                    &lease.pre,
+                                    state.with_plugin_context(
+                                        lease.metadata.id.clone(),
+                                        lease.metadata.permissions.clone(),
+                                    ),
+                                    topic.clone(),
+                                    payload_clone,
+                                )
+                                .await
+                        };
+                        if let Err(e) = delivery.await {
                             tracing::error!(
                                 "Failed to deliver event '{}' to agent '{}': {}",
                                 topic,
@@ -163,73 +334,285 @@ impl brio::core::pub_sub::Host for BrioHostState {
     }
 }
 
-impl brio::core::inference::Host for BrioHostState {
-    fn chat(
+impl brio::ai::inference::Host for BrioHostState {
+    async fn chat(
         &mut self,
         model: String,
-        messages: Vec<brio::core::inference::Message>,
-    ) -> Result<brio::core::inference::CompletionResponse, brio::core::inference::InferenceError>
-    {
+        messages: Vec<brio::ai::inference::Message>,
+    ) -> Result<brio::ai::inference::CompletionResponse, brio::ai::inference::InferenceError> {
+        let options = default_chat_options();
+        brio::ai::inference::Host::chat_with_options(self, model, messages, options).await
+    }
+
+    async fn chat_with_options(
+        &mut self,
+        model: String,
+        messages: Vec<brio::ai::inference::Message>,
+        options: brio::ai::inference::ChatOptions,
+    ) -> Result<brio::ai::inference::CompletionResponse, brio::ai::inference::InferenceError> {
         if let Err(e) = self.check_permission("ai:inference") {
-            return Err(brio::core::inference::InferenceError::ProviderError(e));
+            return Err(brio::ai::inference::InferenceError::ProviderError(e));
         }
 
-        use crate::inference::{ChatRequest, Message, Role};
-
-        // Convert WASM messages to internal messages
-        let internal_messages: Vec<Message> = messages
-            .into_iter()
-            .map(|m| Message {
-                role: match m.role {
-                    brio::core::inference::Role::System => Role::System,
-                    brio::core::inference::Role::User => Role::User,
-                    brio::core::inference::Role::Assistant => Role::Assistant,
+        let request = to_internal_request(model, messages, options)?;
+
+        self.chat_with_report(request)
+            .await
+            .map(
+                |(response, report)| brio::ai::inference::CompletionResponse {
+                    content: response.content,
+                    usage: response.usage.map(to_wit_usage),
+                    tool_calls: to_wit_tool_calls(response.tool_calls),
+                    context: report.map(|r| brio::ai::inference::ContextReport {
+                        dropped_messages: r.dropped_messages,
+                        dropped_tokens: r.dropped_tokens,
+                        summary: r.summary,
+                        retried: r.retried,
+                    }),
                 },
-                content: m.content,
-            })
-            .collect();
+            )
+            .map_err(to_wit_error)
+    }
 
-        let request = ChatRequest {
-            model,
-            messages: internal_messages,
-        };
+    async fn stream_chat(
+        &mut self,
+        model: String,
+        messages: Vec<brio::ai::inference::Message>,
+    ) -> Result<Resource<CompletionStream>, brio::ai::inference::InferenceError> {
+        let options = default_chat_options();
+        brio::ai::inference::Host::stream_chat_with_options(self, model, messages, options).await
+    }
 
-        let inference_provider = match self.inference() {
-            Some(provider) => provider,
-            None => {
-                return Err(brio::core::inference::InferenceError::ProviderError(
-                    "No default inference provider configured".to_string(),
-                ));
-            }
-        };
-        let result = tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current()
-                .block_on(async { inference_provider.chat(request).await })
-        });
+    async fn stream_chat_with_options(
+        &mut self,
+        model: String,
+        messages: Vec<brio::ai::inference::Message>,
+        options: brio::ai::inference::ChatOptions,
+    ) -> Result<Resource<CompletionStream>, brio::ai::inference::InferenceError> {
+        if let Err(e) = self.check_permission("ai:inference") {
+            return Err(brio::ai::inference::InferenceError::ProviderError(e));
+        }
 
-        result
-            .map(|response| brio::core::inference::CompletionResponse {
-                content: response.content,
-                usage: response.usage.map(|u| brio::core::inference::Usage {
-                    prompt_tokens: u.prompt_tokens,
-                    completion_tokens: u.completion_tokens,
-                    total_tokens: u.total_tokens,
-                }),
+        let request = to_internal_request(model, messages, options)?;
+        let stream = self.chat_stream(request).await.map_err(to_wit_error)?;
+
+        self.resources()
+            .push(CompletionStream(stream))
+            .map_err(|e| brio::ai::inference::InferenceError::ProviderError(e.to_string()))
+    }
+
+    async fn embed(
+        &mut self,
+        model: String,
+        inputs: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>, brio::ai::inference::InferenceError> {
+        if let Err(e) = self.check_permission("ai:inference") {
+            return Err(brio::ai::inference::InferenceError::ProviderError(e));
+        }
+
+        let request = crate::inference::EmbeddingRequest::new(model, inputs);
+        BrioHostState::embed(self, request)
+            .await
+            .map(|response| response.embeddings)
+            .map_err(to_wit_error)
+    }
+}
+
+impl brio::core::planner::Host for BrioHostState {
+    async fn decompose(&mut self, objective: String) -> Result<brio::core::planner::Plan, String> {
+        self.check_permission("ai:inference")?;
+
+        let plan = BrioHostState::decompose(self, &objective)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(brio::core::planner::Plan {
+            steps: plan
+                .steps
+                .into_iter()
+                .map(|s| brio::core::planner::Subtask {
+                    id: s.id,
+                    description: s.description,
+                })
+                .collect(),
+        })
+    }
+}
+
+/// A guest's handle on a streamed completion.
+pub struct CompletionStream(ChatStream);
+
+impl brio::ai::inference::HostCompletionStream for BrioHostState {
+    async fn next(
+        &mut self,
+        stream: Resource<CompletionStream>,
+    ) -> Result<Option<brio::ai::inference::ChatDelta>, brio::ai::inference::InferenceError> {
+        let stream = self
+            .resources()
+            .get_mut(&stream)
+            .map_err(|e| brio::ai::inference::InferenceError::ProviderError(e.to_string()))?;
+
+        match stream.0.next().await {
+            Some(Ok(delta)) => Ok(Some(brio::ai::inference::ChatDelta {
+                content: delta.content,
+                usage: delta.usage.map(to_wit_usage),
+                tool_calls: to_wit_tool_calls(delta.tool_calls),
+            })),
+            Some(Err(e)) => Err(to_wit_error(e)),
+            None => Ok(None),
+        }
+    }
+
+    async fn drop(&mut self, stream: Resource<CompletionStream>) -> wasmtime::Result<()> {
+        self.resources().delete(stream)?;
+        Ok(())
+    }
+}
+
+fn to_wit_usage(u: crate::inference::Usage) -> brio::ai::inference::Usage {
+    brio::ai::inference::Usage {
+        prompt_tokens: u.prompt_tokens,
+        completion_tokens: u.completion_tokens,
+        total_tokens: u.total_tokens,
+    }
+}
+
+fn to_wit_error(e: crate::inference::InferenceError) -> brio::ai::inference::InferenceError {
+    match e {
+        crate::inference::InferenceError::RateLimit => {
+            brio::ai::inference::InferenceError::RateLimit
+        }
+        crate::inference::InferenceError::ContextLengthExceeded => {
+            brio::ai::inference::InferenceError::ContextLengthExceeded
+        }
+        crate::inference::InferenceError::ProviderNotFound(msg) => {
+            brio::ai::inference::InferenceError::ProviderNotFound(msg)
+        }
+        crate::inference::InferenceError::ModelNotAllowed(msg) => {
+            brio::ai::inference::InferenceError::ModelNotAllowed(msg)
+        }
+        crate::inference::InferenceError::BudgetExceeded(msg) => {
+            brio::ai::inference::InferenceError::BudgetExceeded(msg)
+        }
+        crate::inference::InferenceError::InvalidResponse(msg) => {
+            brio::ai::inference::InferenceError::InvalidResponse(msg)
+        }
+        other => brio::ai::inference::InferenceError::ProviderError(other.to_string()),
+    }
+}
+
+fn to_wit_tool_calls(calls: Vec<crate::inference::ToolCall>) -> Vec<brio::ai::inference::ToolCall> {
+    calls
+        .into_iter()
+        .map(|c| brio::ai::inference::ToolCall {
+            id: c.id,
+            name: c.name,
+            arguments: c.arguments,
+        })
+        .collect()
+}
+
+/// Options of a plain `chat`: no tools, provider defaults.
+fn default_chat_options() -> brio::ai::inference::ChatOptions {
+    brio::ai::inference::ChatOptions {
+        tools: Vec::new(),
+        temperature: None,
+        top_p: None,
+        stop: Vec::new(),
+        max_tokens: None,
+        response_format: None,
+    }
+}
+
+/// Builds a request from guest arguments; tool and response schemas must be
+/// valid JSON.
+fn to_internal_request(
+    model: String,
+    messages: Vec<brio::ai::inference::Message>,
+    options: brio::ai::inference::ChatOptions,
+) -> Result<ChatRequest, brio::ai::inference::InferenceError> {
+    let tools = options
+        .tools
+        .into_iter()
+        .map(|t| {
+            let parameters = serde_json::from_str(&t.parameters).map_err(|e| {
+                brio::ai::inference::InferenceError::ProviderError(format!(
+                    "Invalid parameter schema for tool '{}': {}",
+                    t.name, e
+                ))
+            })?;
+            Ok(crate::inference::ToolDefinition {
+                name: t.name,
+                description: t.description,
+                parameters,
             })
-            .map_err(|e| match e {
-                crate::inference::InferenceError::RateLimit => {
-                    brio::core::inference::InferenceError::RateLimit
-                }
-                crate::inference::InferenceError::ContextLengthExceeded => {
-                    brio::core::inference::InferenceError::ContextLengthExceeded
-                }
-                other => brio::core::inference::InferenceError::ProviderError(other.to_string()),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut request = ChatRequest::new(model, to_internal_messages(messages))
+        .with_tools(tools)
+        .with_stop(options.stop);
+    request.temperature = options.temperature;
+    request.top_p = options.top_p;
+    request.max_tokens = options.max_tokens;
+    request.response_format = options
+        .response_format
+        .map(to_internal_response_format)
+        .transpose()?;
+    Ok(request)
+}
+
+fn to_internal_response_format(
+    format: brio::ai::inference::ResponseFormat,
+) -> Result<crate::inference::ResponseFormat, brio::ai::inference::InferenceError> {
+    match format {
+        brio::ai::inference::ResponseFormat::Json => Ok(crate::inference::ResponseFormat::Json),
+        brio::ai::inference::ResponseFormat::JsonSchema(f) => {
+            let schema = serde_json::from_str(&f.schema).map_err(|e| {
+                brio::ai::inference::InferenceError::ProviderError(format!(
+                    "Invalid response schema '{}': {}",
+                    f.name, e
+                ))
+            })?;
+            Ok(crate::inference::ResponseFormat::JsonSchema {
+                name: f.name,
+                schema,
             })
+        }
     }
 }
 
+fn to_internal_messages(
+    messages: Vec<brio::ai::inference::Message>,
+) -> Vec<crate::inference::Message> {
+    use crate::inference::{Message, Role, ToolCall};
+
+    messages
+        .into_iter()
+        .map(|m| Message {
+            role: match m.role {
+                brio::ai::inference::Role::System => Role::System,
+                brio::ai::inference::Role::User => Role::User,
+                brio::ai::inference::Role::Assistant => Role::Assistant,
+                brio::ai::inference::Role::Tool => Role::Tool,
+            },
+            content: m.content,
+            tool_calls: m
+                .tool_calls
+                .into_iter()
+                .map(|c| ToolCall {
+                    id: c.id,
+                    name: c.name,
+                    arguments: c.arguments,
+                })
+                .collect(),
+            tool_call_id: m.tool_call_id,
+        })
+        .collect()
+}
+
 impl brio::core::logging::Host for BrioHostState {
-    fn log(&mut self, level: brio::core::logging::Level, context: String, message: String) {
+    async fn log(&mut self, level: brio::core::logging::Level, context: String, message: String) {
         tracing::info!(
             target: "wasm_guest",
             level = %LogLevel(level),
@@ -254,6 +637,32 @@ impl std::fmt::Display for LogLevel {
     }
 }
 
+/// Host interfaces registered by `create_linker`, without version suffix.
+pub const HOST_INTERFACES: &[&str] = &[
+    "brio:core/service-mesh",
+    "brio:core/sql-state",
+    "brio:core/session-fs",
+    "brio:ai/inference",
+    "brio:core/logging",
+    "brio:core/planner",
+    "brio:core/pub-sub",
+];
+
+/// WASI Preview 2 packages linked in full by `create_linker`.
+const WASI_PACKAGES: &[&str] = &[
+    "wasi:cli/",
+    "wasi:clocks/",
+    "wasi:filesystem/",
+    "wasi:io/",
+    "wasi:random/",
+    "wasi:sockets/",
+];
+
+/// Returns true if the linker defines the (unversioned) import `name`.
+pub fn provides_import(name: &str) -> bool {
+    HOST_INTERFACES.contains(&name) || WASI_PACKAGES.iter().any(|p| name.starts_with(p))
+}
+
 pub fn create_linker(engine: &Engine) -> Result<Linker<BrioHostState>> {
     let mut linker = Linker::new(engine);
     register_host_interfaces(&mut linker)?;
@@ -270,6 +679,10 @@ pub fn create_engine_config() -> Config {
     config.async_stack_size(8 * 1024 * 1024); // 8 MiB
     config.memory_reservation(4 * 1024 * 1024 * 1024); // 4 GiB static memory limits
 
+    // Security Hardening: CPU Budgets (armed per store, see `engine::budget`)
+    config.consume_fuel(true);
+    config.epoch_interruption(true);
+
     config
 }
 
@@ -279,9 +692,13 @@ fn register_host_interfaces(linker: &mut Linker<BrioHostState>) -> Result<()> {
     brio::core::service_mesh::add_to_linker::<BrioHostState, State>(linker, |s| s)?;
     brio::core::sql_state::add_to_linker::<BrioHostState, State>(linker, |s| s)?;
     brio::core::session_fs::add_to_linker::<BrioHostState, State>(linker, |s| s)?;
-    brio::core::inference::add_to_linker::<BrioHostState, State>(linker, |s| s)?;
+    brio::ai::inference::add_to_linker::<BrioHostState, State>(linker, |s| s)?;
     brio::core::logging::add_to_linker::<BrioHostState, State>(linker, |s| s)?;
+    brio::core::planner::add_to_linker::<BrioHostState, State>(linker, |s| s)?;
     brio::core::pub_sub::add_to_linker::<BrioHostState, State>(linker, |s| s)?;
 
+    // WASI Preview 2, scoped per store by `BrioHostState`'s `WasiView`
+    wasmtime_wasi::p2::add_to_linker_async(linker)?;
+
     Ok(())
 }
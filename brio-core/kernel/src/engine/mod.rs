@@ -1,7 +1,13 @@
+pub mod budget;
+pub mod cache;
+pub mod limits;
 pub mod linker;
 pub mod runner;
 pub mod runtime;
+pub mod wasi;
 
+pub use budget::{BudgetExhausted, CpuBudget, GuestFailed};
+pub use limits::ResourceLimits;
 pub use linker::{create_engine_config, create_linker};
 pub use runtime::WasmEngine;
 
@@ -15,6 +21,12 @@ wasmtime::component::bindgen!({
                 binary(list<u8>)
             }
             call: func(target: string, method: string, args: payload) -> result<payload, string>;
+
+            record metered-reply {
+                payload: payload,
+                fuel-consumed: option<u64>
+            }
+            call-metered: func(target: string, method: string, args: payload) -> result<metered-reply, string>;
         }
 
         interface sql-state {
@@ -28,16 +40,22 @@ wasmtime::component::bindgen!({
 
         interface session-fs {
             begin-session: func(base-path: string) -> result<string, string>;
-            commit-session: func(session-id: string) -> result<tuple<>, string>;
-        }
-
-        interface inference {
-             variant role { system, user, assistant }
-             record message { role: role, content: string }
-             record usage { prompt-tokens: u32, completion-tokens: u32, total-tokens: u32 }
-             record completion-response { content: string, usage: option<usage> }
-             variant inference-error { provider-error(string), rate-limit, context-length-exceeded }
-             chat: func(model: string, messages: list<message>) -> result<completion-response, inference-error>;
+            enum conflict-kind { content, binary, deleted }
+            record file-conflict { path: string, kind: conflict-kind }
+            variant commit-error { conflicts(list<file-conflict>), failed(string) }
+            commit-session: func(session-id: string) -> result<_, commit-error>;
+            enum change-kind { added, modified, deleted, renamed }
+            record file-diff { path: string, old-path: option<string>, kind: change-kind, binary: bool, patch: string }
+            diff: func(session-id: string) -> result<list<file-diff>, string>;
+            enum entry-kind { file, directory }
+            record file-stat { kind: entry-kind, size: u64, modified: option<u64> }
+            record dir-entry { name: string, kind: entry-kind, size: u64 }
+            read-file: func(session-id: string, path: string) -> result<list<u8>, string>;
+            write-file: func(session-id: string, path: string, contents: list<u8>) -> result<_, string>;
+            list-dir: func(session-id: string, path: string) -> result<list<dir-entry>, string>;
+            stat: func(session-id: string, path: string) -> result<option<file-stat>, string>;
+            delete: func(session-id: string, path: string) -> result<_, string>;
+            rename: func(session-id: string, old-path: string, new-path: string) -> result<_, string>;
         }
 
         interface logging {
@@ -45,19 +63,57 @@ wasmtime::component::bindgen!({
             log: func(level: level, context: string, message: string);
         }
 
+        interface planner {
+            record subtask { id: string, description: string }
+            record plan { steps: list<subtask> }
+            decompose: func(objective: string) -> result<plan, string>;
+        }
+
         interface pub-sub {
              use service-mesh.{payload};
-             subscribe: func(topic: string) -> result<tuple<>, string>;
-             publish: func(topic: string, data: payload) -> result<tuple<>, string>;
+             subscribe: func(topic: string) -> result<_, string>;
+             publish: func(topic: string, data: payload) -> result<_, string>;
         }
 
         world brio-host {
             import service-mesh;
             import sql-state;
             import session-fs;
-            import inference;
+            import brio:ai/inference;
             import logging;
+            import planner;
             import pub-sub;
         }
+
+        package brio:ai {
+            interface inference {
+                variant role { system, user, assistant, tool }
+                record tool-call { id: string, name: string, arguments: string }
+                record message { role: role, content: string, tool-calls: list<tool-call>, tool-call-id: option<string> }
+                record tool-definition { name: string, description: string, parameters: string }
+                record json-schema-format { name: string, schema: string }
+                variant response-format { json, json-schema(json-schema-format) }
+                record chat-options { tools: list<tool-definition>, temperature: option<f32>, top-p: option<f32>, stop: list<string>, max-tokens: option<u32>, response-format: option<response-format> }
+                record usage { prompt-tokens: u32, completion-tokens: u32, total-tokens: u32 }
+                record context-report { dropped-messages: u32, dropped-tokens: u32, summary: option<string>, retried: bool }
+                record completion-response { content: string, usage: option<usage>, tool-calls: list<tool-call>, context: option<context-report> }
+                variant inference-error { provider-error(string), rate-limit, context-length-exceeded, provider-not-found(string), model-not-allowed(string), budget-exceeded(string), invalid-response(string) }
+                chat: func(model: string, messages: list<message>) -> result<completion-response, inference-error>;
+                chat-with-options: func(model: string, messages: list<message>, options: chat-options) -> result<completion-response, inference-error>;
+
+                record chat-delta { content: string, usage: option<usage>, tool-calls: list<tool-call> }
+                resource completion-stream {
+                    next: func() -> result<option<chat-delta>, inference-error>;
+                }
+                stream-chat: func(model: string, messages: list<message>) -> result<completion-stream, inference-error>;
+                stream-chat-with-options: func(model: string, messages: list<message>, options: chat-options) -> result<completion-stream, inference-error>;
+
+                embed: func(model: string, inputs: list<string>) -> result<list<list<f32>>, inference-error>;
+            }
+        }
     "#,
+    imports: { default: async },
+    with: {
+        "brio:ai/inference.completion-stream": crate::engine::linker::CompletionStream,
+    },
 });
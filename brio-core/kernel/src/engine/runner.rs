@@ -1,6 +1,9 @@
+use crate::engine::budget::{CpuBudget, GuestFailed, Metered};
+use crate::engine::limits::ResourceLimits;
 use crate::host::BrioHostState;
-use anyhow::{Context, Result};
-use wasmtime::component::Component;
+use crate::planner::{Plan, Subtask};
+use anyhow::Result;
+use wasmtime::component::InstancePre;
 use wasmtime::{Engine, Store};
 
 // Define the world binding for calling the agent
@@ -34,67 +37,184 @@ wasmtime::component::bindgen!({
         }
     "#,
     world: "smart-agent",
+    exports: { default: async },
     additional_derives: [serde::Deserialize, serde::Serialize],
 });
 
+/// Bindings for calling the `planner` exported by a planner plugin.
+mod planner_plugin {
+    wasmtime::component::bindgen!({
+        inline: r#"
+            package brio:core;
+
+            interface planner {
+                record subtask { id: string, description: string }
+                record plan { steps: list<subtask> }
+                decompose: func(objective: string) -> result<plan, string>;
+            }
+
+            world planner-plugin {
+                export planner;
+            }
+        "#,
+        world: "planner-plugin",
+        exports: { default: async },
+    });
+}
+
 pub type SmartAgentInstance = SmartAgent;
 pub use exports::brio::core::agent_runner::TaskContext;
 pub use exports::brio::core::event_handler::Payload as EventPayload;
 
 pub struct AgentRunner {
     engine: Engine,
+    budget: CpuBudget,
+    limits: ResourceLimits,
 }
 
 impl AgentRunner {
     pub fn new(engine: Engine) -> Self {
-        Self { engine }
+        Self {
+            engine,
+            budget: CpuBudget::unlimited(),
+            limits: ResourceLimits::unlimited(),
+        }
     }
 
-    /// Instantiates an agent component and runs it.
-    pub async fn run_agent(
-        &self,
-        component_path: &std::path::Path,
-        host_state: BrioHostState,
-        context: exports::brio::core::agent_runner::TaskContext,
-    ) -> Result<String> {
-        let component = Component::from_file(&self.engine, component_path)
-            .context("Failed to load component")?;
-
-        let linker = crate::engine::linker::create_linker(&self.engine)?;
+    /// Sets the CPU budget applied to each invocation.
+    pub fn with_budget(mut self, budget: CpuBudget) -> Self {
+        self.budget = budget;
+        self
+    }
 
-        // We need to resolve the `smart-agent` world.
-        // The bindings generated by bindgen!["smart-agent"] provide a `SmartAgent::instantiate_async`.
+    /// Sets the memory and table limits applied to each invocation.
+    pub fn with_limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
 
-        let mut store = Store::new(&self.engine, host_state);
+    /// Creates a store bounded by this runner's budget and limits.
+    fn new_store(&self, host_state: BrioHostState) -> Result<Store<BrioHostState>> {
+        let mut store = Store::new(&self.engine, host_state.with_resource_limits(self.limits));
+        store.data_mut().init_wasi()?;
+        store.limiter(|state| state.limiter_mut());
+        self.budget.apply(&mut store)?;
+        Ok(store)
+    }
 
-        let agent = SmartAgent::instantiate_async(&mut store, &component, &linker).await?;
+    /// Instantiates the agent, reporting instance limit violations.
+    async fn instantiate(
+        &self,
+        store: &mut Store<BrioHostState>,
+        pre: &InstancePre<BrioHostState>,
+    ) -> Result<SmartAgent> {
+        let pre = SmartAgentPre::new(pre.clone())?;
+        let result = pre.instantiate_async(&mut *store).await;
+        if let Err(e) = &result {
+            store.data().limiter().audit_instantiation_error(e);
+        }
+        result
+    }
 
-        let result = agent
-            .brio_core_agent_runner()
-            .call_run(&mut store, &context)?;
+    /// Instantiates a pre-linked agent component and runs it.
+    /// `pre` comes from `PluginRegistry::instance_pre`, so no compilation happens here.
+    pub async fn run_agent(
+        &self,
+        pre: &InstancePre<BrioHostState>,
+        host_state: BrioHostState,
+        context: exports::brio::core::agent_runner::TaskContext,
+    ) -> Result<Metered<String>> {
+        let mut store = self.new_store(host_state)?;
+
+        let result = async {
+            let agent = self.instantiate(&mut store, pre).await?;
+            agent
+                .brio_core_agent_runner()
+                .call_run(&mut store, &context)
+                .await
+        }
+        .await;
+
+        let fuel_consumed = self.budget.consumed(&store);
+        let result = result.map_err(|e| self.budget.classify(e, fuel_consumed))?;
+
+        result
+            .map(|output| Metered {
+                output,
+                fuel_consumed,
+            })
+            .map_err(|message| {
+                GuestFailed {
+                    message,
+                    fuel_consumed,
+                }
+                .into()
+            })
+    }
 
-        result.map_err(|e| anyhow::anyhow!("Agent execution failed: {}", e))
+    /// Calls a planner plugin's exported `decompose`.
+    pub async fn run_planner(
+        &self,
+        pre: &InstancePre<BrioHostState>,
+        host_state: BrioHostState,
+        objective: &str,
+    ) -> Result<Metered<Result<Plan, String>>> {
+        let mut store = self.new_store(host_state)?;
+
+        let result = async {
+            let indices = planner_plugin::exports::brio::core::planner::GuestIndices::new(pre)?;
+            let instance = pre.instantiate_async(&mut store).await?;
+            indices
+                .load(&mut store, &instance)?
+                .call_decompose(&mut store, objective)
+                .await
+        }
+        .await;
+
+        let fuel_consumed = self.budget.consumed(&store);
+        let output = result.map_err(|e| self.budget.classify(e, fuel_consumed))?;
+
+        Ok(Metered {
+            output: output.map(|plan| Plan {
+                steps: plan
+                    .steps
+                    .into_iter()
+                    .map(|s| Subtask {
+                        id: s.id,
+                        description: s.description,
+                    })
+                    .collect(),
+            }),
+            fuel_consumed,
+        })
     }
 
     pub async fn run_event_handler(
         &self,
-        component_path: &std::path::Path,
+        pre: &InstancePre<BrioHostState>,
         host_state: BrioHostState,
         topic: String,
         payload: exports::brio::core::event_handler::Payload,
-    ) -> Result<()> {
-        let component = Component::from_file(&self.engine, component_path)
-            .context("Failed to load component")?;
-
-        let linker = crate::engine::linker::create_linker(&self.engine)?;
-        let mut store = Store::new(&self.engine, host_state);
-
-        let agent = SmartAgent::instantiate_async(&mut store, &component, &linker).await?;
+    ) -> Result<Metered<()>> {
+        let mut store = self.new_store(host_state)?;
+
+        // Resolved on its own so event-handler-only plugins work too
+        let result = async {
+            let indices = exports::brio::core::event_handler::GuestIndices::new(pre)?;
+            let instance = pre.instantiate_async(&mut store).await?;
+            indices
+                .load(&mut store, &instance)?
+                .call_handle_event(&mut store, &topic, &payload)
+                .await
+        }
+        .await;
 
-        agent
-            .brio_core_event_handler()
-            .call_handle_event(&mut store, &topic, &payload)?;
+        let fuel_consumed = self.budget.consumed(&store);
+        result.map_err(|e| self.budget.classify(e, fuel_consumed))?;
 
-        Ok(())
+        Ok(Metered {
+            output: (),
+            fuel_consumed,
+        })
     }
 }
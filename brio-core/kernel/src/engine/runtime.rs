@@ -1,3 +1,5 @@
+use crate::engine::budget::CpuBudget;
+use crate::engine::limits::ResourceLimits;
 use crate::host::BrioHostState;
 use anyhow::{Context, Result};
 use wasmtime::component::{Component, Linker};
@@ -6,12 +8,31 @@ use wasmtime::{Engine, Store};
 pub struct WasmEngine {
     engine: Engine,
     linker: Linker<BrioHostState>,
+    budget: CpuBudget,
+    limits: ResourceLimits,
 }
 
 impl WasmEngine {
     pub fn new(linker: Linker<BrioHostState>) -> Result<Self> {
         let engine = linker.engine().clone();
-        Ok(Self { engine, linker })
+        Ok(Self {
+            engine,
+            linker,
+            budget: CpuBudget::unlimited(),
+            limits: ResourceLimits::unlimited(),
+        })
+    }
+
+    /// Sets the CPU budget applied to every prepared store.
+    pub fn with_budget(mut self, budget: CpuBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Sets the memory and table limits applied to every prepared store.
+    pub fn with_limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = limits;
+        self
     }
 
     pub fn load_component(&self, path: &std::path::Path) -> Result<Component> {
@@ -19,8 +40,13 @@ impl WasmEngine {
             .with_context(|| format!("Failed to load component from {:?}", path))
     }
 
-    pub fn prepare_store(&self, state: BrioHostState) -> Store<BrioHostState> {
-        Store::new(&self.engine, state)
+    /// Creates a store armed with this engine's CPU budget and resource limits.
+    pub fn prepare_store(&self, state: BrioHostState) -> Result<Store<BrioHostState>> {
+        let mut store = Store::new(&self.engine, state.with_resource_limits(self.limits));
+        store.data_mut().init_wasi()?;
+        store.limiter(|state| state.limiter_mut());
+        self.budget.apply(&mut store)?;
+        Ok(store)
     }
 
     pub fn linker(&self) -> &Linker<BrioHostState> {
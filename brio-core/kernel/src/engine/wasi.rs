@@ -0,0 +1,265 @@
+//! WASI Preview 2 context for plugin stores.
+//!
+//! Guests get clocks and random unconditionally. Filesystem access is limited
+//! to the active session directory and network access is denied unless the
+//! plugin holds the matching permission. Guest stdout/stderr are forwarded to
+//! tracing instead of the kernel's own stdio.
+
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use tokio::io::AsyncWrite;
+use wasmtime::component::ResourceTable;
+use wasmtime_wasi::cli::{IsTerminal, StdoutStream};
+use wasmtime_wasi::{DirPerms, FilePerms, WasiCtx, WasiCtxBuilder};
+
+/// Guest path under which the session directory is preopened.
+pub const SESSION_GUEST_PATH: &str = ".";
+
+/// Grants read-only access to the preopened session directory.
+pub const PERMISSION_FS_READ: &str = "fs:read";
+/// Grants read-write access to the preopened session directory.
+pub const PERMISSION_FS_WRITE: &str = "fs:write";
+/// Grants outbound network access and DNS lookups.
+pub const PERMISSION_NET: &str = "net:outbound";
+
+/// WASI state owned by a single store.
+pub struct WasiState {
+    pub ctx: WasiCtx,
+    pub table: ResourceTable,
+}
+
+impl WasiState {
+    /// Builds the context for `plugin_id`, preopening `session_dir` when the
+    /// permissions allow filesystem access.
+    pub fn build(
+        plugin_id: &str,
+        permissions: &std::collections::HashSet<String>,
+        session_dir: Option<&Path>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            ctx: Self::build_ctx(plugin_id, permissions, session_dir)?,
+            table: ResourceTable::new(),
+        })
+    }
+
+    /// The context alone, for replacing that of an existing state.
+    pub fn build_ctx(
+        plugin_id: &str,
+        permissions: &std::collections::HashSet<String>,
+        session_dir: Option<&Path>,
+    ) -> anyhow::Result<WasiCtx> {
+        let mut builder = WasiCtxBuilder::new();
+        builder
+            .stdout(TracingOutput::new(plugin_id, "stdout"))
+            .stderr(TracingOutput::new(plugin_id, "stderr"));
+
+        if let Some(dir) = session_dir {
+            let perms = if permissions.contains(PERMISSION_FS_WRITE) {
+                Some((DirPerms::all(), FilePerms::all()))
+            } else if permissions.contains(PERMISSION_FS_READ) {
+                Some((DirPerms::READ, FilePerms::READ))
+            } else {
+                None
+            };
+            if let Some((dir_perms, file_perms)) = perms {
+                builder.preopened_dir(dir, SESSION_GUEST_PATH, dir_perms, file_perms)?;
+            }
+        }
+
+        if permissions.contains(PERMISSION_NET) {
+            builder.inherit_network().allow_ip_name_lookup(true);
+        }
+
+        Ok(builder.build())
+    }
+}
+
+/// Holds a store's [`WasiState`], built on first use.
+///
+/// Cloning yields an empty slot: WASI resources belong to exactly one store,
+/// so a cloned host state builds its own context. The mutex only provides
+/// `Sync` for the host state and is never locked, access goes through `get_mut`.
+#[derive(Default)]
+pub struct WasiSlot(Option<Mutex<WasiState>>);
+
+impl WasiSlot {
+    /// The built state, building it with `init` on first use.
+    pub fn get_or_init(
+        &mut self,
+        init: impl FnOnce() -> anyhow::Result<WasiState>,
+    ) -> anyhow::Result<&mut WasiState> {
+        if self.0.is_none() {
+            self.0 = Some(Mutex::new(init()?));
+        }
+        Ok(self
+            .0
+            .as_mut()
+            .expect("WASI state initialized")
+            .get_mut()
+            .expect("Mutex poisoned"))
+    }
+
+    /// Swaps the context of a built state, keeping its resource table so the
+    /// guest's open handles stay valid. Does nothing before first use.
+    pub fn replace_ctx(&mut self, ctx: WasiCtx) {
+        if let Some(state) = self.0.as_mut() {
+            state.get_mut().expect("Mutex poisoned").ctx = ctx;
+        }
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+impl Clone for WasiSlot {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+/// Guest output stream forwarded to tracing, one event per line.
+#[derive(Clone)]
+struct TracingOutput {
+    plugin_id: String,
+    stream: &'static str,
+}
+
+impl TracingOutput {
+    fn new(plugin_id: &str, stream: &'static str) -> Self {
+        Self {
+            plugin_id: plugin_id.to_string(),
+            stream,
+        }
+    }
+}
+
+impl IsTerminal for TracingOutput {
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
+
+impl StdoutStream for TracingOutput {
+    fn async_stream(&self) -> Box<dyn AsyncWrite + Send + Sync> {
+        Box::new(LineWriter {
+            output: self.clone(),
+            buffer: Vec::new(),
+        })
+    }
+}
+
+/// Buffers partial lines until a newline or flush.
+struct LineWriter {
+    output: TracingOutput,
+    buffer: Vec<u8>,
+}
+
+impl LineWriter {
+    fn emit(&self, line: &[u8]) {
+        let line = String::from_utf8_lossy(line);
+        tracing::info!(
+            target: "wasm_guest",
+            plugin_id = %self.output.plugin_id,
+            stream = self.output.stream,
+            "{}",
+            line.trim_end_matches('\r')
+        );
+    }
+
+    fn emit_complete_lines(&mut self) {
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            self.emit(&line[..pos]);
+        }
+    }
+
+    fn emit_remainder(&mut self) {
+        if !self.buffer.is_empty() {
+            let line = std::mem::take(&mut self.buffer);
+            self.emit(&line);
+        }
+    }
+}
+
+impl AsyncWrite for LineWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.buffer.extend_from_slice(buf);
+        self.emit_complete_lines();
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.emit_remainder();
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.emit_remainder();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for LineWriter {
+    fn drop(&mut self) {
+        self.emit_remainder();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn test_line_writer_buffers_partial_lines() -> anyhow::Result<()> {
+        let mut writer = LineWriter {
+            output: TracingOutput::new("agent", "stdout"),
+            buffer: Vec::new(),
+        };
+        writer.write_all(b"hello\nwor").await?;
+        assert_eq!(writer.buffer, b"wor");
+        writer.write_all(b"ld\n").await?;
+        assert!(writer.buffer.is_empty());
+        writer.write_all(b"tail").await?;
+        writer.flush().await?;
+        assert!(writer.buffer.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_slot_clone_is_empty() -> anyhow::Result<()> {
+        let mut slot = WasiSlot::default();
+        slot.get_or_init(|| WasiState::build("agent", &HashSet::new(), None))?;
+        assert!(slot.is_initialized());
+        assert!(!slot.clone().is_initialized());
+        Ok(())
+    }
+
+    #[test]
+    fn test_slot_init_error_is_returned() {
+        let mut slot = WasiSlot::default();
+        let missing = Path::new("/nonexistent/brio-session");
+        let permissions: HashSet<String> = [PERMISSION_FS_READ.to_string()].into();
+        assert!(
+            slot.get_or_init(|| WasiState::build("agent", &permissions, Some(missing)))
+                .is_err()
+        );
+        assert!(!slot.is_initialized());
+    }
+
+    #[test]
+    fn test_build_with_session_dir() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let permissions: HashSet<String> = [PERMISSION_FS_WRITE.to_string()].into();
+        WasiState::build("agent", &permissions, Some(dir.path()))?;
+        Ok(())
+    }
+}
@@ -4,29 +4,70 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::oneshot;
+use tracing::{debug, warn};
 
-use crate::inference::{LLMProvider, ProviderRegistry};
+use crate::engine::limits::{PluginLimiter, ResourceLimits};
+use crate::engine::wasi::{WasiSlot, WasiState};
+use crate::inference::context::{self, ContextManager, ContextReport, ContextStrategy};
+use crate::inference::{
+    ChatRequest, ChatResponse, ChatStream, EmbeddingRequest, EmbeddingResponse, InferenceError,
+    LLMProvider, Message, ProviderRegistry, ResolvedModel, UsageLedger, UsageScope, structured,
+};
+use crate::infrastructure::config::{BudgetSettings, ContextSettings};
 use crate::mesh::events::EventBus;
 use crate::mesh::remote::RemoteRouter;
 use crate::mesh::types::{NodeId, NodeInfo};
 use crate::mesh::{MeshMessage, Payload};
-use crate::registry::PluginRegistry;
-use crate::store::{PrefixPolicy, SqlStore};
+use crate::planner::{InferencePlanner, Plan, Planner, PlannerError};
+use crate::registry::{PluginRegistry, PluginWorld};
+use crate::store::{PrefixPolicy, SqlStore, TableGrant};
+use crate::vfs::diff::FileDiff;
 use crate::vfs::manager::SessionManager;
+use crate::vfs::merge::CommitError;
+use crate::vfs::persistence::SessionStore;
 use crate::ws::{BroadcastMessage, Broadcaster, WsPatch};
 
+/// A mesh call's reply with the fuel spent serving it, known when a local
+/// plugin did.
+#[derive(Debug)]
+pub struct MeshReply {
+    pub payload: Payload,
+    pub fuel_consumed: Option<u64>,
+}
+
+impl MeshReply {
+    fn unmetered(payload: Payload) -> Self {
+        Self {
+            payload,
+            fuel_consumed: None,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct BrioHostState {
     mesh_router: Arc<std::sync::RwLock<HashMap<String, Sender<MeshMessage>>>>,
     remote_router: Option<RemoteRouter>,
     db_pool: SqlitePool,
+    sql_policy: Arc<PrefixPolicy>,
     broadcaster: Broadcaster,
     session_manager: Arc<std::sync::Mutex<SessionManager>>,
+    session_store: SessionStore,
     provider_registry: Arc<ProviderRegistry>,
+    planner: Arc<dyn Planner>,
+    planner_plugin: Option<String>,
+    stream_patches: bool,
+    allowed_models: Arc<HashMap<String, Vec<String>>>,
+    usage: UsageLedger,
+    context: Option<Arc<ContextManager>>,
     permissions: Arc<std::collections::HashSet<String>>,
     plugin_registry: Option<Arc<PluginRegistry>>,
     event_bus: Arc<EventBus>,
     current_plugin_id: Option<String>,
+    current_task_id: Option<String>,
+    limiter: PluginLimiter,
+    active_session: Option<String>,
+    wasi: WasiSlot,
 }
 
 impl BrioHostState {
@@ -38,21 +79,36 @@ impl BrioHostState {
         sandbox: crate::infrastructure::config::SandboxSettings,
     ) -> Result<Self> {
         let pool = SqlitePoolOptions::new().connect(db_url).await?;
+        let usage = UsageLedger::new(pool.clone()).await?;
+        let (session_manager, session_store) = open_sessions(&pool, sandbox).await?;
+        let provider_registry = Arc::new(registry);
 
-        Ok(Self {
+        let state = Self {
             mesh_router: Arc::new(std::sync::RwLock::new(HashMap::new())),
             remote_router: None, // Default to standalone mode
             db_pool: pool,
+            sql_policy: Arc::new(PrefixPolicy::new()),
             broadcaster: Broadcaster::new(),
-            session_manager: Arc::new(std::sync::Mutex::new(
-                SessionManager::new(sandbox).map_err(|e| anyhow!(e))?,
-            )),
-            provider_registry: Arc::new(registry),
+            session_manager: Arc::new(std::sync::Mutex::new(session_manager)),
+            session_store,
+            planner: Arc::new(InferencePlanner::new()),
+            planner_plugin: None,
+            stream_patches: false,
+            allowed_models: Arc::new(HashMap::new()),
+            usage,
+            context: None,
+            provider_registry,
             permissions: Arc::new(std::collections::HashSet::new()),
             plugin_registry,
             event_bus: Arc::new(EventBus::new()),
             current_plugin_id: None,
-        })
+            current_task_id: None,
+            limiter: PluginLimiter::default(),
+            active_session: None,
+            wasi: WasiSlot::default(),
+        };
+        state.subscribe_plugin_topics();
+        Ok(state)
     }
 
     /// Creates a new BrioHostState with distributed mesh support
@@ -64,22 +120,37 @@ impl BrioHostState {
         sandbox: crate::infrastructure::config::SandboxSettings,
     ) -> Result<Self> {
         let pool = SqlitePoolOptions::new().connect(db_url).await?;
+        let usage = UsageLedger::new(pool.clone()).await?;
+        let (session_manager, session_store) = open_sessions(&pool, sandbox).await?;
+        let provider_registry = Arc::new(registry);
         let remote_router = RemoteRouter::new();
 
-        Ok(Self {
+        let state = Self {
             mesh_router: Arc::new(std::sync::RwLock::new(HashMap::new())),
             remote_router: Some(remote_router),
             db_pool: pool,
+            sql_policy: Arc::new(PrefixPolicy::new()),
             broadcaster: Broadcaster::new(),
-            session_manager: Arc::new(std::sync::Mutex::new(
-                SessionManager::new(sandbox).map_err(|e| anyhow!(e))?,
-            )),
-            provider_registry: Arc::new(registry),
+            session_manager: Arc::new(std::sync::Mutex::new(session_manager)),
+            session_store,
+            planner: Arc::new(InferencePlanner::new()),
+            planner_plugin: None,
+            stream_patches: false,
+            allowed_models: Arc::new(HashMap::new()),
+            usage,
+            context: None,
+            provider_registry,
             permissions: Arc::new(std::collections::HashSet::new()),
             plugin_registry,
             event_bus: Arc::new(EventBus::new()),
             current_plugin_id: None,
-        })
+            current_task_id: None,
+            limiter: PluginLimiter::default(),
+            active_session: None,
+            wasi: WasiSlot::default(),
+        };
+        state.subscribe_plugin_topics();
+        Ok(state)
     }
 
     /// Creates a new BrioHostState with a single provider (backward compatible).
@@ -90,6 +161,65 @@ impl BrioHostState {
         Self::new(db_url, registry, None, Default::default()).await
     }
 
+    /// Grants plugins access to shared tables outside their own SQL scope.
+    pub fn with_shared_tables(mut self, grants: HashMap<String, TableGrant>) -> Self {
+        self.sql_policy = Arc::new(PrefixPolicy::new().with_grants(grants));
+        self
+    }
+
+    /// Replaces the host planner used when no planner plugin is registered.
+    pub fn with_planner(mut self, planner: Arc<dyn Planner>) -> Self {
+        self.planner = planner;
+        self
+    }
+
+    /// Routes `planner::decompose` to the given planner plugin. Without it, the
+    /// first registered planner plugin (by id) is used.
+    pub fn with_planner_plugin(mut self, plugin_id: impl Into<String>) -> Self {
+        self.planner_plugin = Some(plugin_id.into());
+        self
+    }
+
+    /// Broadcasts streamed completion deltas as WS patches under `/inference`.
+    pub fn with_stream_patches(mut self, enabled: bool) -> Self {
+        self.stream_patches = enabled;
+        self
+    }
+
+    /// Restricts plugins to the listed models, as `provider/model` or
+    /// `provider/*`. Plugins without an entry may use any model.
+    pub fn with_allowed_models(mut self, allowed: HashMap<String, Vec<String>>) -> Self {
+        self.allowed_models = Arc::new(allowed);
+        self
+    }
+
+    /// Sets the token budgets checked before each `chat`.
+    pub fn with_token_budgets(mut self, budgets: BudgetSettings) -> Self {
+        self.usage = self.usage.with_budgets(budgets);
+        self
+    }
+
+    /// Enables context-window management of `chat` when `settings.enabled`.
+    pub fn with_context_management(mut self, settings: ContextSettings) -> Self {
+        self.context = settings
+            .enabled
+            .then(|| Arc::new(ContextManager::new(settings)));
+        self
+    }
+
+    /// Subscribes registered plugins to the topics declared in their manifests.
+    fn subscribe_plugin_topics(&self) {
+        let Some(registry) = &self.plugin_registry else {
+            return;
+        };
+        for plugin in registry.list_plugins() {
+            for topic in plugin.subscriptions {
+                debug!(plugin_id = %plugin.id, topic = %topic, "Subscribing plugin from manifest");
+                self.event_bus.subscribe(topic, plugin.id.clone());
+            }
+        }
+    }
+
     pub fn register_component(&self, id: String, sender: Sender<MeshMessage>) {
         let mut router = self.mesh_router.write().expect("RwLock poisoned");
         router.insert(id, sender);
@@ -105,8 +235,14 @@ impl BrioHostState {
         &self.db_pool
     }
 
-    pub fn get_store(&self, _scope: &str) -> SqlStore {
-        SqlStore::new(self.db_pool.clone(), Box::new(PrefixPolicy))
+    pub fn get_store(&self) -> SqlStore {
+        SqlStore::new(self.db_pool.clone(), Box::new(self.sql_policy.clone()))
+    }
+
+    /// The SQL scope of the calling plugin: its tables are prefixed `<plugin_id>_`.
+    pub fn sql_scope(&self) -> Result<&str, String> {
+        self.current_plugin_id()
+            .ok_or_else(|| "SQL access requires a plugin context".to_string())
     }
 
     pub fn broadcaster(&self) -> &Broadcaster {
@@ -120,6 +256,20 @@ impl BrioHostState {
     }
 
     pub async fn mesh_call(&self, target: &str, method: &str, payload: Payload) -> Result<Payload> {
+        self.mesh_call_metered(target, method, payload)
+            .await
+            .map(|reply| reply.payload)
+    }
+
+    /// As `mesh_call`, also reporting the fuel an agent plugin serving the
+    /// call consumed. A failed plugin invocation's error carries its fuel,
+    /// read with [`budget::fuel_consumed`](crate::engine::budget::fuel_consumed).
+    pub async fn mesh_call_metered(
+        &self,
+        target: &str,
+        method: &str,
+        payload: Payload,
+    ) -> Result<MeshReply> {
         // 1. Try local routing first
         let sender = {
             let router = self.mesh_router.read().expect("RwLock poisoned");
@@ -142,7 +292,9 @@ impl BrioHostState {
             let response = reply_rx
                 .await
                 .map_err(|e| anyhow!("Failed to receive reply from target '{}': {}", target, e))?;
-            return response.map_err(|e| anyhow!("Target '{}' returned error: {}", target, e));
+            return response
+                .map(MeshReply::unmetered)
+                .map_err(|e| anyhow!("Target '{}' returned error: {}", target, e));
         }
 
         // 2. Try remote routing if enabled and target is formatted as "node_id/component"
@@ -160,14 +312,17 @@ impl BrioHostState {
                 reply_tx: oneshot::channel().0, // Reply handling is managed by RemoteRouter's request/response flow
             };
 
-            return router.send(&node_id, message).await;
+            return router
+                .send(&node_id, message)
+                .await
+                .map(MeshReply::unmetered);
         }
 
         // 3. Try on-demand plugin execution
         #[allow(clippy::collapsible_if)]
         // Cannot collapse effectively due to dependency on `registry` for engine access
         if let Some(registry) = &self.plugin_registry {
-            if let Some(metadata) = registry.get(target) {
+            if registry.get(target).is_some() {
                 use crate::engine::runner::{AgentRunner, TaskContext};
 
                 let context: TaskContext = match payload {
@@ -176,11 +331,38 @@ impl BrioHostState {
                     _ => return Err(anyhow!("Agents only support JSON payload")),
                 };
 
-                let runner = AgentRunner::new(registry.engine().clone());
-                let result = runner
-                    .run_agent(&metadata.path, self.clone(), context)
-                    .await?;
-                return Ok(Payload::Json(result));
+                // The lease keeps this plugin version alive across a hot reload
+                let lease = registry.acquire(target)?;
+                if !lease.metadata.world.runs_tasks() {
+                    return Err(anyhow!(
+                        "Plugin '{}' is not an agent ({})",
+                        target,
+                        lease.metadata.world
+                    ));
+                }
+                let runner = AgentRunner::new(registry.engine().clone())
+                    .with_budget(registry.cpu_budget(target))
+                    .with_limits(registry.resource_limits(target));
+                let plugin_state = self
+                    .with_plugin_context(
+                        lease.metadata.id.clone(),
+                        lease.metadata.permissions.clone(),
+                    )
+                    .with_task(context.task_id.clone());
+                let result = runner.run_agent(&lease.pre, plugin_state, context).await?;
+
+                debug!(
+                    plugin_id = %target,
+                    fuel_consumed = result.fuel_consumed,
+                    "Agent invocation completed"
+                );
+                metrics::histogram!("brio_plugin_fuel_consumed", "plugin" => target.to_string())
+                    .record(result.fuel_consumed as f64);
+
+                return Ok(MeshReply {
+                    payload: Payload::Json(result.output),
+                    fuel_consumed: Some(result.fuel_consumed),
+                });
             }
         }
 
@@ -190,14 +372,177 @@ impl BrioHostState {
         ))
     }
 
-    pub fn begin_session(&self, base_path: String) -> Result<String, String> {
-        let mut manager = self.session_manager.lock().expect("Mutex poisoned");
-        manager.begin_session(base_path)
+    /// Decomposes an objective with the planner plugin if one is registered,
+    /// otherwise with the host planner.
+    pub async fn decompose(&self, objective: &str) -> Result<Plan, PlannerError> {
+        let Some((registry, plugin_id)) = self.planner_plugin_target() else {
+            return self.planner.decompose(self, objective).await;
+        };
+        let plugin_error =
+            |e: anyhow::Error| PlannerError::Plugin(plugin_id.clone(), format!("{:#}", e));
+
+        let lease = registry.acquire(&plugin_id).map_err(plugin_error)?;
+        if lease.metadata.world != PluginWorld::Planner {
+            return Err(plugin_error(anyhow!(
+                "not a planner ({})",
+                lease.metadata.world
+            )));
+        }
+        let runner = crate::engine::runner::AgentRunner::new(registry.engine().clone())
+            .with_budget(registry.cpu_budget(&plugin_id))
+            .with_limits(registry.resource_limits(&plugin_id));
+        let plugin_state = self.with_plugin_context(
+            lease.metadata.id.clone(),
+            lease.metadata.permissions.clone(),
+        );
+        let result = runner
+            .run_planner(&lease.pre, plugin_state, objective)
+            .await
+            .map_err(plugin_error)?;
+
+        debug!(
+            plugin_id = %plugin_id,
+            fuel_consumed = result.fuel_consumed,
+            "Planner invocation completed"
+        );
+        result
+            .output
+            .map_err(|e| PlannerError::Plugin(plugin_id, e))
     }
 
-    pub fn commit_session(&self, session_id: String) -> Result<(), String> {
-        let mut manager = self.session_manager.lock().expect("Mutex poisoned");
-        manager.commit_session(session_id)
+    /// The planner plugin to delegate to, unless the caller is that plugin.
+    fn planner_plugin_target(&self) -> Option<(Arc<PluginRegistry>, String)> {
+        let registry = self.plugin_registry.clone()?;
+        let plugin_id = match &self.planner_plugin {
+            Some(id) => id.clone(),
+            None => registry
+                .list_plugins()
+                .into_iter()
+                .filter(|p| p.world == PluginWorld::Planner)
+                .map(|p| p.id)
+                .min()?,
+        };
+        if self.current_plugin_id() == Some(plugin_id.as_str()) {
+            return None;
+        }
+        Some((registry, plugin_id))
+    }
+
+    /// Begins a session owned by the calling plugin and task, and persists it.
+    /// Expired sessions are rolled back first.
+    pub async fn begin_session(&self, base_path: String) -> Result<String, String> {
+        let expired = self
+            .session_manager
+            .lock()
+            .expect("Mutex poisoned")
+            .expire_sessions();
+        for session_id in expired {
+            if let Err(e) = self.session_store.remove(&session_id).await {
+                warn!(session_id = %session_id, error = %e, "Failed to forget expired session");
+            }
+        }
+        let record = {
+            let mut manager = self.session_manager.lock().expect("Mutex poisoned");
+            let session_id = manager.begin_session_for(
+                base_path,
+                self.current_plugin_id.clone(),
+                self.current_task_id.clone(),
+            )?;
+            manager
+                .session(&session_id)
+                .cloned()
+                .expect("Session just began")
+        };
+        if let Err(e) = self.session_store.save(&record).await {
+            // A session that would not survive a restart is still usable
+            warn!(session_id = %record.id, error = %e, "Failed to persist session");
+        }
+        Ok(record.id)
+    }
+
+    /// Commits a session, then updates its persisted record: deleted once
+    /// the session is gone, rewritten when a conflict rebased it.
+    pub async fn commit_session(&self, session_id: String) -> Result<(), CommitError> {
+        let (result, record) = {
+            let mut manager = self.session_manager.lock().expect("Mutex poisoned");
+            let result = manager.commit_session(session_id.clone());
+            (result, manager.session(&session_id).cloned())
+        };
+        let persisted = match &record {
+            Some(record) => self.session_store.save(record).await,
+            None => self.session_store.remove(&session_id).await,
+        };
+        if let Err(e) = persisted {
+            warn!(session_id = %session_id, error = %e, "Failed to persist session");
+        }
+        result
+    }
+
+    /// The working directory of a session.
+    pub fn session_path(&self, session_id: &str) -> Result<std::path::PathBuf, String> {
+        let manager = self.session_manager.lock().expect("Mutex poisoned");
+        manager
+            .get_session_path(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))
+    }
+
+    /// The working directory of a session, for `session-fs` file access,
+    /// which keeps the session from expiring.
+    pub async fn access_session(&self, session_id: &str) -> Result<std::path::PathBuf, String> {
+        let (path, touched) = {
+            let mut manager = self.session_manager.lock().expect("Mutex poisoned");
+            let touched = manager.touch_session(session_id);
+            let path = manager
+                .get_session_path(session_id)
+                .ok_or_else(|| format!("Session not found: {}", session_id))?;
+            (path, touched)
+        };
+        if let Some(last_active) = touched
+            && let Err(e) = self.session_store.touch(session_id, last_active).await
+        {
+            warn!(session_id = %session_id, error = %e, "Failed to persist session activity");
+        }
+        Ok(path)
+    }
+
+    /// Fails unless the calling plugin began `session_id`; only its owner
+    /// may change or commit a session.
+    pub fn check_session_owner(&self, session_id: &str) -> Result<(), String> {
+        let manager = self.session_manager.lock().expect("Mutex poisoned");
+        let record = manager
+            .session(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        if record.owner_plugin.as_deref() != self.current_plugin_id() {
+            return Err(format!(
+                "Permission denied: session {} belongs to another plugin",
+                session_id
+            ));
+        }
+        Ok(())
+    }
+
+    /// Per-file unified diffs of what a session changed, computed on the
+    /// blocking pool without holding the session manager.
+    pub async fn diff_session(&self, session_id: &str) -> Result<Vec<FileDiff>, String> {
+        let inputs = self
+            .session_manager
+            .lock()
+            .expect("Mutex poisoned")
+            .diff_inputs(session_id)?;
+        tokio::task::spawn_blocking(move || inputs.diff())
+            .await
+            .map_err(|e| format!("Diff task failed: {}", e))?
+    }
+
+    /// The session manager, shared with the control plane.
+    pub fn sessions(&self) -> Arc<std::sync::Mutex<SessionManager>> {
+        self.session_manager.clone()
+    }
+
+    /// Removes session directories that no persisted session owns.
+    pub fn cleanup_orphaned_sessions(&self) -> Result<usize, String> {
+        let manager = self.session_manager.lock().expect("Mutex poisoned");
+        manager.cleanup_orphaned_sessions()
     }
 
     /// Returns the provider registry for multi-model access.
@@ -215,6 +560,293 @@ impl BrioHostState {
         self.provider_registry.get_default()
     }
 
+    /// Resolves a guest's model name through the provider registry and
+    /// checks it against the calling plugin's allow-list.
+    ///
+    /// # Errors
+    /// Returns `ProviderNotFound` for unknown providers and `ModelNotAllowed`
+    /// if the plugin may not use the model.
+    pub fn resolve_model(&self, model: &str) -> Result<ResolvedModel, InferenceError> {
+        let resolved = self.provider_registry.resolve(model)?;
+        // Chains are checked member by member as they are tried
+        if resolved.chain.is_none() {
+            self.check_model_allowed(&resolved)?;
+        }
+        Ok(resolved)
+    }
+
+    fn check_model_allowed(&self, resolved: &ResolvedModel) -> Result<(), InferenceError> {
+        let allowed = self
+            .current_plugin_id
+            .as_ref()
+            .and_then(|id| self.allowed_models.get(id).map(|list| (id, list)));
+        if let Some((plugin_id, patterns)) = allowed {
+            let name = resolved.qualified_name();
+            if !patterns.iter().any(|p| model_matches(p, &name)) {
+                return Err(InferenceError::ModelNotAllowed(format!(
+                    "Plugin '{}' may not use model '{}'",
+                    plugin_id, name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls `resolved` within the caller's budgets, or for a fallback chain
+    /// each member the caller may use until one serves the call. Returns the
+    /// model that served it, which its usage is accounted to.
+    async fn serve<T, F, Fut>(
+        &self,
+        resolved: &ResolvedModel,
+        call: F,
+    ) -> Result<(T, ResolvedModel), InferenceError>
+    where
+        F: Fn(ResolvedModel) -> Fut,
+        Fut: std::future::Future<Output = Result<T, InferenceError>>,
+    {
+        let attempt = |served: ResolvedModel| {
+            let call = &call;
+            async move {
+                self.check_model_allowed(&served)?;
+                self.usage.check_budget(&self.usage_scope(&served)).await?;
+                let result = call(served.clone()).await?;
+                Ok((result, served))
+            }
+        };
+        match &resolved.chain {
+            Some(chain) => chain.run(|member| attempt(resolved.member(member))).await,
+            None => attempt(resolved.clone()).await,
+        }
+    }
+
+    /// Sends a completion to the provider named by `request.model`, within
+    /// the token budgets of the caller. An answer that does not match the
+    /// requested response format fails with `InvalidResponse`.
+    pub async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        self.chat_with_report(request)
+            .await
+            .map(|(response, _)| response)
+    }
+
+    /// As `chat`, also reporting what context-window management dropped
+    /// from the history, if anything.
+    pub async fn chat_with_report(
+        &self,
+        mut request: ChatRequest,
+    ) -> Result<(ChatResponse, Option<ContextReport>), InferenceError> {
+        structured::check_request(&request)?;
+        let resolved = self.resolve_model(&request.model)?;
+        request.model = resolved.model.clone();
+        let format = request.response_format.clone();
+
+        let (response, report) = match &self.context {
+            Some(manager) => self.chat_in_window(manager, &resolved, request).await?,
+            None => (self.complete(&resolved, request).await?, None),
+        };
+        if let Some(format) = &format {
+            structured::validate_response(format, &response)?;
+        }
+        Ok((response, report))
+    }
+
+    /// Sends `request` to `resolved` and records its usage against the model
+    /// that served it.
+    async fn complete(
+        &self,
+        resolved: &ResolvedModel,
+        request: ChatRequest,
+    ) -> Result<ChatResponse, InferenceError> {
+        let (response, served) = self
+            .serve(resolved, |served| {
+                let mut request = request.clone();
+                request.model = served.model.clone();
+                async move { served.provider.chat(request).await }
+            })
+            .await?;
+        if let Some(usage) = &response.usage
+            && let Err(e) = self.usage.record(&self.usage_scope(&served), usage).await
+        {
+            warn!(error = %e, "Failed to record token usage");
+        }
+        Ok(response)
+    }
+
+    /// Compacts the history if its estimate exceeds the model's window, and
+    /// once more if the provider still finds it too long.
+    async fn chat_in_window(
+        &self,
+        manager: &ContextManager,
+        resolved: &ResolvedModel,
+        mut request: ChatRequest,
+    ) -> Result<(ChatResponse, Option<ContextReport>), InferenceError> {
+        let mut report = None;
+        if let Some(budget) = manager.prompt_budget(resolved, &request)
+            && context::estimate_tokens(&request.messages) > budget
+        {
+            report = Some(self.compact(manager, resolved, &mut request, budget).await);
+        }
+
+        match self.complete(resolved, request.clone()).await {
+            Err(InferenceError::ContextLengthExceeded) => {
+                // The estimate was off; aim well below what was rejected
+                let budget = context::estimate_tokens(&request.messages) * 3 / 4;
+                let mut retry = self.compact(manager, resolved, &mut request, budget).await;
+                if retry.dropped_messages == 0 {
+                    return Err(InferenceError::ContextLengthExceeded);
+                }
+                retry.retried = true;
+                let report = match report {
+                    Some(mut report) => {
+                        report.merge(retry);
+                        report
+                    }
+                    None => retry,
+                };
+                let response = self.complete(resolved, request).await?;
+                Ok((response, Some(report)))
+            }
+            result => Ok((result?, report)),
+        }
+    }
+
+    /// Drops, or summarizes, the oldest messages of `request` to fit `budget`.
+    async fn compact(
+        &self,
+        manager: &ContextManager,
+        resolved: &ResolvedModel,
+        request: &mut ChatRequest,
+        budget: u32,
+    ) -> ContextReport {
+        let summarize = manager.strategy() == ContextStrategy::Summarize;
+        let target = if summarize {
+            budget.saturating_sub(context::SUMMARY_MAX_TOKENS)
+        } else {
+            budget
+        };
+        let (mut kept, dropped) = context::trim(std::mem::take(&mut request.messages), target);
+        let mut report = ContextReport::new(&dropped);
+
+        if summarize && !dropped.is_empty() {
+            match self.summarize(manager, resolved, &dropped).await {
+                Ok(summary) => {
+                    context::insert_summary(&mut kept, &summary);
+                    report.summary = Some(summary);
+                }
+                Err(e) => {
+                    warn!(error = %e, "Failed to summarize dropped messages, trimming instead")
+                }
+            }
+        }
+        request.messages = kept;
+
+        if report.dropped_messages > 0 {
+            debug!(
+                model = %resolved.qualified_name(),
+                dropped_messages = report.dropped_messages,
+                dropped_tokens = report.dropped_tokens,
+                strategy = manager.strategy().as_str(),
+                "Compacted chat history to fit the context window"
+            );
+            metrics::counter!(
+                "brio_inference_context_compactions_total",
+                "provider" => resolved.provider_name.clone(),
+                "model" => resolved.model.clone(),
+                "strategy" => manager.strategy().as_str()
+            )
+            .increment(1);
+        }
+        report
+    }
+
+    /// Summarizes `dropped` with the configured summary model, or the model
+    /// being called. The summary model must be on the caller's allow-list,
+    /// and the summary counts against the caller's budgets.
+    async fn summarize(
+        &self,
+        manager: &ContextManager,
+        resolved: &ResolvedModel,
+        dropped: &[Message],
+    ) -> Result<String, InferenceError> {
+        let summarizer = match manager.summary_model() {
+            Some(model) => self.resolve_model(model)?,
+            None => resolved.clone(),
+        };
+        let request = context::summary_request(summarizer.model.clone(), dropped);
+        let response = self.complete(&summarizer, request).await?;
+        Ok(response.content.trim().to_string())
+    }
+
+    /// Streams a completion from the provider named by `request.model`,
+    /// forwarding deltas to WebSocket clients when enabled.
+    pub async fn chat_stream(&self, request: ChatRequest) -> Result<ChatStream, InferenceError> {
+        structured::check_request(&request)?;
+        let resolved = self.resolve_model(&request.model)?;
+        let format = request.response_format.clone();
+        let (stream, served) = self
+            .serve(&resolved, |served| {
+                let mut request = request.clone();
+                request.model = served.model.clone();
+                async move { served.provider.chat_stream(request).await }
+            })
+            .await?;
+        let model = served.qualified_name();
+        let mut stream = self.usage.record_stream(stream, self.usage_scope(&served));
+        if let Some(format) = format {
+            stream = structured::validate_stream(stream, format);
+        }
+
+        if !self.stream_patches {
+            return Ok(stream);
+        }
+        Ok(crate::inference::live::forward_patches(
+            stream,
+            self.broadcaster.clone(),
+            self.current_plugin_id.clone(),
+            &model,
+        ))
+    }
+
+    /// Embeds `request.inputs` with the provider named by `request.model`,
+    /// within the token budgets of the caller.
+    pub async fn embed(
+        &self,
+        request: EmbeddingRequest,
+    ) -> Result<EmbeddingResponse, InferenceError> {
+        let resolved = self.resolve_model(&request.model)?;
+        let (response, served) = self
+            .serve(&resolved, |served| {
+                let mut request = request.clone();
+                request.model = served.model.clone();
+                async move { served.provider.embed(request).await }
+            })
+            .await?;
+
+        if let Some(usage) = &response.usage
+            && let Err(e) = self.usage.record(&self.usage_scope(&served), usage).await
+        {
+            warn!(error = %e, "Failed to record token usage");
+        }
+        Ok(response)
+    }
+
+    /// Accounts calls to the current plugin (or `kernel`) and task.
+    fn usage_scope(&self, resolved: &ResolvedModel) -> UsageScope {
+        UsageScope {
+            plugin_id: self
+                .current_plugin_id
+                .clone()
+                .unwrap_or_else(|| "kernel".to_string()),
+            task_id: self.current_task_id.clone(),
+            provider: resolved.provider_name.clone(),
+            model: resolved.model.clone(),
+        }
+    }
+
+    /// The token usage ledger.
+    pub fn usage(&self) -> &UsageLedger {
+        &self.usage
+    }
+
     /// Creates a new view of the host state with restricted permissions and plugin context.
     pub fn with_plugin_context(&self, plugin_id: String, permissions: Vec<String>) -> Self {
         let mut new_state = self.clone();
@@ -235,6 +867,54 @@ impl BrioHostState {
         }
     }
 
+    /// Creates a new view of the host state whose store will be bounded by `limits`.
+    pub fn with_resource_limits(&self, limits: ResourceLimits) -> Self {
+        let mut new_state = self.clone();
+        let plugin_id = self.current_plugin_id.as_deref().unwrap_or("kernel");
+        new_state.limiter = PluginLimiter::new(plugin_id, limits);
+        new_state
+    }
+
+    /// Returns the resource limiter for `Store::limiter`.
+    pub fn limiter_mut(&mut self) -> &mut PluginLimiter {
+        &mut self.limiter
+    }
+
+    pub fn limiter(&self) -> &PluginLimiter {
+        &self.limiter
+    }
+
+    /// Creates a new view of the host state whose WASI context preopens the
+    /// directory of `session_id`.
+    pub fn with_active_session(&self, session_id: impl Into<String>) -> Self {
+        let mut new_state = self.clone();
+        new_state.active_session = Some(session_id.into());
+        new_state
+    }
+
+    /// Sets or clears the session preopened for WASI guests. A WASI context
+    /// already in use is rebuilt with the new preopens.
+    pub fn set_active_session(&mut self, session_id: Option<String>) -> Result<(), String> {
+        if self.active_session == session_id {
+            return Ok(());
+        }
+        self.active_session = session_id;
+        if self.wasi.is_initialized() {
+            let ctx = WasiState::build_ctx(
+                self.wasi_plugin_id(),
+                &self.permissions,
+                self.active_session_dir().as_deref(),
+            )
+            .map_err(|e| format!("Failed to preopen session: {}", e))?;
+            self.wasi.replace_ctx(ctx);
+        }
+        Ok(())
+    }
+
+    pub fn active_session(&self) -> Option<&str> {
+        self.active_session.as_deref()
+    }
+
     pub fn event_bus(&self) -> &EventBus {
         &self.event_bus
     }
@@ -243,7 +923,117 @@ impl BrioHostState {
         self.current_plugin_id.as_deref()
     }
 
+    /// Creates a new view of the host state whose inference usage is
+    /// accounted to `task_id`.
+    pub fn with_task(&self, task_id: impl Into<String>) -> Self {
+        let mut new_state = self.clone();
+        new_state.current_task_id = Some(task_id.into());
+        new_state
+    }
+
+    pub fn current_task_id(&self) -> Option<&str> {
+        self.current_task_id.as_deref()
+    }
+
     pub fn plugin_registry(&self) -> Option<Arc<PluginRegistry>> {
         self.plugin_registry.clone()
     }
 }
+
+impl BrioHostState {
+    fn wasi_plugin_id(&self) -> &str {
+        self.current_plugin_id.as_deref().unwrap_or("kernel")
+    }
+
+    fn active_session_dir(&self) -> Option<std::path::PathBuf> {
+        session_dir(&self.session_manager, self.active_session.as_deref())
+    }
+
+    /// Builds the store's WASI context unless it already has one, so that a
+    /// session that cannot be preopened fails the store's creation rather
+    /// than a later WASI call.
+    pub fn init_wasi(&mut self) -> Result<&mut WasiState> {
+        let plugin_id = self.current_plugin_id.as_deref().unwrap_or("kernel");
+        self.wasi.get_or_init(|| {
+            let dir = session_dir(&self.session_manager, self.active_session.as_deref());
+            WasiState::build(plugin_id, &self.permissions, dir.as_deref())
+        })
+    }
+
+    /// The per-store resource table, shared with WASI.
+    pub(crate) fn resources(&mut self) -> &mut wasmtime::component::ResourceTable {
+        wasmtime_wasi::WasiView::ctx(self).table
+    }
+}
+
+/// The host as a provider: resolution, allow-lists, budgets and usage apply
+/// as for the plugin and task it acts for. The host planner prompts through it.
+#[async_trait::async_trait]
+impl LLMProvider for BrioHostState {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        BrioHostState::chat(self, request).await
+    }
+
+    async fn chat_stream(&self, request: ChatRequest) -> Result<ChatStream, InferenceError> {
+        BrioHostState::chat_stream(self, request).await
+    }
+
+    async fn embed(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse, InferenceError> {
+        BrioHostState::embed(self, request).await
+    }
+}
+
+impl wasmtime_wasi::WasiView for BrioHostState {
+    fn ctx(&mut self) -> wasmtime_wasi::WasiCtxView<'_> {
+        let wasi = self
+            .init_wasi()
+            .expect("WASI context is built when the store is created");
+        wasmtime_wasi::WasiCtxView {
+            ctx: &mut wasi.ctx,
+            table: &mut wasi.table,
+        }
+    }
+}
+
+/// The directory of `session_id` in `manager`, if it still exists.
+fn session_dir(
+    manager: &std::sync::Mutex<SessionManager>,
+    session_id: Option<&str>,
+) -> Option<std::path::PathBuf> {
+    manager
+        .lock()
+        .expect("Mutex poisoned")
+        .get_session_path(session_id?)
+}
+
+/// Creates the session manager and restores the sessions persisted in `pool`,
+/// dropping those whose directories are gone or that have expired.
+async fn open_sessions(
+    pool: &SqlitePool,
+    sandbox: crate::infrastructure::config::SandboxSettings,
+) -> Result<(SessionManager, SessionStore)> {
+    let mut manager = SessionManager::new(sandbox).map_err(|e| anyhow!(e))?;
+    let store = SessionStore::new(pool.clone()).await?;
+    for lost in manager.restore_sessions(store.load().await?) {
+        store.remove(&lost).await?;
+    }
+    for expired in manager.expire_sessions() {
+        store.remove(&expired).await?;
+    }
+    if manager.active_session_count() > 0 {
+        debug!(
+            sessions = manager.active_session_count(),
+            "Restored persisted sessions"
+        );
+    }
+    Ok((manager, store))
+}
+
+/// Matches a qualified model name against an allow-list entry: an exact
+/// `provider/model`, `provider/*`, or `*`.
+fn model_matches(pattern: &str, qualified_name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => qualified_name.starts_with(prefix),
+        None => pattern == qualified_name,
+    }
+}
@@ -1,9 +1,15 @@
-use crate::inference::provider::LLMProvider;
-use crate::inference::types::{ChatRequest, ChatResponse, InferenceError, Message, Role, Usage};
+use crate::inference::provider::{ChatStream, LLMProvider};
+use crate::inference::sse::{self, SseEvent};
+use crate::inference::types::{
+    ChatDelta, ChatRequest, ChatResponse, InferenceError, Message, ResponseFormat, Role, ToolCall,
+    ToolDefinition, Usage,
+};
 use async_trait::async_trait;
+use futures_util::StreamExt;
 use reqwest::{Client, StatusCode, Url};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
+use std::future::{Future, ready};
 use std::time::Duration;
 use tracing::{debug, warn};
 
@@ -15,6 +21,8 @@ const DEFAULT_BASE_DELAY_MS: u64 = 1000;
 const MAX_DELAY_MS: u64 = 30000;
 /// Default Anthropic API version
 const DEFAULT_API_VERSION: &str = "2023-06-01";
+/// Name of the forced response tool in JSON mode
+const JSON_RESPONSE_TOOL: &str = "json_response";
 
 // =============================================================================
 // Anthropic API Types
@@ -23,7 +31,81 @@ const DEFAULT_API_VERSION: &str = "2023-06-01";
 #[derive(Serialize)]
 struct AnthropicMessage {
     role: String,
-    content: String,
+    content: AnthropicMessageContent,
+}
+
+/// Plain text, or content blocks when the turn carries tool calls or results.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+enum AnthropicMessageContent {
+    Text(String),
+    Blocks(Vec<AnthropicContentBlock>),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+    },
+    /// Blocks we do not use, such as `thinking`
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Serialize)]
+struct AnthropicTool {
+    name: String,
+    description: String,
+    input_schema: serde_json::Value,
+}
+
+impl From<ToolDefinition> for AnthropicTool {
+    fn from(t: ToolDefinition) -> Self {
+        Self {
+            name: t.name,
+            description: t.description,
+            input_schema: t.parameters,
+        }
+    }
+}
+
+/// Anthropic has no JSON mode; a response format becomes a tool the model
+/// must call, whose input is the answer.
+impl From<ResponseFormat> for AnthropicTool {
+    fn from(format: ResponseFormat) -> Self {
+        let (name, input_schema) = match format {
+            ResponseFormat::Json => (
+                JSON_RESPONSE_TOOL.to_string(),
+                serde_json::json!({"type": "object"}),
+            ),
+            ResponseFormat::JsonSchema { name, schema } => (name, schema),
+        };
+        Self {
+            name,
+            description: "Respond by calling this tool with your answer.".to_string(),
+            input_schema,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicToolChoice {
+    /// Any tool, when the response tool is offered alongside others
+    Any,
+    Tool {
+        name: String,
+    },
 }
 
 #[derive(Serialize)]
@@ -33,11 +115,21 @@ struct AnthropicChatRequest {
     messages: Vec<AnthropicMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     system: Option<String>,
-}
-
-#[derive(Deserialize)]
-struct AnthropicContent {
-    text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<AnthropicTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<AnthropicToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop_sequences: Vec<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    /// The tool carrying the answer when a response format is requested
+    #[serde(skip)]
+    response_tool: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -48,10 +140,55 @@ struct AnthropicUsage {
 
 #[derive(Deserialize)]
 struct AnthropicChatResponse {
-    content: Vec<AnthropicContent>,
+    content: Vec<AnthropicContentBlock>,
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicStreamMessage {
     usage: Option<AnthropicUsage>,
 }
 
+/// A `text_delta`, or an `input_json_delta` extending the current tool call.
+#[derive(Deserialize)]
+struct AnthropicBlockDelta {
+    text: Option<String>,
+    partial_json: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicOutputUsage {
+    output_tokens: u32,
+}
+
+#[derive(Deserialize)]
+struct AnthropicStreamError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicStreamEvent {
+    MessageStart {
+        message: AnthropicStreamMessage,
+    },
+    ContentBlockStart {
+        content_block: AnthropicContentBlock,
+    },
+    ContentBlockDelta {
+        delta: AnthropicBlockDelta,
+    },
+    ContentBlockStop,
+    MessageDelta {
+        usage: AnthropicOutputUsage,
+    },
+    Error {
+        error: AnthropicStreamError,
+    },
+    #[serde(other)]
+    Other,
+}
+
 // =============================================================================
 // Configuration
 // =============================================================================
@@ -158,10 +295,14 @@ impl AnthropicProvider {
         Duration::from_millis(capped_delay + jitter)
     }
 
-    /// Converts our Message type to Anthropic format, extracting system message
-    fn prepare_messages(messages: &[Message]) -> (Option<String>, Vec<AnthropicMessage>) {
+    /// Converts our Message type to Anthropic format, extracting system message.
+    /// Tool results become `tool_result` blocks in a user turn; consecutive
+    /// results share one turn.
+    fn prepare_messages(
+        messages: &[Message],
+    ) -> Result<(Option<String>, Vec<AnthropicMessage>), InferenceError> {
         let mut system_message = None;
-        let mut anthropic_messages = Vec::new();
+        let mut anthropic_messages: Vec<AnthropicMessage> = Vec::new();
 
         for msg in messages {
             match msg.role {
@@ -172,19 +313,104 @@ impl AnthropicProvider {
                 Role::User => {
                     anthropic_messages.push(AnthropicMessage {
                         role: "user".to_string(),
-                        content: msg.content.clone(),
+                        content: AnthropicMessageContent::Text(msg.content.clone()),
+                    });
+                }
+                Role::Assistant if msg.tool_calls.is_empty() => {
+                    anthropic_messages.push(AnthropicMessage {
+                        role: "assistant".to_string(),
+                        content: AnthropicMessageContent::Text(msg.content.clone()),
                     });
                 }
                 Role::Assistant => {
+                    let mut blocks = Vec::new();
+                    if !msg.content.is_empty() {
+                        blocks.push(AnthropicContentBlock::Text {
+                            text: msg.content.clone(),
+                        });
+                    }
+                    for call in &msg.tool_calls {
+                        let input = serde_json::from_str(&call.arguments).map_err(|e| {
+                            InferenceError::ProviderError(format!(
+                                "Invalid arguments for tool call '{}': {}",
+                                call.id, e
+                            ))
+                        })?;
+                        blocks.push(AnthropicContentBlock::ToolUse {
+                            id: call.id.clone(),
+                            name: call.name.clone(),
+                            input,
+                        });
+                    }
                     anthropic_messages.push(AnthropicMessage {
                         role: "assistant".to_string(),
-                        content: msg.content.clone(),
+                        content: AnthropicMessageContent::Blocks(blocks),
                     });
                 }
+                Role::Tool => {
+                    let tool_use_id = msg.tool_call_id.clone().ok_or_else(|| {
+                        InferenceError::ProviderError(
+                            "Tool message without tool_call_id".to_string(),
+                        )
+                    })?;
+                    let block = AnthropicContentBlock::ToolResult {
+                        tool_use_id,
+                        content: msg.content.clone(),
+                    };
+                    match anthropic_messages.last_mut() {
+                        Some(AnthropicMessage {
+                            role,
+                            content: AnthropicMessageContent::Blocks(blocks),
+                        }) if role == "user" => blocks.push(block),
+                        _ => anthropic_messages.push(AnthropicMessage {
+                            role: "user".to_string(),
+                            content: AnthropicMessageContent::Blocks(vec![block]),
+                        }),
+                    }
+                }
             }
         }
 
-        (system_message, anthropic_messages)
+        Ok((system_message, anthropic_messages))
+    }
+
+    fn build_chat_request(
+        &self,
+        request: ChatRequest,
+        stream: bool,
+    ) -> Result<AnthropicChatRequest, InferenceError> {
+        let (system, messages) = Self::prepare_messages(&request.messages)?;
+        let mut tools: Vec<AnthropicTool> =
+            request.tools.into_iter().map(AnthropicTool::from).collect();
+
+        let mut tool_choice = None;
+        let mut response_tool = None;
+        if let Some(format) = request.response_format {
+            let tool = AnthropicTool::from(format);
+            tool_choice = Some(if tools.is_empty() {
+                AnthropicToolChoice::Tool {
+                    name: tool.name.clone(),
+                }
+            } else {
+                AnthropicToolChoice::Any
+            });
+            response_tool = Some(tool.name.clone());
+            tools.push(tool);
+        }
+
+        Ok(AnthropicChatRequest {
+            model: request.model,
+            max_tokens: request.max_tokens.unwrap_or(self.max_tokens),
+            messages,
+            system,
+            tools,
+            tool_choice,
+            temperature: request.temperature,
+            top_p: request.top_p,
+            stop_sequences: request.stop,
+            stream,
+            response_tool,
+        })
     }
 
     /// Makes a single request attempt
@@ -203,7 +429,67 @@ impl AnthropicProvider {
             )
         })?;
 
-        self.map_api_response(res).await
+        self.map_api_response(res, provider_req.response_tool.as_deref())
+            .await
+    }
+
+    /// Sends a streaming request, returning the response once headers arrive.
+    async fn open_stream(
+        &self,
+        provider_req: &AnthropicChatRequest,
+    ) -> Result<reqwest::Response, (InferenceError, bool)> {
+        let request = self
+            .build_api_request(provider_req)
+            .map_err(|e| (e, false))?;
+
+        let res = request
+            .send()
+            .await
+            .map_err(|e| (InferenceError::NetworkError(e.to_string()), true))?;
+
+        if res.status() == StatusCode::OK {
+            Ok(res)
+        } else {
+            Err(map_error_response(res).await)
+        }
+    }
+
+    /// Runs `attempt` until it succeeds, fails permanently or retries run out.
+    async fn with_retries<T, F, Fut>(&self, mut attempt_fn: F) -> Result<T, InferenceError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, (InferenceError, bool)>>,
+    {
+        let mut last_error = InferenceError::NetworkError("No attempts made".to_string());
+
+        for attempt in 0..=self.max_retries {
+            match attempt_fn().await {
+                Ok(response) => return Ok(response),
+                Err((error, should_retry)) => {
+                    last_error = error;
+
+                    if !should_retry || attempt >= self.max_retries {
+                        break;
+                    }
+
+                    let delay = self.calculate_backoff_delay(attempt);
+                    warn!(
+                        attempt = attempt + 1,
+                        max_retries = self.max_retries,
+                        delay_ms = delay.as_millis() as u64,
+                        error = %last_error,
+                        "Anthropic request failed, retrying after backoff"
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+
+        debug!(
+            attempts = self.max_retries + 1,
+            "All Anthropic retry attempts exhausted"
+        );
+        Err(last_error)
     }
 
     fn build_api_request(
@@ -228,6 +514,7 @@ impl AnthropicProvider {
     async fn map_api_response(
         &self,
         res: reqwest::Response,
+        response_tool: Option<&str>,
     ) -> Result<ChatResponse, (InferenceError, bool)> {
         match res.status() {
             StatusCode::OK => {
@@ -238,100 +525,219 @@ impl AnthropicProvider {
                     )
                 })?;
 
-                let content = body
-                    .content
-                    .first()
-                    .map(|c| c.text.clone())
-                    .unwrap_or_default();
-
-                Ok(ChatResponse {
-                    content,
-                    usage: body.usage.map(|u| Usage {
-                        prompt_tokens: u.input_tokens,
-                        completion_tokens: u.output_tokens,
-                        total_tokens: u.input_tokens + u.output_tokens,
-                    }),
-                })
+                Ok(to_chat_response(body, response_tool))
             }
-            // Anthropic uses 529 for overloaded, 429 for rate limit
-            StatusCode::TOO_MANY_REQUESTS => Err((InferenceError::RateLimit, true)),
-            status if status.as_u16() == 529 => Err((InferenceError::RateLimit, true)),
-            StatusCode::BAD_REQUEST => {
-                let text = res.text().await.unwrap_or_default();
-                if text.contains("context_length") || text.contains("max_tokens") {
-                    Err((InferenceError::ContextLengthExceeded, false))
-                } else {
-                    Err((
-                        InferenceError::ProviderError(format!("Bad Request: {}", text)),
-                        false,
-                    ))
-                }
-            }
-            StatusCode::INTERNAL_SERVER_ERROR
-            | StatusCode::BAD_GATEWAY
-            | StatusCode::SERVICE_UNAVAILABLE
-            | StatusCode::GATEWAY_TIMEOUT => {
-                let status = res.status();
-                let text = res.text().await.unwrap_or_default();
-                Err((
-                    InferenceError::ProviderError(format!("HTTP {}: {}", status, text)),
-                    true,
-                ))
+            _ => Err(map_error_response(res).await),
+        }
+    }
+}
+
+/// Collects text and tool calls. A call to `response_tool` is the answer
+/// itself and becomes the content, replacing any text around it.
+fn to_chat_response(body: AnthropicChatResponse, response_tool: Option<&str>) -> ChatResponse {
+    let mut content = String::new();
+    let mut answer = None;
+    let mut tool_calls = Vec::new();
+    for block in body.content {
+        match block {
+            AnthropicContentBlock::Text { text } => content.push_str(&text),
+            AnthropicContentBlock::ToolUse { name, input, .. }
+                if Some(name.as_str()) == response_tool =>
+            {
+                answer = Some(input.to_string())
             }
-            _ => {
-                let status = res.status();
-                let text = res.text().await.unwrap_or_default();
-                Err((
-                    InferenceError::ProviderError(format!("HTTP {}: {}", status, text)),
+            AnthropicContentBlock::ToolUse { id, name, input } => tool_calls.push(ToolCall {
+                id,
+                name,
+                arguments: input.to_string(),
+            }),
+            AnthropicContentBlock::ToolResult { .. } | AnthropicContentBlock::Other => {}
+        }
+    }
+
+    ChatResponse {
+        content: answer.unwrap_or(content),
+        tool_calls,
+        usage: body.usage.map(|u| Usage {
+            prompt_tokens: u.input_tokens,
+            completion_tokens: u.output_tokens,
+            total_tokens: u.input_tokens + u.output_tokens,
+        }),
+    }
+}
+
+/// Maps a non-200 response to an error and whether it is worth retrying.
+async fn map_error_response(res: reqwest::Response) -> (InferenceError, bool) {
+    let status = res.status();
+    match status {
+        // Anthropic uses 529 for overloaded, 429 for rate limit
+        StatusCode::TOO_MANY_REQUESTS => (InferenceError::RateLimit, true),
+        status if status.as_u16() == 529 => (InferenceError::RateLimit, true),
+        StatusCode::BAD_REQUEST => {
+            let text = res.text().await.unwrap_or_default();
+            if is_context_length_error(&text) {
+                (InferenceError::ContextLengthExceeded, false)
+            } else {
+                (
+                    InferenceError::ProviderError(format!("Bad Request: {}", text)),
                     false,
-                ))
+                )
             }
         }
+        StatusCode::INTERNAL_SERVER_ERROR
+        | StatusCode::BAD_GATEWAY
+        | StatusCode::SERVICE_UNAVAILABLE
+        | StatusCode::GATEWAY_TIMEOUT => {
+            // Transient: still worth failing over once retries run out
+            let text = res.text().await.unwrap_or_default();
+            (
+                InferenceError::ProviderUnavailable(format!("HTTP {}: {}", status, text)),
+                true,
+            )
+        }
+        _ => {
+            let text = res.text().await.unwrap_or_default();
+            (
+                InferenceError::ProviderError(format!("HTTP {}: {}", status, text)),
+                false,
+            )
+        }
     }
 }
 
-#[async_trait]
-impl LLMProvider for AnthropicProvider {
-    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
-        let (system, messages) = Self::prepare_messages(&request.messages);
+/// Whether a 400 body reports a prompt too long for the context window, as
+/// opposed to an invalid `max_tokens` or another bad parameter.
+fn is_context_length_error(body: &str) -> bool {
+    body.contains("prompt is too long")
+        || body.contains("exceed context limit")
+        || body.contains("context_length")
+}
 
-        let provider_req = AnthropicChatRequest {
-            model: request.model,
-            max_tokens: self.max_tokens,
-            messages,
-            system,
-        };
+/// Tracks prompt tokens from `message_start` so the final `message_delta`
+/// can report complete usage, and the `tool_use` block being streamed.
+/// Input streamed to `response_tool` is passed on as content.
+#[derive(Default)]
+struct StreamState {
+    input_tokens: u32,
+    tool_call: Option<ToolCall>,
+    response_tool: Option<String>,
+    in_response_tool: bool,
+}
 
-        let mut last_error = InferenceError::NetworkError("No attempts made".to_string());
+impl StreamState {
+    fn new(response_tool: Option<String>) -> Self {
+        Self {
+            response_tool,
+            ..Self::default()
+        }
+    }
+}
 
-        for attempt in 0..=self.max_retries {
-            match self.make_request(&provider_req).await {
-                Ok(response) => return Ok(response),
-                Err((error, should_retry)) => {
-                    last_error = error;
+impl StreamState {
+    /// Maps one streamed event to a delta. Events carrying no text, usage or
+    /// completed tool call (`ping`, text block boundaries, `message_stop`)
+    /// yield `None`.
+    fn handle(&mut self, event: &SseEvent) -> Option<Result<ChatDelta, InferenceError>> {
+        let parsed: AnthropicStreamEvent = match serde_json::from_str(&event.data) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                return Some(Err(InferenceError::ProviderError(format!(
+                    "Stream parse error: {}: {}",
+                    e, event.data
+                ))));
+            }
+        };
 
-                    if !should_retry || attempt >= self.max_retries {
-                        break;
+        match parsed {
+            AnthropicStreamEvent::MessageStart { message } => {
+                self.input_tokens = message.usage.map_or(0, |u| u.input_tokens);
+                None
+            }
+            AnthropicStreamEvent::ContentBlockStart { content_block } => {
+                if let AnthropicContentBlock::ToolUse { id, name, .. } = content_block {
+                    if self.response_tool.as_ref() == Some(&name) {
+                        self.in_response_tool = true;
+                        return None;
                     }
-
-                    let delay = self.calculate_backoff_delay(attempt);
-                    warn!(
-                        attempt = attempt + 1,
-                        max_retries = self.max_retries,
-                        delay_ms = delay.as_millis() as u64,
-                        error = %last_error,
-                        "Anthropic request failed, retrying after backoff"
-                    );
-                    tokio::time::sleep(delay).await;
+                    self.tool_call = Some(ToolCall {
+                        id,
+                        name,
+                        arguments: String::new(),
+                    });
+                }
+                None
+            }
+            AnthropicStreamEvent::ContentBlockDelta { delta } => {
+                if self.in_response_tool {
+                    let content = delta.partial_json.filter(|j| !j.is_empty())?;
+                    return Some(Ok(ChatDelta {
+                        content,
+                        ..ChatDelta::default()
+                    }));
                 }
+                if let (Some(call), Some(json)) = (self.tool_call.as_mut(), delta.partial_json) {
+                    call.arguments.push_str(&json);
+                    return None;
+                }
+                let content = delta.text.filter(|t| !t.is_empty())?;
+                Some(Ok(ChatDelta {
+                    content,
+                    ..ChatDelta::default()
+                }))
+            }
+            AnthropicStreamEvent::ContentBlockStop => {
+                self.in_response_tool = false;
+                let mut call = self.tool_call.take()?;
+                // A tool without parameters streams no input at all
+                if call.arguments.is_empty() {
+                    call.arguments = "{}".to_string();
+                }
+                Some(Ok(ChatDelta {
+                    tool_calls: vec![call],
+                    ..ChatDelta::default()
+                }))
             }
+            AnthropicStreamEvent::MessageDelta { usage } => Some(Ok(ChatDelta {
+                usage: Some(Usage {
+                    prompt_tokens: self.input_tokens,
+                    completion_tokens: usage.output_tokens,
+                    total_tokens: self.input_tokens + usage.output_tokens,
+                }),
+                ..ChatDelta::default()
+            })),
+            AnthropicStreamEvent::Error { error } => {
+                Some(Err(InferenceError::ProviderError(error.message)))
+            }
+            AnthropicStreamEvent::Other => None,
         }
+    }
+}
 
-        debug!(
-            attempts = self.max_retries + 1,
-            "All Anthropic retry attempts exhausted"
-        );
-        Err(last_error)
+#[async_trait]
+impl LLMProvider for AnthropicProvider {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        let provider_req = self.build_chat_request(request, false)?;
+
+        self.with_retries(|| self.make_request(&provider_req)).await
+    }
+
+    async fn chat_stream(&self, request: ChatRequest) -> Result<ChatStream, InferenceError> {
+        let provider_req = self.build_chat_request(request, true)?;
+        let state = StreamState::new(provider_req.response_tool.clone());
+
+        // Only opening the stream is retried; a broken stream surfaces as an error
+        let res = self
+            .with_retries(|| self.open_stream(&provider_req))
+            .await?;
+        let deltas = sse::events(res)
+            .scan(state, |state, event| {
+                ready(Some(match event {
+                    Ok(event) => state.handle(&event),
+                    Err(e) => Some(Err(e)),
+                }))
+            })
+            .filter_map(ready);
+        Ok(Box::pin(deltas))
     }
 }
 
@@ -402,42 +808,79 @@ mod tests {
 
     #[test]
     fn test_prepare_messages_extracts_system() {
-        let messages = vec![
-            Message {
-                role: Role::System,
-                content: "You are helpful.".to_string(),
-            },
-            Message {
-                role: Role::User,
-                content: "Hello!".to_string(),
-            },
-        ];
+        let messages = vec![Message::system("You are helpful."), Message::user("Hello!")];
 
-        let (system, msgs) = AnthropicProvider::prepare_messages(&messages);
+        let (system, msgs) = AnthropicProvider::prepare_messages(&messages).unwrap();
         assert_eq!(system, Some("You are helpful.".to_string()));
         assert_eq!(msgs.len(), 1);
         assert_eq!(msgs[0].role, "user");
-        assert_eq!(msgs[0].content, "Hello!");
+        assert_eq!(
+            msgs[0].content,
+            AnthropicMessageContent::Text("Hello!".to_string())
+        );
     }
 
     #[test]
     fn test_prepare_messages_no_system() {
-        let messages = vec![
-            Message {
-                role: Role::User,
-                content: "Hello!".to_string(),
-            },
-            Message {
-                role: Role::Assistant,
-                content: "Hi there!".to_string(),
-            },
-        ];
+        let messages = vec![Message::user("Hello!"), Message::assistant("Hi there!")];
 
-        let (system, msgs) = AnthropicProvider::prepare_messages(&messages);
+        let (system, msgs) = AnthropicProvider::prepare_messages(&messages).unwrap();
         assert!(system.is_none());
         assert_eq!(msgs.len(), 2);
     }
 
+    #[test]
+    fn test_prepare_messages_maps_tool_calls_and_results() {
+        let call = |id: &str| ToolCall {
+            id: id.to_string(),
+            name: "grep".to_string(),
+            arguments: r#"{"pattern":"TODO"}"#.to_string(),
+        };
+        let messages = vec![
+            Message::user("Find TODOs"),
+            Message::tool_calls(vec![call("toolu_1"), call("toolu_2")]),
+            Message::tool_result("toolu_1", "src/lib.rs:3"),
+            Message::tool_result("toolu_2", "none"),
+        ];
+
+        let (_, msgs) = AnthropicProvider::prepare_messages(&messages).unwrap();
+        assert_eq!(msgs.len(), 3);
+        let body = serde_json::to_value(&msgs[1].content).unwrap();
+        assert_eq!(body[0]["type"], "tool_use");
+        assert_eq!(body[0]["input"]["pattern"], "TODO");
+
+        assert_eq!(msgs[2].role, "user");
+        let AnthropicMessageContent::Blocks(results) = &msgs[2].content else {
+            panic!("tool results should be blocks");
+        };
+        assert_eq!(
+            results[1],
+            AnthropicContentBlock::ToolResult {
+                tool_use_id: "toolu_2".to_string(),
+                content: "none".to_string(),
+            }
+        );
+
+        let invalid = vec![Message::tool_calls(vec![ToolCall {
+            arguments: "not json".to_string(),
+            ..call("toolu_3")
+        }])];
+        assert!(AnthropicProvider::prepare_messages(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_context_length_errors_exclude_bad_max_tokens() {
+        assert!(is_context_length_error(
+            r#"{"type":"error","error":{"type":"invalid_request_error","message":"prompt is too long: 210000 tokens > 200000 maximum"}}"#
+        ));
+        assert!(is_context_length_error(
+            "input length and `max_tokens` exceed context limit: 190000 + 20000 > 200000"
+        ));
+        assert!(!is_context_length_error(
+            "max_tokens: 100000 > 64000, which is the maximum allowed number of output tokens"
+        ));
+    }
+
     #[test]
     fn test_backoff_delay_calculation() -> anyhow::Result<()> {
         let api_key = SecretString::new("test-key".into());
@@ -454,4 +897,168 @@ mod tests {
         assert!(delay1.as_millis() <= 2500);
         Ok(())
     }
+
+    #[test]
+    fn test_stream_state_reports_text_and_usage() {
+        let event = |data: &str| SseEvent {
+            event: None,
+            data: data.to_string(),
+        };
+        let mut state = StreamState::default();
+
+        assert!(state
+            .handle(&event(
+                r#"{"type":"message_start","message":{"usage":{"input_tokens":12,"output_tokens":1}}}"#
+            ))
+            .is_none());
+        assert!(state.handle(&event(r#"{"type":"ping"}"#)).is_none());
+
+        let delta = state.handle(&event(
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#,
+        ));
+        assert_eq!(delta.unwrap().unwrap().content, "Hi");
+
+        let last = state
+            .handle(&event(
+                r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":7}}"#,
+            ))
+            .unwrap()
+            .unwrap();
+        assert_eq!(last.usage.unwrap().total_tokens, 19);
+
+        let error = state.handle(&event(
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+        ));
+        assert!(error.unwrap().is_err());
+    }
+
+    #[test]
+    fn test_stream_state_assembles_tool_use() {
+        let event = |data: &str| SseEvent {
+            event: None,
+            data: data.to_string(),
+        };
+        let mut state = StreamState::default();
+
+        assert!(state
+            .handle(&event(
+                r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_1","name":"grep","input":{}}}"#
+            ))
+            .is_none());
+        assert!(state
+            .handle(&event(
+                r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"pattern\":"}}"#
+            ))
+            .is_none());
+        assert!(state
+            .handle(&event(
+                r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"\"TODO\"}"}}"#
+            ))
+            .is_none());
+
+        let delta = state
+            .handle(&event(r#"{"type":"content_block_stop","index":1}"#))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            delta.tool_calls,
+            vec![ToolCall {
+                id: "toolu_1".to_string(),
+                name: "grep".to_string(),
+                arguments: r#"{"pattern":"TODO"}"#.to_string(),
+            }]
+        );
+        assert!(
+            state
+                .handle(&event(r#"{"type":"content_block_stop","index":2}"#))
+                .is_none()
+        );
+    }
+
+    fn provider() -> AnthropicProvider {
+        AnthropicProvider::new(AnthropicConfig::new(
+            SecretString::new("test-key".into()),
+            Url::parse("https://api.anthropic.com/v1/").unwrap(),
+        ))
+    }
+
+    #[test]
+    fn test_build_chat_request_forces_response_tool() -> anyhow::Result<()> {
+        let request = ChatRequest::new("claude-sonnet-4-5", vec![Message::user("Review")])
+            .with_temperature(0.5)
+            .with_stop(vec!["END".to_string()])
+            .with_max_tokens(128)
+            .with_response_format(ResponseFormat::JsonSchema {
+                name: "verdict".to_string(),
+                schema: serde_json::json!({"type": "object"}),
+            });
+        let provider_req = provider().build_chat_request(request, false)?;
+        assert_eq!(provider_req.response_tool.as_deref(), Some("verdict"));
+
+        let body = serde_json::to_value(&provider_req)?;
+        assert_eq!(body["max_tokens"], 128);
+        assert_eq!(body["temperature"], 0.5);
+        assert_eq!(body["stop_sequences"], serde_json::json!(["END"]));
+        assert_eq!(body["tools"][0]["name"], "verdict");
+        assert_eq!(
+            body["tool_choice"],
+            serde_json::json!({"type": "tool", "name": "verdict"})
+        );
+        assert!(body.get("response_tool").is_none());
+
+        // Alongside other tools the model may call those first
+        let request = ChatRequest::new("claude-sonnet-4-5", vec![Message::user("Review")])
+            .with_tools(vec![ToolDefinition {
+                name: "grep".to_string(),
+                description: "Search files".to_string(),
+                parameters: serde_json::json!({"type": "object"}),
+            }])
+            .with_response_format(ResponseFormat::Json);
+        let body = serde_json::to_value(provider().build_chat_request(request, false)?)?;
+        assert_eq!(body["tools"][1]["name"], JSON_RESPONSE_TOOL);
+        assert_eq!(body["tool_choice"], serde_json::json!({"type": "any"}));
+        Ok(())
+    }
+
+    #[test]
+    fn test_response_tool_call_becomes_content() {
+        let body: AnthropicChatResponse = serde_json::from_value(serde_json::json!({
+            "content": [
+                {"type": "text", "text": "Here is my verdict"},
+                {"type": "tool_use", "id": "toolu_1", "name": "verdict", "input": {"approved": true}}
+            ],
+            "usage": {"input_tokens": 10, "output_tokens": 5}
+        }))
+        .unwrap();
+        let response = to_chat_response(body, Some("verdict"));
+        assert_eq!(response.content, r#"{"approved":true}"#);
+        assert!(response.tool_calls.is_empty());
+    }
+
+    #[test]
+    fn test_stream_state_streams_response_tool_as_content() {
+        let event = |data: &str| SseEvent {
+            event: None,
+            data: data.to_string(),
+        };
+        let mut state = StreamState::new(Some("verdict".to_string()));
+
+        assert!(state
+            .handle(&event(
+                r#"{"type":"content_block_start","index":0,"content_block":{"type":"tool_use","id":"toolu_1","name":"verdict","input":{}}}"#
+            ))
+            .is_none());
+        let delta = state
+            .handle(&event(
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"input_json_delta","partial_json":"{\"approved\":true}"}}"#,
+            ))
+            .unwrap()
+            .unwrap();
+        assert_eq!(delta.content, r#"{"approved":true}"#);
+        assert!(
+            state
+                .handle(&event(r#"{"type":"content_block_stop","index":0}"#))
+                .is_none()
+        );
+    }
 }
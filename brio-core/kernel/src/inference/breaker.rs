@@ -0,0 +1,293 @@
+//! Per-provider circuit breakers.
+//!
+//! A breaker opens after `failure_threshold` consecutive transient failures
+//! (`RateLimit`, `NetworkError`, `ProviderUnavailable` for 5xx responses)
+//! and rejects calls with `ProviderUnavailable`
+//! until `cooldown` has passed. It then lets one probe through (half-open):
+//! a transient failure opens it again, any other outcome closes it.
+//!
+//! State is exported as the `brio_inference_circuit_state` gauge
+//! (0 = closed, 1 = half-open, 2 = open) labelled by provider.
+
+use crate::inference::provider::{ChatStream, LLMProvider};
+use crate::inference::types::{
+    ChatRequest, ChatResponse, EmbeddingRequest, EmbeddingResponse, InferenceError,
+};
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+/// Default consecutive failures before a breaker opens
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+/// Default time an open breaker waits before probing
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakerConfig {
+    pub failure_threshold: u32,
+    pub cooldown: Duration,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+            cooldown: DEFAULT_COOLDOWN,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    Closed,
+    HalfOpen,
+    Open,
+}
+
+impl BreakerState {
+    fn gauge_value(self) -> f64 {
+        match self {
+            BreakerState::Closed => 0.0,
+            BreakerState::HalfOpen => 1.0,
+            BreakerState::Open => 2.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: BreakerState,
+    consecutive_failures: u32,
+    /// When the breaker opened, or when the half-open probe started
+    since: Instant,
+}
+
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    provider: String,
+    config: BreakerConfig,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(provider: impl Into<String>, config: BreakerConfig) -> Self {
+        let breaker = Self {
+            provider: provider.into(),
+            config,
+            inner: Mutex::new(Inner {
+                state: BreakerState::Closed,
+                consecutive_failures: 0,
+                since: Instant::now(),
+            }),
+        };
+        breaker.export(BreakerState::Closed);
+        breaker
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.inner.lock().expect("Mutex poisoned").state
+    }
+
+    /// Whether a call may proceed. An open breaker past its cooldown admits a
+    /// single probe; a probe that never reports back is replaced after
+    /// another cooldown.
+    pub fn allow(&self) -> bool {
+        let mut inner = self.inner.lock().expect("Mutex poisoned");
+        match inner.state {
+            BreakerState::Closed => true,
+            BreakerState::Open | BreakerState::HalfOpen
+                if inner.since.elapsed() >= self.config.cooldown =>
+            {
+                inner.since = Instant::now();
+                self.transition(&mut inner, BreakerState::HalfOpen);
+                true
+            }
+            BreakerState::Open | BreakerState::HalfOpen => false,
+        }
+    }
+
+    /// Records the outcome of an admitted call.
+    pub fn record(&self, result: Result<(), &InferenceError>) {
+        let mut inner = self.inner.lock().expect("Mutex poisoned");
+        match result {
+            Err(e) if is_breaker_failure(e) => {
+                metrics::counter!("brio_inference_provider_failures_total", "provider" => self.provider.clone())
+                    .increment(1);
+                inner.consecutive_failures += 1;
+                let trip = inner.state == BreakerState::HalfOpen
+                    || inner.consecutive_failures >= self.config.failure_threshold;
+                if trip && inner.state != BreakerState::Open {
+                    inner.since = Instant::now();
+                    self.transition(&mut inner, BreakerState::Open);
+                }
+            }
+            // Other errors say nothing about the provider's health, except
+            // that a probe got an answer
+            Err(_) if inner.state != BreakerState::HalfOpen => {}
+            Err(_) | Ok(()) => {
+                inner.consecutive_failures = 0;
+                self.transition(&mut inner, BreakerState::Closed);
+            }
+        }
+    }
+
+    fn transition(&self, inner: &mut Inner, state: BreakerState) {
+        if inner.state == state {
+            return;
+        }
+        match state {
+            BreakerState::Open => warn!(
+                provider = %self.provider,
+                failures = inner.consecutive_failures,
+                cooldown_ms = self.config.cooldown.as_millis() as u64,
+                "Circuit breaker opened"
+            ),
+            BreakerState::HalfOpen => info!(provider = %self.provider, "Circuit breaker probing"),
+            BreakerState::Closed => info!(provider = %self.provider, "Circuit breaker closed"),
+        }
+        inner.state = state;
+        self.export(state);
+    }
+
+    fn export(&self, state: BreakerState) {
+        metrics::gauge!("brio_inference_circuit_state", "provider" => self.provider.clone())
+            .set(state.gauge_value());
+    }
+}
+
+fn is_breaker_failure(error: &InferenceError) -> bool {
+    matches!(
+        error,
+        InferenceError::RateLimit
+            | InferenceError::NetworkError(_)
+            | InferenceError::ProviderUnavailable(_)
+    )
+}
+
+/// A provider whose calls pass through its circuit breaker.
+pub struct GuardedProvider {
+    inner: Arc<dyn LLMProvider>,
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl GuardedProvider {
+    pub fn new(inner: Arc<dyn LLMProvider>, breaker: Arc<CircuitBreaker>) -> Self {
+        Self { inner, breaker }
+    }
+
+    fn admit(&self) -> Result<(), InferenceError> {
+        if self.breaker.allow() {
+            Ok(())
+        } else {
+            Err(InferenceError::ProviderUnavailable(format!(
+                "Circuit breaker for '{}' is open",
+                self.breaker.provider
+            )))
+        }
+    }
+}
+
+#[async_trait]
+impl LLMProvider for GuardedProvider {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        self.admit()?;
+        let result = self.inner.chat(request).await;
+        self.breaker.record(result.as_ref().map(|_| ()));
+        result
+    }
+
+    /// Only opening the stream is judged; errors mid-stream reach the caller.
+    async fn chat_stream(&self, request: ChatRequest) -> Result<ChatStream, InferenceError> {
+        self.admit()?;
+        let result = self.inner.chat_stream(request).await;
+        self.breaker.record(result.as_ref().map(|_| ()));
+        result
+    }
+
+    async fn embed(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse, InferenceError> {
+        self.admit()?;
+        let result = self.inner.embed(request).await;
+        self.breaker.record(result.as_ref().map(|_| ()));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COOLDOWN: Duration = Duration::from_secs(10);
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new(
+            "openai",
+            BreakerConfig {
+                failure_threshold: 2,
+                cooldown: COOLDOWN,
+            },
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_breaker_opens_and_probes() {
+        let breaker = breaker();
+        let rate_limit = InferenceError::RateLimit;
+
+        assert!(breaker.allow());
+        breaker.record(Err(&rate_limit));
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        breaker.record(Err(&rate_limit));
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(!breaker.allow());
+
+        tokio::time::advance(COOLDOWN).await;
+        assert!(breaker.allow());
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        // Only one probe at a time
+        assert!(!breaker.allow());
+
+        // A failed probe reopens immediately
+        breaker.record(Err(&rate_limit));
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        tokio::time::advance(COOLDOWN).await;
+        assert!(breaker.allow());
+        breaker.record(Ok(()));
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert!(breaker.allow());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_breaker_ignores_non_transient_errors() {
+        let breaker = breaker();
+        for _ in 0..3 {
+            breaker.record(Err(&InferenceError::ContextLengthExceeded));
+        }
+        assert_eq!(breaker.state(), BreakerState::Closed);
+
+        // Successes reset the failure count
+        breaker.record(Err(&InferenceError::RateLimit));
+        breaker.record(Ok(()));
+        breaker.record(Err(&InferenceError::NetworkError("reset".into())));
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_probe_answered_with_an_error_closes() {
+        let breaker = breaker();
+        breaker.record(Err(&InferenceError::RateLimit));
+        breaker.record(Err(&InferenceError::RateLimit));
+        tokio::time::advance(COOLDOWN).await;
+        assert!(breaker.allow());
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+
+        breaker.record(Err(&InferenceError::ContextLengthExceeded));
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert!(breaker.allow());
+        // The failure count starts over
+        breaker.record(Err(&InferenceError::RateLimit));
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+}
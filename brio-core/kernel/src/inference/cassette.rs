@@ -0,0 +1,329 @@
+//! Record/replay of chat completions for deterministic tests.
+//!
+//! A cassette is a JSON file of request/response pairs keyed by a hash of
+//! the normalized request. `CassetteProvider` wraps a real provider to
+//! record them, and serves them back offline on replay. Identical requests
+//! replay their recordings in order, repeating the last one.
+//!
+//! Streams replay as a single delta, like providers without streaming.
+
+use crate::inference::provider::LLMProvider;
+use crate::inference::types::{
+    ChatRequest, ChatResponse, EmbeddingRequest, EmbeddingResponse, InferenceError,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::debug;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CassetteMode {
+    /// Calls the wrapped provider and records every response, replacing the
+    /// cassette's previous contents.
+    Record,
+    /// Serves recorded responses; unmatched requests go to the wrapped
+    /// provider and are added to the cassette.
+    Replay,
+    /// Serves recorded responses only; unmatched requests fail.
+    Strict,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    request_hash: String,
+    request: ChatRequest,
+    response: ChatResponse,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Cassette {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Default)]
+struct State {
+    cassette: Cassette,
+    /// Responses already replayed per request hash
+    played: HashMap<String, usize>,
+}
+
+pub struct CassetteProvider {
+    inner: Option<Arc<dyn LLMProvider>>,
+    path: PathBuf,
+    mode: CassetteMode,
+    state: Mutex<State>,
+}
+
+impl CassetteProvider {
+    /// Opens the cassette at `path`. A missing file is an empty cassette.
+    ///
+    /// # Errors
+    /// Returns `ConfigError` if the file cannot be read or parsed, or if
+    /// `inner` is missing outside `Strict` mode.
+    pub fn open(
+        path: impl Into<PathBuf>,
+        mode: CassetteMode,
+        inner: Option<Arc<dyn LLMProvider>>,
+    ) -> Result<Self, InferenceError> {
+        let path = path.into();
+        if inner.is_none() && mode != CassetteMode::Strict {
+            return Err(InferenceError::ConfigError(format!(
+                "Cassette {} needs a provider to record from",
+                path.display()
+            )));
+        }
+
+        let cassette = match mode {
+            CassetteMode::Record => Cassette::default(),
+            CassetteMode::Replay | CassetteMode::Strict => load(&path)?,
+        };
+        debug!(
+            path = %path.display(),
+            mode = ?mode,
+            interactions = cassette.interactions.len(),
+            "Opened inference cassette"
+        );
+        Ok(Self {
+            inner,
+            path,
+            mode,
+            state: Mutex::new(State {
+                cassette,
+                played: HashMap::new(),
+            }),
+        })
+    }
+
+    /// A strict replay of `path` that never reaches a provider.
+    pub fn replay_only(path: impl Into<PathBuf>) -> Result<Self, InferenceError> {
+        Self::open(path, CassetteMode::Strict, None)
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    async fn record(
+        &self,
+        hash: String,
+        request: ChatRequest,
+    ) -> Result<ChatResponse, InferenceError> {
+        let Some(inner) = &self.inner else {
+            return Err(InferenceError::ProviderError(format!(
+                "Cassette {} has no recording for request {}",
+                self.path.display(),
+                hash
+            )));
+        };
+        let response = inner.chat(request.clone()).await?;
+
+        // Held across the write so concurrent recordings land in order
+        let mut state = self.state.lock().await;
+        state.cassette.interactions.push(Interaction {
+            request_hash: hash.clone(),
+            request,
+            response: response.clone(),
+        });
+        *state.played.entry(hash).or_default() += 1;
+        let json = serde_json::to_string_pretty(&state.cassette)
+            .map_err(|e| InferenceError::ProviderError(format!("Cassette encoding: {}", e)))?;
+        tokio::fs::write(&self.path, json).await.map_err(|e| {
+            InferenceError::ProviderError(format!(
+                "Failed to write cassette {}: {}",
+                self.path.display(),
+                e
+            ))
+        })?;
+        Ok(response)
+    }
+
+    /// The next recorded response for `hash`, if any.
+    async fn replay(&self, hash: &str) -> Option<ChatResponse> {
+        let mut state = self.state.lock().await;
+        let recorded: Vec<&ChatResponse> = state
+            .cassette
+            .interactions
+            .iter()
+            .filter(|i| i.request_hash == hash)
+            .map(|i| &i.response)
+            .collect();
+        let played = state.played.get(hash).copied().unwrap_or(0);
+        let response = (*recorded.get(played).or(recorded.last())?).clone();
+        state.played.insert(hash.to_string(), played + 1);
+        Some(response)
+    }
+}
+
+#[async_trait]
+impl LLMProvider for CassetteProvider {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        let hash = request_hash(&request);
+        if self.mode != CassetteMode::Record
+            && let Some(response) = self.replay(&hash).await
+        {
+            debug!(hash = %hash, "Replaying recorded completion");
+            return Ok(response);
+        }
+        if self.mode == CassetteMode::Strict {
+            return Err(InferenceError::ProviderError(format!(
+                "Cassette {} has no recording for request {} (model '{}')",
+                self.path.display(),
+                hash,
+                request.model
+            )));
+        }
+        self.record(hash, request).await
+    }
+
+    /// Embeddings are not recorded; they go to the wrapped provider.
+    async fn embed(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse, InferenceError> {
+        match &self.inner {
+            Some(inner) => inner.embed(request).await,
+            None => Err(InferenceError::ProviderError(format!(
+                "Cassette {} cannot replay embeddings",
+                self.path.display()
+            ))),
+        }
+    }
+}
+
+/// Hash identifying a request in a cassette. Message content is compared
+/// with line endings normalized and surrounding whitespace trimmed; object
+/// keys (e.g. in tool schemas) are compared regardless of order.
+pub fn request_hash(request: &ChatRequest) -> String {
+    let mut normalized = request.clone();
+    for message in &mut normalized.messages {
+        message.content = message.content.replace("\r\n", "\n").trim().to_string();
+    }
+    // `Value` maps are sorted, which makes the encoding canonical
+    let value = serde_json::to_value(&normalized).unwrap_or_default();
+    hex::encode(Sha256::digest(value.to_string().as_bytes()))
+}
+
+fn load(path: &Path) -> Result<Cassette, InferenceError> {
+    let json = match std::fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Cassette::default()),
+        Err(e) => {
+            return Err(InferenceError::ConfigError(format!(
+                "Failed to read cassette {}: {}",
+                path.display(),
+                e
+            )));
+        }
+    };
+    serde_json::from_str(&json).map_err(|e| {
+        InferenceError::ConfigError(format!("Invalid cassette {}: {}", path.display(), e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::mock::MockProvider;
+    use crate::inference::types::Message;
+
+    fn request(content: &str) -> ChatRequest {
+        ChatRequest::new("gpt-4o", vec![Message::user(content)])
+    }
+
+    #[test]
+    fn test_request_hash_normalizes_requests() {
+        assert_eq!(
+            request_hash(&request("fix the bug")),
+            request_hash(&request("  fix the bug\r\n"))
+        );
+        assert_ne!(
+            request_hash(&request("fix the bug")),
+            request_hash(&request("fix the test"))
+        );
+        let mut other_model = request("fix the bug");
+        other_model.model = "gpt-4o-mini".to_string();
+        assert_ne!(
+            request_hash(&request("fix the bug")),
+            request_hash(&other_model)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cassette_records_and_replays() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.json");
+
+        let recorder = CassetteProvider::open(
+            &path,
+            CassetteMode::Record,
+            Some(Arc::new(MockProvider::new())),
+        )
+        .unwrap();
+        let recorded = recorder.chat(request("hello")).await.unwrap();
+        assert_eq!(recorded.content, "hello");
+
+        let player = CassetteProvider::replay_only(&path).unwrap();
+        let replayed = player.chat(request("hello ")).await.unwrap();
+        assert_eq!(replayed.content, recorded.content);
+
+        match player.chat(request("unknown")).await {
+            Err(InferenceError::ProviderError(msg)) => assert!(msg.contains("no recording")),
+            other => panic!(
+                "expected a strict replay miss, got {:?}",
+                other.map(|r| r.content)
+            ),
+        }
+
+        // Non-strict replay records what it is missing
+        let replayer = CassetteProvider::open(
+            &path,
+            CassetteMode::Replay,
+            Some(Arc::new(MockProvider::new().with_response("new"))),
+        )
+        .unwrap();
+        assert_eq!(
+            replayer.chat(request("hello")).await.unwrap().content,
+            "hello"
+        );
+        assert_eq!(
+            replayer.chat(request("unknown")).await.unwrap().content,
+            "new"
+        );
+        let player = CassetteProvider::replay_only(&path).unwrap();
+        assert_eq!(
+            player.chat(request("unknown")).await.unwrap().content,
+            "new"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cassette_replays_repeated_requests_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassette.json");
+        let cassette = Cassette {
+            interactions: ["first", "second"]
+                .into_iter()
+                .map(|content| Interaction {
+                    request_hash: request_hash(&request("again")),
+                    request: request("again"),
+                    response: ChatResponse {
+                        content: content.to_string(),
+                        usage: None,
+                        tool_calls: Vec::new(),
+                    },
+                })
+                .collect(),
+        };
+        std::fs::write(&path, serde_json::to_string(&cassette).unwrap()).unwrap();
+
+        let player = CassetteProvider::replay_only(&path).unwrap();
+        for expected in ["first", "second", "second"] {
+            assert_eq!(
+                player.chat(request("again")).await.unwrap().content,
+                expected
+            );
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! Context-window management: keeps chat histories within what the model
+//! accepts by trimming, or summarizing, their oldest messages.
+//!
+//! Token counts are estimates (about four characters per token), so callers
+//! retry once with a smaller history when a provider still reports
+//! `ContextLengthExceeded`.
+
+use crate::inference::registry::ResolvedModel;
+use crate::inference::types::{ChatRequest, Message, Role};
+use crate::infrastructure::config::ContextSettings;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Longest summary written by the `summarize` strategy.
+pub const SUMMARY_MAX_TOKENS: u32 = 512;
+
+/// Context windows of well-known models, matched by name prefix.
+const BUILTIN_WINDOWS: &[(&str, u32)] = &[
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4-32k", 32_768),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo", 16_385),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4-mini", 200_000),
+    ("claude-", 200_000),
+    ("gemini-1.5", 1_048_576),
+    ("gemini-2", 1_048_576),
+    ("llama-3.1", 128_000),
+    ("llama-3", 8_192),
+    ("mistral-large", 128_000),
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextStrategy {
+    /// Drops the oldest messages.
+    #[default]
+    Trim,
+    /// Replaces the oldest messages with a summary written by a model.
+    Summarize,
+}
+
+impl ContextStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Trim => "trim",
+            Self::Summarize => "summarize",
+        }
+    }
+}
+
+/// What compaction removed from a request before it was sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ContextReport {
+    pub dropped_messages: u32,
+    /// Estimated tokens of the dropped messages.
+    pub dropped_tokens: u32,
+    /// The summary standing in for them, with the `summarize` strategy.
+    pub summary: Option<String>,
+    /// Whether the provider rejected the request as too long first.
+    pub retried: bool,
+}
+
+impl ContextReport {
+    pub fn new(dropped: &[Message]) -> Self {
+        Self {
+            dropped_messages: dropped.len() as u32,
+            dropped_tokens: estimate_tokens(dropped),
+            ..Self::default()
+        }
+    }
+
+    /// Adds a later compaction of the same request.
+    pub fn merge(&mut self, later: ContextReport) {
+        self.dropped_messages += later.dropped_messages;
+        self.dropped_tokens += later.dropped_tokens;
+        self.summary = later.summary.or(self.summary.take());
+        self.retried |= later.retried;
+    }
+}
+
+/// Estimated prompt tokens of `messages`, including per-message overhead.
+pub fn estimate_tokens(messages: &[Message]) -> u32 {
+    messages
+        .iter()
+        .map(|m| {
+            let chars = m.content.chars().count()
+                + m.tool_calls
+                    .iter()
+                    .map(|c| c.name.len() + c.arguments.chars().count())
+                    .sum::<usize>();
+            4 + chars.div_ceil(4) as u32
+        })
+        .sum()
+}
+
+pub struct ContextManager {
+    settings: ContextSettings,
+}
+
+impl ContextManager {
+    pub fn new(settings: ContextSettings) -> Self {
+        Self { settings }
+    }
+
+    pub fn strategy(&self) -> ContextStrategy {
+        self.settings.strategy
+    }
+
+    pub fn summary_model(&self) -> Option<&str> {
+        self.settings.summary_model.as_deref()
+    }
+
+    /// The context window of `resolved`, from the settings or the built-in
+    /// catalog. Models routed through a gateway (`vendor/model`) match by
+    /// their last segment.
+    pub fn context_window(&self, resolved: &ResolvedModel) -> Option<u32> {
+        let windows = &self.settings.context_windows;
+        if let Some(window) = windows
+            .get(&resolved.qualified_name())
+            .or_else(|| windows.get(&resolved.model))
+        {
+            return Some(*window);
+        }
+
+        let name = resolved.model.rsplit('/').next().unwrap_or(&resolved.model);
+        BUILTIN_WINDOWS
+            .iter()
+            .filter(|(prefix, _)| name.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, window)| *window)
+    }
+
+    /// Tokens the prompt of `request` may use: the window minus room for
+    /// the answer. `None` for models of unknown size.
+    pub fn prompt_budget(&self, resolved: &ResolvedModel, request: &ChatRequest) -> Option<u32> {
+        let reserve = request.max_tokens.unwrap_or(self.settings.reserve_tokens);
+        self.context_window(resolved)
+            .map(|window| window.saturating_sub(reserve))
+    }
+}
+
+/// Drops the oldest messages until the rest fit in `budget` tokens, returning
+/// the kept and the dropped messages.
+///
+/// Leading system messages and the latest turn are always kept, and a tool
+/// call is dropped together with its results. The kept messages may still
+/// exceed `budget` when nothing else can go.
+pub fn trim(messages: Vec<Message>, budget: u32) -> (Vec<Message>, Vec<Message>) {
+    let pinned = messages
+        .iter()
+        .take_while(|m| m.role == Role::System)
+        .count();
+    let mut rest = messages;
+    let mut kept: Vec<Message> = rest.drain(..pinned).collect();
+
+    // Turns start at each non-tool message; tool results join their call
+    let mut turns: VecDeque<Vec<Message>> = VecDeque::new();
+    for message in rest {
+        match turns.back_mut() {
+            Some(turn) if message.role == Role::Tool => turn.push(message),
+            _ => turns.push_back(vec![message]),
+        }
+    }
+
+    let mut total = estimate_tokens(&kept) + turns.iter().map(|t| estimate_tokens(t)).sum::<u32>();
+    let mut dropped = Vec::new();
+    // The latest turn is what the model has to answer
+    while total > budget && turns.len() > 1 {
+        let turn = turns.pop_front().expect("More than one turn left");
+        total -= estimate_tokens(&turn);
+        dropped.extend(turn);
+    }
+    kept.extend(turns.into_iter().flatten());
+    (kept, dropped)
+}
+
+/// A request asking `model` to summarize `dropped`.
+pub fn summary_request(model: impl Into<String>, dropped: &[Message]) -> ChatRequest {
+    let transcript: Vec<String> = dropped
+        .iter()
+        .map(|m| {
+            let role = match m.role {
+                Role::System => "system",
+                Role::User => "user",
+                Role::Assistant => "assistant",
+                Role::Tool => "tool",
+            };
+            let calls: Vec<String> = m
+                .tool_calls
+                .iter()
+                .map(|c| format!(" [calls {}({})]", c.name, c.arguments))
+                .collect();
+            format!("{}: {}{}", role, m.content, calls.concat())
+        })
+        .collect();
+
+    ChatRequest::new(
+        model,
+        vec![
+            Message::system(
+                "Summarize the conversation below for the assistant that continues it. \
+                 Keep facts, decisions, file names and open questions; be brief.",
+            ),
+            Message::user(transcript.join("\n")),
+        ],
+    )
+    .with_max_tokens(SUMMARY_MAX_TOKENS)
+}
+
+/// Puts `summary` into the leading system message, or a new one, so providers
+/// that take a single system prompt keep the original instructions.
+pub fn insert_summary(messages: &mut Vec<Message>, summary: &str) {
+    let note = format!("Summary of the earlier conversation:\n{}", summary);
+    match messages.first_mut() {
+        Some(first) if first.role == Role::System => {
+            first.content = format!("{}\n\n{}", first.content, note);
+        }
+        _ => messages.insert(0, Message::system(note)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::mock::MockProvider;
+    use crate::inference::types::ToolCall;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn resolved(provider: &str, model: &str) -> ResolvedModel {
+        ResolvedModel {
+            provider_name: provider.to_string(),
+            provider: Arc::new(MockProvider::new()),
+            model: model.to_string(),
+            chain: None,
+        }
+    }
+
+    fn text(tokens: usize) -> String {
+        "word".repeat(tokens)
+    }
+
+    #[test]
+    fn test_context_window_uses_settings_then_catalog() {
+        let manager = ContextManager::new(ContextSettings {
+            context_windows: HashMap::from([
+                ("local/llama-3".to_string(), 32_000),
+                ("gpt-4o".to_string(), 64_000),
+            ]),
+            ..ContextSettings::default()
+        });
+
+        assert_eq!(
+            manager.context_window(&resolved("local", "llama-3")),
+            Some(32_000)
+        );
+        assert_eq!(
+            manager.context_window(&resolved("openai", "gpt-4o")),
+            Some(64_000)
+        );
+        assert_eq!(
+            manager.context_window(&resolved("openai", "gpt-4-turbo-preview")),
+            Some(128_000)
+        );
+        assert_eq!(
+            manager.context_window(&resolved("openrouter", "anthropic/claude-sonnet-4-5")),
+            Some(200_000)
+        );
+        assert_eq!(manager.context_window(&resolved("local", "phi-2")), None);
+
+        let request = ChatRequest::new("gpt-4", vec![]).with_max_tokens(1_000);
+        assert_eq!(
+            manager.prompt_budget(&resolved("openai", "gpt-4"), &request),
+            Some(7_192)
+        );
+    }
+
+    #[test]
+    fn test_trim_drops_oldest_turns_with_their_tool_results() {
+        let messages = vec![
+            Message::system("Be brief"),
+            Message::user(text(100)),
+            Message::tool_calls(vec![ToolCall {
+                id: "call_1".to_string(),
+                name: "grep".to_string(),
+                arguments: "{}".to_string(),
+            }]),
+            Message::tool_result("call_1", text(100)),
+            Message::assistant(text(10)),
+            Message::user("And now?"),
+        ];
+        let total = estimate_tokens(&messages);
+
+        let (kept, dropped) = trim(messages.clone(), total);
+        assert_eq!(kept.len(), messages.len());
+        assert!(dropped.is_empty());
+
+        let (kept, dropped) = trim(messages.clone(), total - 50);
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(kept.len(), 5);
+        // The tool result may not outlive its call
+        let (kept, dropped) = trim(messages.clone(), total - 150);
+        assert_eq!(dropped.len(), 3);
+        assert_eq!(kept[0].content, "Be brief");
+        assert_eq!(kept[1].role, Role::Assistant);
+
+        let (kept, dropped) = trim(messages, 0);
+        assert_eq!(dropped.len(), 4);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[1].content, "And now?");
+    }
+
+    #[test]
+    fn test_insert_summary_keeps_system_prompt() {
+        let mut messages = vec![Message::system("Be brief"), Message::user("Hi")];
+        insert_summary(&mut messages, "We fixed the parser.");
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].content.starts_with("Be brief"));
+        assert!(messages[0].content.ends_with("We fixed the parser."));
+
+        let mut messages = vec![Message::user("Hi")];
+        insert_summary(&mut messages, "We fixed the parser.");
+        assert_eq!(messages[0].role, Role::System);
+        assert_eq!(messages.len(), 2);
+    }
+}
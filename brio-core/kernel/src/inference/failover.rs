@@ -0,0 +1,117 @@
+//! Fallback chains: a provider name that tries several providers in order.
+
+use crate::inference::provider::{ChatStream, LLMProvider};
+use crate::inference::types::{
+    ChatRequest, ChatResponse, EmbeddingRequest, EmbeddingResponse, InferenceError,
+};
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::warn;
+
+/// A member of a chain, with the model it is asked for when the chain's
+/// member spec names one (`provider/model`).
+pub struct ChainMember {
+    pub provider_name: String,
+    pub provider: Arc<dyn LLMProvider>,
+    pub model: Option<String>,
+}
+
+/// Tries each member in turn while they fail with `RateLimit`,
+/// `NetworkError` or `ProviderUnavailable` (a 5xx response or an open
+/// circuit breaker), or are refused to the caller (`ModelNotAllowed`,
+/// `BudgetExceeded`). Other errors are returned as-is, as the next provider
+/// would most likely fail the same way.
+pub struct FailoverChain {
+    name: String,
+    members: Vec<ChainMember>,
+}
+
+impl FailoverChain {
+    pub fn new(name: impl Into<String>, members: Vec<ChainMember>) -> Self {
+        Self {
+            name: name.into(),
+            members,
+        }
+    }
+
+    /// Calls each member until one succeeds or fails for good, so callers
+    /// can tell which member served them.
+    pub async fn run<T, F, Fut>(&self, call: F) -> Result<T, InferenceError>
+    where
+        F: Fn(&ChainMember) -> Fut,
+        Fut: std::future::Future<Output = Result<T, InferenceError>>,
+    {
+        let mut last_error = InferenceError::ProviderNotFound(format!(
+            "Fallback chain '{}' has no providers",
+            self.name
+        ));
+
+        for member in &self.members {
+            match call(member).await {
+                Err(e) if should_fail_over(&e) => {
+                    warn!(
+                        chain = %self.name,
+                        provider = %member.provider_name,
+                        error = %e,
+                        "Provider failed, trying the next in chain"
+                    );
+                    metrics::counter!(
+                        "brio_inference_failovers_total",
+                        "chain" => self.name.clone(),
+                        "provider" => member.provider_name.clone()
+                    )
+                    .increment(1);
+                    last_error = e;
+                }
+                result => return result,
+            }
+        }
+        Err(last_error)
+    }
+}
+
+fn should_fail_over(error: &InferenceError) -> bool {
+    matches!(
+        error,
+        InferenceError::RateLimit
+            | InferenceError::NetworkError(_)
+            | InferenceError::ProviderUnavailable(_)
+            | InferenceError::ModelNotAllowed(_)
+            | InferenceError::BudgetExceeded(_)
+    )
+}
+
+#[async_trait]
+impl LLMProvider for FailoverChain {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        self.run(|member| {
+            let mut request = request.clone();
+            request.model = member.model.clone().unwrap_or(request.model);
+            let provider = member.provider.clone();
+            async move { provider.chat(request).await }
+        })
+        .await
+    }
+
+    /// Fails over only while opening the stream; once deltas flow, errors
+    /// reach the caller.
+    async fn chat_stream(&self, request: ChatRequest) -> Result<ChatStream, InferenceError> {
+        self.run(|member| {
+            let mut request = request.clone();
+            request.model = member.model.clone().unwrap_or(request.model);
+            let provider = member.provider.clone();
+            async move { provider.chat_stream(request).await }
+        })
+        .await
+    }
+
+    async fn embed(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse, InferenceError> {
+        self.run(|member| {
+            let mut request = request.clone();
+            request.model = member.model.clone().unwrap_or(request.model);
+            let provider = member.provider.clone();
+            async move { provider.embed(request).await }
+        })
+        .await
+    }
+}
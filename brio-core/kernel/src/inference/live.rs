@@ -0,0 +1,139 @@
+//! Live display of streamed completions over WebSocket.
+//!
+//! Each stream appears under `/inference/<stream-id>` as
+//! `{ plugin_id, model, deltas: [], tool_calls: [], done: false }`; deltas and
+//! tool calls are appended as they arrive, and `usage`, `error` and `done` are
+//! set as the stream ends.
+
+use crate::inference::provider::ChatStream;
+use crate::ws::{BroadcastMessage, Broadcaster, WsPatch};
+use futures_util::StreamExt;
+use futures_util::stream;
+use serde_json::{Value, json};
+use std::future::ready;
+use tracing::debug;
+use uuid::Uuid;
+
+/// Wraps `stream` so every delta is also broadcast as a WS patch.
+pub fn forward_patches(
+    stream: ChatStream,
+    broadcaster: Broadcaster,
+    plugin_id: Option<String>,
+    model: &str,
+) -> ChatStream {
+    let root = format!("/inference/{}", Uuid::new_v4());
+    send(
+        &broadcaster,
+        json!([{
+            "op": "add",
+            "path": root,
+            "value": { "plugin_id": plugin_id, "model": model, "deltas": [], "tool_calls": [], "done": false },
+        }]),
+    );
+
+    let deltas = {
+        let broadcaster = broadcaster.clone();
+        let root = root.clone();
+        stream.inspect(move |item| {
+            let mut ops = Vec::new();
+            match item {
+                Ok(delta) => {
+                    if !delta.content.is_empty() {
+                        ops.push(json!({
+                            "op": "add",
+                            "path": format!("{}/deltas/-", root),
+                            "value": delta.content,
+                        }));
+                    }
+                    for call in &delta.tool_calls {
+                        ops.push(json!({
+                            "op": "add",
+                            "path": format!("{}/tool_calls/-", root),
+                            "value": call,
+                        }));
+                    }
+                    if let Some(usage) = &delta.usage {
+                        ops.push(json!({
+                            "op": "add",
+                            "path": format!("{}/usage", root),
+                            "value": usage,
+                        }));
+                    }
+                }
+                Err(e) => ops.push(json!({
+                    "op": "add",
+                    "path": format!("{}/error", root),
+                    "value": e.to_string(),
+                })),
+            }
+            if !ops.is_empty() {
+                send(&broadcaster, Value::Array(ops));
+            }
+        })
+    };
+
+    let done = stream::once(async move {
+        send(
+            &broadcaster,
+            json!([{ "op": "replace", "path": format!("{}/done", root), "value": true }]),
+        );
+    })
+    .filter_map(|_| ready(None));
+
+    Box::pin(deltas.chain(done))
+}
+
+fn send(broadcaster: &Broadcaster, operations: Value) {
+    // Avoid warning on every delta when nobody is watching
+    if broadcaster.sender().receiver_count() == 0 {
+        return;
+    }
+    let patch = match serde_json::from_value(operations) {
+        Ok(patch) => WsPatch::new(patch),
+        Err(e) => {
+            debug!("Failed to build inference patch: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = broadcaster.broadcast(BroadcastMessage::Patch(patch)) {
+        debug!("No WebSocket clients for inference patch: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::types::{ChatDelta, InferenceError};
+
+    #[tokio::test]
+    async fn test_forward_patches_broadcasts_each_delta() {
+        let broadcaster = Broadcaster::new();
+        let mut receiver = broadcaster.sender().subscribe();
+
+        let source: ChatStream = Box::pin(stream::iter(vec![
+            Ok(ChatDelta {
+                content: "Hel".to_string(),
+                ..ChatDelta::default()
+            }),
+            Ok(ChatDelta {
+                content: "lo".to_string(),
+                ..ChatDelta::default()
+            }),
+            Err(InferenceError::NetworkError("reset".to_string())),
+        ]));
+
+        let forwarded = forward_patches(source, broadcaster, Some("coder".to_string()), "gpt-4");
+        let items: Vec<_> = forwarded.collect().await;
+        assert_eq!(items.len(), 3);
+
+        let mut patches = Vec::new();
+        while let Ok(BroadcastMessage::Patch(patch)) = receiver.try_recv() {
+            patches.push(patch.to_json().unwrap());
+        }
+        assert_eq!(patches.len(), 5);
+        assert!(patches[0].contains(r#""plugin_id":"coder""#));
+        assert!(patches[1].contains(r#""value":"Hel""#));
+        assert!(patches[3].contains("/error"));
+        assert!(patches[4].contains(r#""op":"replace""#));
+    }
+}
@@ -0,0 +1,41 @@
+//! Offline provider for local development and tests.
+
+use crate::inference::provider::LLMProvider;
+use crate::inference::types::{ChatRequest, ChatResponse, InferenceError};
+use async_trait::async_trait;
+
+/// Replies with a fixed response, or echoes the last message when none is set.
+#[derive(Debug, Clone, Default)]
+pub struct MockProvider {
+    response: Option<String>,
+}
+
+impl MockProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_response(mut self, response: impl Into<String>) -> Self {
+        self.response = Some(response.into());
+        self
+    }
+}
+
+#[async_trait]
+impl LLMProvider for MockProvider {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        let content = match &self.response {
+            Some(response) => response.clone(),
+            None => request
+                .messages
+                .last()
+                .map(|m| m.content.clone())
+                .unwrap_or_default(),
+        };
+        Ok(ChatResponse {
+            content,
+            usage: None,
+            tool_calls: Vec::new(),
+        })
+    }
+}
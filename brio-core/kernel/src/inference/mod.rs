@@ -1,11 +1,26 @@
 pub mod anthropic;
+pub mod breaker;
+pub mod cassette;
+pub mod context;
+pub mod failover;
+pub mod live;
+pub mod mock;
 pub mod openai;
 pub mod provider;
 pub mod registry;
+pub mod sse;
+pub mod structured;
 pub mod types;
+pub mod usage;
 
 pub use anthropic::{AnthropicConfig, AnthropicProvider};
+pub use breaker::{BreakerConfig, BreakerState, CircuitBreaker};
+pub use cassette::{CassetteMode, CassetteProvider};
+pub use context::{ContextManager, ContextReport, ContextStrategy};
+pub use failover::FailoverChain;
+pub use mock::MockProvider;
 pub use openai::{OpenAIConfig, OpenAIProvider};
-pub use provider::LLMProvider;
-pub use registry::ProviderRegistry;
+pub use provider::{ChatStream, LLMProvider};
+pub use registry::{ProviderRegistry, ResolvedModel};
 pub use types::*;
+pub use usage::{UsageFilter, UsageLedger, UsageScope, UsageSummary};
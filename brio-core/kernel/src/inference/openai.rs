@@ -1,10 +1,16 @@
-use crate::inference::provider::LLMProvider;
-use crate::inference::types::{ChatRequest, ChatResponse, InferenceError, Message, Usage};
+use crate::inference::provider::{ChatStream, LLMProvider};
+use crate::inference::sse::{self, SseEvent};
+use crate::inference::types::{
+    ChatDelta, ChatRequest, ChatResponse, EmbeddingRequest, EmbeddingResponse, InferenceError,
+    Message, ResponseFormat, Role, ToolCall, ToolDefinition, Usage,
+};
 use anyhow::Result;
 use async_trait::async_trait;
+use futures_util::StreamExt;
 use reqwest::{Client, StatusCode, Url};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
+use std::future::{Future, ready};
 use std::time::Duration;
 use tracing::{debug, warn};
 
@@ -18,12 +24,178 @@ const MAX_DELAY_MS: u64 = 30000;
 #[derive(Serialize)]
 struct OpenAIChatRequest {
     model: String,
-    messages: Vec<Message>,
+    messages: Vec<OpenAIMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<OpenAITool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<OpenAIResponseFormat>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<OpenAIStreamOptions>,
+}
+
+impl OpenAIChatRequest {
+    fn new(request: ChatRequest, max_tokens: Option<u32>, stream: bool) -> Self {
+        Self {
+            model: request.model,
+            messages: request
+                .messages
+                .into_iter()
+                .map(OpenAIMessage::from)
+                .collect(),
+            tools: request.tools.into_iter().map(OpenAITool::from).collect(),
+            max_tokens: request.max_tokens.or(max_tokens),
+            temperature: request.temperature,
+            top_p: request.top_p,
+            stop: request.stop,
+            response_format: request.response_format.map(OpenAIResponseFormat::from),
+            stream,
+            stream_options: stream.then_some(OpenAIStreamOptions {
+                include_usage: true,
+            }),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OpenAIResponseFormat {
+    JsonObject,
+    JsonSchema { json_schema: OpenAIJsonSchema },
+}
+
+#[derive(Serialize)]
+struct OpenAIJsonSchema {
+    name: String,
+    schema: serde_json::Value,
+}
+
+impl From<ResponseFormat> for OpenAIResponseFormat {
+    fn from(format: ResponseFormat) -> Self {
+        match format {
+            ResponseFormat::Json => Self::JsonObject,
+            ResponseFormat::JsonSchema { name, schema } => Self::JsonSchema {
+                json_schema: OpenAIJsonSchema { name, schema },
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct OpenAIStreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Serialize)]
+struct OpenAIMessage {
+    role: Role,
+    /// Null for assistant turns that only call tools
+    content: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OpenAIToolCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+impl From<Message> for OpenAIMessage {
+    fn from(m: Message) -> Self {
+        let content = if m.content.is_empty() && !m.tool_calls.is_empty() {
+            None
+        } else {
+            Some(m.content)
+        };
+        Self {
+            role: m.role,
+            content,
+            tool_calls: m.tool_calls.into_iter().map(OpenAIToolCall::from).collect(),
+            tool_call_id: m.tool_call_id,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct OpenAITool {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: OpenAIFunction,
+}
+
+#[derive(Serialize)]
+struct OpenAIFunction {
+    name: String,
+    description: String,
+    parameters: serde_json::Value,
+}
+
+impl From<ToolDefinition> for OpenAITool {
+    fn from(t: ToolDefinition) -> Self {
+        Self {
+            kind: "function",
+            function: OpenAIFunction {
+                name: t.name,
+                description: t.description,
+                parameters: t.parameters,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct OpenAIToolCall {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    function: OpenAIFunctionCall,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OpenAIFunctionCall {
+    name: String,
+    arguments: String,
+}
+
+impl From<ToolCall> for OpenAIToolCall {
+    fn from(c: ToolCall) -> Self {
+        Self {
+            id: c.id,
+            kind: "function".to_string(),
+            function: OpenAIFunctionCall {
+                name: c.name,
+                arguments: c.arguments,
+            },
+        }
+    }
+}
+
+impl From<OpenAIToolCall> for ToolCall {
+    fn from(c: OpenAIToolCall) -> Self {
+        Self {
+            id: c.id,
+            name: c.function.name,
+            arguments: c.function.arguments,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct OpenAIResponseMessage {
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<OpenAIToolCall>,
 }
 
 #[derive(Deserialize)]
 struct OpenAIChoice {
-    message: Message,
+    message: OpenAIResponseMessage,
 }
 
 #[derive(Deserialize)]
@@ -39,6 +211,74 @@ struct OpenAIChatResponse {
     usage: Option<OpenAIUsage>,
 }
 
+#[derive(Deserialize)]
+struct OpenAIFunctionDelta {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIToolCallDelta {
+    index: usize,
+    id: Option<String>,
+    function: Option<OpenAIFunctionDelta>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIDelta {
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<OpenAIToolCallDelta>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIStreamChoice {
+    delta: OpenAIDelta,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIStreamChunk {
+    #[serde(default)]
+    choices: Vec<OpenAIStreamChoice>,
+    usage: Option<OpenAIUsage>,
+}
+
+#[derive(Serialize)]
+struct OpenAIEmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct OpenAIEmbedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// Embeddings have no completion tokens.
+#[derive(Deserialize)]
+struct OpenAIEmbeddingUsage {
+    prompt_tokens: u32,
+    total_tokens: u32,
+}
+
+#[derive(Deserialize)]
+struct OpenAIEmbeddingResponse {
+    data: Vec<OpenAIEmbedding>,
+    usage: Option<OpenAIEmbeddingUsage>,
+}
+
+impl From<OpenAIUsage> for Usage {
+    fn from(u: OpenAIUsage) -> Self {
+        Usage {
+            prompt_tokens: u.prompt_tokens,
+            completion_tokens: u.completion_tokens,
+            total_tokens: u.total_tokens,
+        }
+    }
+}
+
 /// Configuration for the OpenAI provider
 pub struct OpenAIConfig {
     pub api_key: SecretString,
@@ -47,6 +287,8 @@ pub struct OpenAIConfig {
     pub max_retries: Option<u32>,
     /// Base delay in milliseconds for exponential backoff
     pub base_delay_ms: Option<u64>,
+    /// Maximum tokens to generate; the model's limit applies when unset
+    pub max_tokens: Option<u32>,
 }
 
 impl OpenAIConfig {
@@ -57,6 +299,7 @@ impl OpenAIConfig {
             base_url,
             max_retries: None,
             base_delay_ms: None,
+            max_tokens: None,
         }
     }
 
@@ -71,6 +314,12 @@ impl OpenAIConfig {
         self.base_delay_ms = Some(delay_ms);
         self
     }
+
+    /// Sets the maximum tokens to generate
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
 }
 
 pub struct OpenAIProvider {
@@ -109,7 +358,7 @@ impl OpenAIProvider {
         provider_req: &OpenAIChatRequest,
     ) -> Result<ChatResponse, (InferenceError, bool)> {
         let request = self
-            .build_api_request(provider_req)
+            .build_api_request("chat/completions", provider_req)
             .map_err(|e| (e, false))?;
 
         let res = request.send().await.map_err(|e| {
@@ -122,14 +371,119 @@ impl OpenAIProvider {
         self.map_api_response(res).await
     }
 
-    fn build_api_request(
+    /// Sends a streaming request, returning the response once headers arrive.
+    async fn open_stream(
         &self,
         provider_req: &OpenAIChatRequest,
+    ) -> Result<reqwest::Response, (InferenceError, bool)> {
+        let request = self
+            .build_api_request("chat/completions", provider_req)
+            .map_err(|e| (e, false))?;
+
+        let res = request
+            .send()
+            .await
+            .map_err(|e| (InferenceError::NetworkError(e.to_string()), true))?;
+
+        if res.status() == StatusCode::OK {
+            Ok(res)
+        } else {
+            Err(map_error_response(res).await)
+        }
+    }
+
+    /// Runs `attempt` until it succeeds, fails permanently or retries run out.
+    async fn with_retries<T, F, Fut>(&self, mut attempt_fn: F) -> Result<T, InferenceError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, (InferenceError, bool)>>,
+    {
+        let mut last_error = InferenceError::NetworkError("No attempts made".to_string());
+
+        for attempt in 0..=self.max_retries {
+            match attempt_fn().await {
+                Ok(response) => return Ok(response),
+                Err((error, should_retry)) => {
+                    last_error = error;
+
+                    if !should_retry || attempt >= self.max_retries {
+                        break;
+                    }
+
+                    let delay = self.calculate_backoff_delay(attempt);
+                    warn!(
+                        attempt = attempt + 1,
+                        max_retries = self.max_retries,
+                        delay_ms = delay.as_millis() as u64,
+                        error = %last_error,
+                        "Request failed, retrying after backoff"
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+
+        debug!(
+            attempts = self.max_retries + 1,
+            "All retry attempts exhausted"
+        );
+        Err(last_error)
+    }
+
+    /// Makes a single embeddings request attempt
+    async fn make_embedding_request(
+        &self,
+        provider_req: &OpenAIEmbeddingRequest<'_>,
+    ) -> Result<EmbeddingResponse, (InferenceError, bool)> {
+        let request = self
+            .build_api_request("embeddings", provider_req)
+            .map_err(|e| (e, false))?;
+
+        let res = request
+            .send()
+            .await
+            .map_err(|e| (InferenceError::NetworkError(e.to_string()), true))?;
+        if res.status() != StatusCode::OK {
+            return Err(map_error_response(res).await);
+        }
+
+        let mut body: OpenAIEmbeddingResponse = res.json().await.map_err(|e| {
+            (
+                InferenceError::ProviderError(format!("Parse error: {}", e)),
+                false,
+            )
+        })?;
+        if body.data.len() != provider_req.input.len() {
+            return Err((
+                InferenceError::ProviderError(format!(
+                    "Expected {} embeddings, got {}",
+                    provider_req.input.len(),
+                    body.data.len()
+                )),
+                false,
+            ));
+        }
+        body.data.sort_by_key(|e| e.index);
+
+        Ok(EmbeddingResponse {
+            embeddings: body.data.into_iter().map(|e| e.embedding).collect(),
+            usage: body.usage.map(|u| Usage {
+                prompt_tokens: u.prompt_tokens,
+                completion_tokens: 0,
+                total_tokens: u.total_tokens,
+            }),
+        })
+    }
+
+    fn build_api_request(
+        &self,
+        endpoint: &str,
+        body: &impl Serialize,
     ) -> Result<reqwest::RequestBuilder, InferenceError> {
         let url = self
             .config
             .base_url
-            .join("chat/completions")
+            .join(endpoint)
             .map_err(|e| InferenceError::ConfigError(format!("Invalid URL join: {}", e)))?;
 
         Ok(self
@@ -140,7 +494,7 @@ impl OpenAIProvider {
                 format!("Bearer {}", self.config.api_key.expose_secret()),
             )
             .header("Content-Type", "application/json")
-            .json(provider_req))
+            .json(body))
     }
 
     async fn map_api_response(
@@ -156,7 +510,7 @@ impl OpenAIProvider {
                     )
                 })?;
 
-                let choice = body.choices.first().ok_or_else(|| {
+                let choice = body.choices.into_iter().next().ok_or_else(|| {
                     (
                         InferenceError::ProviderError("No choices returned".to_string()),
                         false,
@@ -164,87 +518,185 @@ impl OpenAIProvider {
                 })?;
 
                 Ok(ChatResponse {
-                    content: choice.message.content.clone(),
-                    usage: body.usage.map(|u| Usage {
-                        prompt_tokens: u.prompt_tokens,
-                        completion_tokens: u.completion_tokens,
-                        total_tokens: u.total_tokens,
-                    }),
+                    content: choice.message.content.unwrap_or_default(),
+                    usage: body.usage.map(Usage::from),
+                    tool_calls: choice
+                        .message
+                        .tool_calls
+                        .into_iter()
+                        .map(ToolCall::from)
+                        .collect(),
                 })
             }
-            StatusCode::TOO_MANY_REQUESTS => Err((InferenceError::RateLimit, true)),
-            StatusCode::BAD_REQUEST => {
-                let text = res.text().await.unwrap_or_default();
-                if text.contains("context_length_exceeded") {
-                    Err((InferenceError::ContextLengthExceeded, false))
-                } else {
-                    Err((
-                        InferenceError::ProviderError(format!("Bad Request: {}", text)),
-                        false,
-                    ))
-                }
+            _ => Err(map_error_response(res).await),
+        }
+    }
+}
+
+/// Maps a non-200 response to an error and whether it is worth retrying.
+async fn map_error_response(res: reqwest::Response) -> (InferenceError, bool) {
+    let status = res.status();
+    match status {
+        StatusCode::TOO_MANY_REQUESTS => (InferenceError::RateLimit, true),
+        StatusCode::BAD_REQUEST => {
+            let text = res.text().await.unwrap_or_default();
+            if text.contains("context_length_exceeded") {
+                (InferenceError::ContextLengthExceeded, false)
+            } else {
+                (
+                    InferenceError::ProviderError(format!("Bad Request: {}", text)),
+                    false,
+                )
             }
-            StatusCode::INTERNAL_SERVER_ERROR
-            | StatusCode::BAD_GATEWAY
-            | StatusCode::SERVICE_UNAVAILABLE
-            | StatusCode::GATEWAY_TIMEOUT => {
-                let status = res.status();
-                let text = res.text().await.unwrap_or_default();
-                Err((
-                    InferenceError::ProviderError(format!("HTTP {}: {}", status, text)),
-                    true,
-                ))
+        }
+        StatusCode::INTERNAL_SERVER_ERROR
+        | StatusCode::BAD_GATEWAY
+        | StatusCode::SERVICE_UNAVAILABLE
+        | StatusCode::GATEWAY_TIMEOUT => {
+            // Transient: still worth failing over once retries run out
+            let text = res.text().await.unwrap_or_default();
+            (
+                InferenceError::ProviderUnavailable(format!("HTTP {}: {}", status, text)),
+                true,
+            )
+        }
+        _ => {
+            let text = res.text().await.unwrap_or_default();
+            (
+                InferenceError::ProviderError(format!("HTTP {}: {}", status, text)),
+                false,
+            )
+        }
+    }
+}
+
+/// Assembles tool calls, whose id, name and arguments arrive in fragments
+/// keyed by `index`, until the choice finishes.
+#[derive(Default)]
+struct StreamState {
+    tool_calls: Vec<ToolCall>,
+}
+
+impl StreamState {
+    /// Maps one streamed chunk to a delta. `[DONE]`, role-only chunks and
+    /// tool call fragments yield `None`.
+    fn handle(&mut self, event: &SseEvent) -> Option<Result<ChatDelta, InferenceError>> {
+        if event.data == "[DONE]" {
+            return self.finish_tool_calls();
+        }
+
+        let chunk: OpenAIStreamChunk = match serde_json::from_str(&event.data) {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                return Some(Err(InferenceError::ProviderError(format!(
+                    "Stream parse error: {}: {}",
+                    e, event.data
+                ))));
             }
-            _ => {
-                let status = res.status();
-                let text = res.text().await.unwrap_or_default();
-                Err((
-                    InferenceError::ProviderError(format!("HTTP {}: {}", status, text)),
-                    false,
-                ))
+        };
+
+        let mut content = String::new();
+        let mut finished = false;
+        for choice in chunk.choices {
+            content.extend(choice.delta.content);
+            for fragment in choice.delta.tool_calls {
+                if let Err(e) = self.apply_fragment(fragment) {
+                    return Some(Err(e));
+                }
             }
+            finished |= choice.finish_reason.is_some();
+        }
+
+        let tool_calls = if finished {
+            std::mem::take(&mut self.tool_calls)
+        } else {
+            Vec::new()
+        };
+        let usage = chunk.usage.map(Usage::from);
+        if content.is_empty() && usage.is_none() && tool_calls.is_empty() {
+            return None;
+        }
+        Some(Ok(ChatDelta {
+            content,
+            usage,
+            tool_calls,
+        }))
+    }
+
+    /// Merges a fragment into its call. Calls are numbered in order, so an
+    /// index may at most open the next call.
+    fn apply_fragment(&mut self, fragment: OpenAIToolCallDelta) -> Result<(), InferenceError> {
+        if fragment.index > self.tool_calls.len() {
+            return Err(InferenceError::ProviderError(format!(
+                "Tool call index {} skips past {} pending calls",
+                fragment.index,
+                self.tool_calls.len()
+            )));
         }
+        if fragment.index == self.tool_calls.len() {
+            self.tool_calls.push(ToolCall {
+                id: String::new(),
+                name: String::new(),
+                arguments: String::new(),
+            });
+        }
+        let call = &mut self.tool_calls[fragment.index];
+        if let Some(id) = fragment.id {
+            call.id = id;
+        }
+        if let Some(function) = fragment.function {
+            call.name.extend(function.name);
+            call.arguments.extend(function.arguments);
+        }
+        Ok(())
+    }
+
+    /// Delivers calls still pending when the stream ends without a finish reason.
+    fn finish_tool_calls(&mut self) -> Option<Result<ChatDelta, InferenceError>> {
+        if self.tool_calls.is_empty() {
+            return None;
+        }
+        Some(Ok(ChatDelta {
+            tool_calls: std::mem::take(&mut self.tool_calls),
+            ..ChatDelta::default()
+        }))
     }
 }
 
 #[async_trait]
 impl LLMProvider for OpenAIProvider {
     async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
-        let provider_req = OpenAIChatRequest {
-            model: request.model,
-            messages: request.messages,
-        };
-
-        let mut last_error = InferenceError::NetworkError("No attempts made".to_string());
+        let provider_req = OpenAIChatRequest::new(request, self.config.max_tokens, false);
 
-        for attempt in 0..=self.max_retries {
-            match self.make_request(&provider_req).await {
-                Ok(response) => return Ok(response),
-                Err((error, should_retry)) => {
-                    last_error = error;
+        self.with_retries(|| self.make_request(&provider_req)).await
+    }
 
-                    if !should_retry || attempt >= self.max_retries {
-                        break;
-                    }
+    async fn chat_stream(&self, request: ChatRequest) -> Result<ChatStream, InferenceError> {
+        let provider_req = OpenAIChatRequest::new(request, self.config.max_tokens, true);
+
+        // Only opening the stream is retried; a broken stream surfaces as an error
+        let res = self
+            .with_retries(|| self.open_stream(&provider_req))
+            .await?;
+        let deltas = sse::events(res)
+            .scan(StreamState::default(), |state, event| {
+                ready(Some(match event {
+                    Ok(event) => state.handle(&event),
+                    Err(e) => Some(Err(e)),
+                }))
+            })
+            .filter_map(ready);
+        Ok(Box::pin(deltas))
+    }
 
-                    let delay = self.calculate_backoff_delay(attempt);
-                    warn!(
-                        attempt = attempt + 1,
-                        max_retries = self.max_retries,
-                        delay_ms = delay.as_millis() as u64,
-                        error = %last_error,
-                        "Request failed, retrying after backoff"
-                    );
-                    tokio::time::sleep(delay).await;
-                }
-            }
-        }
+    async fn embed(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse, InferenceError> {
+        let provider_req = OpenAIEmbeddingRequest {
+            model: &request.model,
+            input: &request.inputs,
+        };
 
-        debug!(
-            attempts = self.max_retries + 1,
-            "All retry attempts exhausted"
-        );
-        Err(last_error)
+        self.with_retries(|| self.make_embedding_request(&provider_req))
+            .await
     }
 }
 
@@ -330,4 +782,143 @@ mod tests {
         assert!(delay1.as_millis() <= 2500);
         Ok(())
     }
+
+    #[test]
+    fn test_stream_state_reports_text_and_usage() {
+        let event = |data: &str| SseEvent {
+            event: None,
+            data: data.to_string(),
+        };
+        let mut state = StreamState::default();
+
+        let delta = state.handle(&event(r#"{"choices":[{"delta":{"content":"Hel"}}]}"#));
+        assert_eq!(delta.unwrap().unwrap().content, "Hel");
+
+        // Role-only opening chunk and the terminator carry nothing
+        assert!(
+            state
+                .handle(&event(r#"{"choices":[{"delta":{"role":"assistant"}}]}"#))
+                .is_none()
+        );
+        assert!(state.handle(&event("[DONE]")).is_none());
+
+        let usage = state.handle(&event(
+            r#"{"choices":[],"usage":{"prompt_tokens":3,"completion_tokens":2,"total_tokens":5}}"#,
+        ));
+        assert_eq!(usage.unwrap().unwrap().usage.unwrap().total_tokens, 5);
+
+        assert!(state.handle(&event("not json")).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_stream_state_assembles_tool_calls() {
+        let event = |data: &str| SseEvent {
+            event: None,
+            data: data.to_string(),
+        };
+        let mut state = StreamState::default();
+
+        assert!(state
+            .handle(&event(
+                r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"grep","arguments":""}}]}}]}"#
+            ))
+            .is_none());
+        assert!(state
+            .handle(&event(
+                r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"pattern\":"}}]}}]}"#
+            ))
+            .is_none());
+        assert!(state
+            .handle(&event(
+                r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"TODO\"}"}}]}}]}"#
+            ))
+            .is_none());
+
+        let delta = state
+            .handle(&event(
+                r#"{"choices":[{"delta":{},"finish_reason":"tool_calls"}]}"#,
+            ))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            delta.tool_calls,
+            vec![ToolCall {
+                id: "call_1".to_string(),
+                name: "grep".to_string(),
+                arguments: r#"{"pattern":"TODO"}"#.to_string(),
+            }]
+        );
+        assert!(state.handle(&event("[DONE]")).is_none());
+
+        // An index beyond the next call is rejected rather than allocated
+        assert!(state
+            .handle(&event(
+                r#"{"choices":[{"delta":{"tool_calls":[{"index":4294967295,"function":{"name":"grep"}}]}}]}"#
+            ))
+            .unwrap()
+            .is_err());
+        assert!(state.tool_calls.is_empty());
+    }
+
+    #[test]
+    fn test_request_maps_tools_and_tool_messages() {
+        let request = ChatRequest::new(
+            "gpt-4",
+            vec![
+                Message::user("Find TODOs"),
+                Message::tool_calls(vec![ToolCall {
+                    id: "call_1".to_string(),
+                    name: "grep".to_string(),
+                    arguments: "{}".to_string(),
+                }]),
+                Message::tool_result("call_1", "none"),
+            ],
+        )
+        .with_tools(vec![ToolDefinition {
+            name: "grep".to_string(),
+            description: "Search files".to_string(),
+            parameters: serde_json::json!({"type": "object"}),
+        }]);
+
+        let body = serde_json::to_value(OpenAIChatRequest::new(request, Some(256), false)).unwrap();
+        assert_eq!(body["tools"][0]["type"], "function");
+        assert_eq!(body["tools"][0]["function"]["name"], "grep");
+        assert!(body["messages"][1]["content"].is_null());
+        assert_eq!(body["messages"][1]["tool_calls"][0]["id"], "call_1");
+        assert_eq!(body["messages"][2]["role"], "tool");
+        assert_eq!(body["messages"][2]["tool_call_id"], "call_1");
+        assert_eq!(body["max_tokens"], 256);
+        assert!(body.get("stream").is_none());
+    }
+
+    #[test]
+    fn test_request_maps_generation_params_and_response_format() {
+        let request = ChatRequest::new("gpt-4o", vec![Message::user("Review this")])
+            .with_temperature(0.2)
+            .with_top_p(0.9)
+            .with_stop(vec!["END".to_string()])
+            .with_max_tokens(64)
+            .with_response_format(ResponseFormat::JsonSchema {
+                name: "verdict".to_string(),
+                schema: serde_json::json!({"type": "object"}),
+            });
+
+        let body = serde_json::to_value(OpenAIChatRequest::new(request, Some(256), false)).unwrap();
+        assert_eq!(body["temperature"], 0.2f32 as f64);
+        assert_eq!(body["top_p"], 0.9f32 as f64);
+        assert_eq!(body["stop"], serde_json::json!(["END"]));
+        assert_eq!(body["max_tokens"], 64);
+        assert_eq!(body["response_format"]["type"], "json_schema");
+        assert_eq!(body["response_format"]["json_schema"]["name"], "verdict");
+
+        let json_mode = ChatRequest::new("gpt-4o", vec![Message::user("Hi")])
+            .with_response_format(ResponseFormat::Json);
+        let body = serde_json::to_value(OpenAIChatRequest::new(json_mode, None, false)).unwrap();
+        assert_eq!(
+            body["response_format"],
+            serde_json::json!({"type": "json_object"})
+        );
+        assert!(body.get("temperature").is_none());
+        assert!(body.get("stop").is_none());
+    }
 }
@@ -1,8 +1,35 @@
-use crate::inference::types::{ChatRequest, ChatResponse, InferenceError};
+use crate::inference::types::{
+    ChatDelta, ChatRequest, ChatResponse, EmbeddingRequest, EmbeddingResponse, InferenceError,
+};
 use async_trait::async_trait;
+use futures_util::stream::{self, BoxStream};
+
+/// A streamed completion, ending after the last delta.
+pub type ChatStream = BoxStream<'static, Result<ChatDelta, InferenceError>>;
 
 #[async_trait]
 pub trait LLMProvider: Send + Sync {
     /// Executes a chat completion request.
     async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError>;
+
+    /// Executes a chat completion request, yielding the content as it is
+    /// generated. Providers without streaming support yield a single delta.
+    async fn chat_stream(&self, request: ChatRequest) -> Result<ChatStream, InferenceError> {
+        let response = self.chat(request).await?;
+        let delta = ChatDelta {
+            content: response.content,
+            usage: response.usage,
+            tool_calls: response.tool_calls,
+        };
+        Ok(Box::pin(stream::once(async move { Ok(delta) })))
+    }
+
+    /// Embeds each input as a vector. Providers without an embeddings
+    /// endpoint fail with `ProviderError`.
+    async fn embed(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse, InferenceError> {
+        Err(InferenceError::ProviderError(format!(
+            "Provider does not support embeddings (model '{}')",
+            request.model
+        )))
+    }
 }
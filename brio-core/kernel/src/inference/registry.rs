@@ -1,16 +1,70 @@
+use crate::inference::anthropic::{AnthropicConfig, AnthropicProvider};
+use crate::inference::breaker::{BreakerConfig, CircuitBreaker, GuardedProvider};
+use crate::inference::cassette::{CassetteMode, CassetteProvider};
+use crate::inference::failover::{ChainMember, FailoverChain};
+use crate::inference::mock::MockProvider;
+use crate::inference::openai::{OpenAIConfig, OpenAIProvider};
 use crate::inference::provider::LLMProvider;
 use crate::inference::types::{ChatRequest, ChatResponse, InferenceError};
+use crate::infrastructure::config::{InferenceSettings, ProviderKind, ProviderSettings};
+use reqwest::Url;
+use secrecy::SecretString;
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 use tracing::debug;
 
+const OPENAI_BASE_URL: &str = "https://api.openai.com/v1/";
+const ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com/v1/";
+/// Base URL of the legacy `openai_*` settings
+const LEGACY_OPENAI_BASE_URL: &str = "https://openrouter.ai/api/v1/";
+
+/// A model name resolved to the provider serving it.
+#[derive(Clone)]
+pub struct ResolvedModel {
+    pub provider_name: String,
+    pub provider: Arc<dyn LLMProvider>,
+    /// The model name as the provider knows it.
+    pub model: String,
+    /// Set when `provider_name` is a fallback chain, so that callers can
+    /// account each call to the member that served it.
+    pub chain: Option<Arc<FailoverChain>>,
+}
+
+impl ResolvedModel {
+    /// The canonical `provider/model` name.
+    pub fn qualified_name(&self) -> String {
+        format!("{}/{}", self.provider_name, self.model)
+    }
+
+    /// What a member of this model's chain is asked for.
+    pub fn member(&self, member: &ChainMember) -> ResolvedModel {
+        ResolvedModel {
+            provider_name: member.provider_name.clone(),
+            provider: member.provider.clone(),
+            model: member.model.clone().unwrap_or_else(|| self.model.clone()),
+            chain: None,
+        }
+    }
+}
+
 /// A registry for managing multiple LLM providers.
 ///
 /// Allows routing requests to different providers by name, enabling
 /// concurrent use of multiple LLM backends (OpenAI, Anthropic, etc.).
+/// Model names of the form `provider/model` select a provider; bare names go
+/// to the default provider. Aliases map short names to either form.
+///
+/// Fallback chains are looked up like providers and try their members in
+/// order. With circuit breakers enabled, every provider handed out is
+/// guarded by its breaker.
 pub struct ProviderRegistry {
     providers: RwLock<HashMap<String, Arc<dyn LLMProvider>>>,
     default_provider: RwLock<Option<String>>,
+    aliases: RwLock<HashMap<String, String>>,
+    /// Chain name to member specs, `provider` or `provider/model`
+    chains: RwLock<HashMap<String, Vec<String>>>,
+    breaker_config: RwLock<Option<BreakerConfig>>,
+    breakers: RwLock<HashMap<String, Arc<CircuitBreaker>>>,
 }
 
 impl ProviderRegistry {
@@ -19,6 +73,10 @@ impl ProviderRegistry {
         Self {
             providers: RwLock::new(HashMap::new()),
             default_provider: RwLock::new(None),
+            aliases: RwLock::new(HashMap::new()),
+            chains: RwLock::new(HashMap::new()),
+            breaker_config: RwLock::new(None),
+            breakers: RwLock::new(HashMap::new()),
         }
     }
 
@@ -46,27 +104,294 @@ impl ProviderRegistry {
         *default = Some(name);
     }
 
-    /// Gets a provider by name
+    /// Gets a provider or fallback chain by name
     pub fn get(&self, name: &str) -> Option<Arc<dyn LLMProvider>> {
-        let providers = self.providers.read().expect("RwLock poisoned");
-        providers.get(name).cloned()
+        match self.get_chain(name) {
+            Some(chain) => Some(chain),
+            None => self.get_provider(name),
+        }
+    }
+
+    fn get_chain(&self, name: &str) -> Option<Arc<FailoverChain>> {
+        let members = {
+            let chains = self.chains.read().expect("RwLock poisoned");
+            chains.get(name).cloned()
+        }?;
+        Some(Arc::new(self.build_chain(name, &members)))
+    }
+
+    /// Gets a registered provider, behind its breaker when breakers are enabled
+    fn get_provider(&self, name: &str) -> Option<Arc<dyn LLMProvider>> {
+        let provider = {
+            let providers = self.providers.read().expect("RwLock poisoned");
+            providers.get(name).cloned()?
+        };
+        match self.breaker(name) {
+            Some(breaker) => Some(Arc::new(GuardedProvider::new(provider, breaker))),
+            None => Some(provider),
+        }
+    }
+
+    fn build_chain(&self, name: &str, members: &[String]) -> FailoverChain {
+        let members = members
+            .iter()
+            .filter_map(|spec| {
+                let (provider_name, model) = match spec.split_once('/') {
+                    Some((provider, model)) => (provider, Some(model.to_string())),
+                    None => (spec.as_str(), None),
+                };
+                // Members removed since the chain was added are skipped
+                let provider = self.get_provider(provider_name)?;
+                Some(ChainMember {
+                    provider_name: provider_name.to_string(),
+                    provider,
+                    model,
+                })
+            })
+            .collect();
+        FailoverChain::new(name, members)
     }
 
     /// Gets the default provider
     pub fn get_default(&self) -> Option<Arc<dyn LLMProvider>> {
-        let default_name = {
-            let default = self.default_provider.read().expect("RwLock poisoned");
-            default.clone()
-        };
+        // If no default set, the first registered provider is used
+        self.default_name().and_then(|name| self.get(&name))
+    }
 
-        match default_name {
-            Some(name) => self.get(&name),
+    /// Name of the provider `get_default` returns
+    fn default_name(&self) -> Option<String> {
+        let default = self.default_provider.read().expect("RwLock poisoned");
+        match &*default {
+            Some(name) => Some(name.clone()),
             None => {
-                // If no default set, return first registered provider
                 let providers = self.providers.read().expect("RwLock poisoned");
-                providers.values().next().cloned()
+                providers.keys().next().cloned()
+            }
+        }
+    }
+
+    /// Adds a fallback chain trying `members` in order. Members are provider
+    /// names, optionally with the model to ask that provider for
+    /// (`anthropic/claude-sonnet-4-5`); otherwise the requested model is used.
+    ///
+    /// # Errors
+    /// Returns `ConfigError` if the chain is empty, shares a provider's name,
+    /// or names a provider that is not registered.
+    pub fn add_chain(
+        &self,
+        name: impl Into<String>,
+        members: Vec<String>,
+    ) -> Result<(), InferenceError> {
+        let name = name.into();
+        if members.is_empty() {
+            return Err(config_error(format!("Fallback chain '{}' is empty", name)));
+        }
+        {
+            let providers = self.providers.read().expect("RwLock poisoned");
+            if providers.contains_key(&name) {
+                return Err(config_error(format!(
+                    "Fallback chain '{}' has the name of a provider",
+                    name
+                )));
+            }
+            for member in &members {
+                let provider = member.split_once('/').map_or(member.as_str(), |(p, _)| p);
+                if !providers.contains_key(provider) {
+                    return Err(config_error(format!(
+                        "Fallback chain '{}' names unknown provider '{}'",
+                        name, provider
+                    )));
+                }
             }
         }
+
+        debug!(chain = %name, members = ?members, "Registering fallback chain");
+        let mut chains = self.chains.write().expect("RwLock poisoned");
+        chains.insert(name, members);
+        Ok(())
+    }
+
+    /// Guards every provider with a circuit breaker using `config`.
+    pub fn enable_circuit_breakers(&self, config: BreakerConfig) {
+        *self.breaker_config.write().expect("RwLock poisoned") = Some(config);
+    }
+
+    /// The circuit breaker of a provider, created on first use. `None` when
+    /// breakers are disabled.
+    pub fn breaker(&self, provider: &str) -> Option<Arc<CircuitBreaker>> {
+        let config = (*self.breaker_config.read().expect("RwLock poisoned"))?;
+        if let Some(breaker) = self.breakers.read().expect("RwLock poisoned").get(provider) {
+            return Some(breaker.clone());
+        }
+        let mut breakers = self.breakers.write().expect("RwLock poisoned");
+        let breaker = breakers
+            .entry(provider.to_string())
+            .or_insert_with(|| Arc::new(CircuitBreaker::new(provider, config)));
+        Some(breaker.clone())
+    }
+
+    /// Builds a registry from settings, validating the whole configuration.
+    ///
+    /// Without `providers`, the legacy keys register an OpenAI-compatible
+    /// `default` provider and an `anthropic` provider for whichever keys are set.
+    ///
+    /// # Errors
+    /// Returns `ConfigError` for missing keys, invalid URLs, an unknown or
+    /// ambiguous default provider, conflicting aliases, or aliases and
+    /// allow-lists naming unknown providers.
+    pub fn from_settings(settings: &InferenceSettings) -> Result<Self, InferenceError> {
+        let registry = Self::new();
+        let providers = if settings.providers.is_empty() {
+            legacy_providers(settings)
+        } else {
+            settings.providers.clone()
+        };
+
+        let mut names: Vec<_> = providers.keys().cloned().collect();
+        names.sort();
+        for name in &names {
+            let provider = &providers[name];
+            if name.is_empty() || name.contains('/') {
+                return Err(config_error(format!(
+                    "Invalid provider name '{}': must be non-empty and contain no '/'",
+                    name
+                )));
+            }
+            registry.register_arc(name.clone(), build_provider(name, provider)?);
+
+            for (alias, model) in &provider.models {
+                registry.insert_alias(alias, format!("{}/{}", name, model))?;
+            }
+        }
+        for (alias, target) in &settings.model_aliases {
+            registry.insert_alias(alias, target.clone())?;
+        }
+        for (chain, members) in &settings.fallback_chains {
+            registry.add_chain(chain.clone(), members.clone())?;
+        }
+        if settings.circuit_breaker.enabled {
+            registry.enable_circuit_breakers(BreakerConfig {
+                failure_threshold: settings.circuit_breaker.failure_threshold,
+                cooldown: std::time::Duration::from_millis(settings.circuit_breaker.cooldown_ms),
+            });
+        }
+
+        match (&settings.default_provider, names.as_slice()) {
+            (Some(name), _) if registry.get(name).is_none() => {
+                return Err(config_error(format!(
+                    "Default provider '{}' is not configured",
+                    name
+                )));
+            }
+            (Some(name), _) => registry.set_default(name.clone()),
+            (None, [only]) => registry.set_default(only.clone()),
+            (None, []) => {}
+            (None, _) if registry.get("default").is_some() => registry.set_default("default"),
+            (None, _) => {
+                return Err(config_error(format!(
+                    "Several providers configured ({}); set inference.default_provider",
+                    names.join(", ")
+                )));
+            }
+        }
+
+        // Targets and allow-lists must name configured providers
+        let aliases = registry.aliases.read().expect("RwLock poisoned").clone();
+        for (alias, target) in &aliases {
+            if let Some((provider, _)) = target.split_once('/')
+                && registry.get(provider).is_none()
+            {
+                return Err(config_error(format!(
+                    "Alias '{}' targets unknown provider '{}'",
+                    alias, provider
+                )));
+            }
+        }
+        for (plugin_id, patterns) in &settings.allowed_models {
+            for pattern in patterns.iter().filter(|p| p.as_str() != "*") {
+                let provider = pattern.split_once('/').map_or(pattern.as_str(), |(p, _)| p);
+                if registry.get(provider).is_none() {
+                    return Err(config_error(format!(
+                        "Allowed model '{}' for plugin '{}' names unknown provider '{}'",
+                        pattern, plugin_id, provider
+                    )));
+                }
+            }
+        }
+
+        Ok(registry)
+    }
+
+    /// Adds an alias, refusing to redefine one
+    fn insert_alias(&self, alias: &str, target: String) -> Result<(), InferenceError> {
+        let mut aliases = self.aliases.write().expect("RwLock poisoned");
+        match aliases.get(alias) {
+            Some(existing) if *existing != target => Err(config_error(format!(
+                "Model alias '{}' maps to both '{}' and '{}'",
+                alias, existing, target
+            ))),
+            _ => {
+                aliases.insert(alias.to_string(), target);
+                Ok(())
+            }
+        }
+    }
+
+    /// Maps `alias` to a model name, e.g. `fast` to `openai/gpt-4o-mini`
+    pub fn add_alias(&self, alias: impl Into<String>, target: impl Into<String>) {
+        let (alias, target) = (alias.into(), target.into());
+        debug!(alias = %alias, target = %target, "Registering model alias");
+        let mut aliases = self.aliases.write().expect("RwLock poisoned");
+        aliases.insert(alias, target);
+    }
+
+    /// Resolves a model name to its provider.
+    ///
+    /// Aliases are applied first. `provider/model` splits at the first `/`,
+    /// so `openrouter/mistralai/devstral` asks the `openrouter` provider for
+    /// `mistralai/devstral`. A bare name uses the default provider. Vendor
+    /// style names served by another provider need an alias
+    /// (`mistralai/devstral = "openrouter/mistralai/devstral"`).
+    ///
+    /// # Errors
+    /// Returns `ProviderNotFound` if the named or default provider is not registered.
+    pub fn resolve(&self, model: &str) -> Result<ResolvedModel, InferenceError> {
+        let target = {
+            let aliases = self.aliases.read().expect("RwLock poisoned");
+            aliases.get(model).cloned()
+        };
+        let name = target.as_deref().unwrap_or(model);
+
+        let (provider_name, model) = match name.split_once('/') {
+            Some((provider, model)) => (provider.to_string(), model.to_string()),
+            None => {
+                let provider = self.default_name().ok_or_else(|| {
+                    InferenceError::ProviderNotFound(format!(
+                        "No default provider configured for model '{}'",
+                        name
+                    ))
+                })?;
+                (provider, name.to_string())
+            }
+        };
+        let chain = self.get_chain(&provider_name);
+        let provider = match &chain {
+            Some(chain) => Some(chain.clone() as Arc<dyn LLMProvider>),
+            None => self.get_provider(&provider_name),
+        }
+        .ok_or_else(|| {
+            InferenceError::ProviderNotFound(format!(
+                "Unknown provider '{}' for model '{}'",
+                provider_name, name
+            ))
+        })?;
+
+        Ok(ResolvedModel {
+            provider_name,
+            provider,
+            model,
+            chain,
+        })
     }
 
     /// Lists all registered provider names
@@ -89,6 +414,7 @@ impl ProviderRegistry {
     /// Removes a provider by name
     pub fn remove(&self, name: &str) -> Option<Arc<dyn LLMProvider>> {
         debug!(provider_name = %name, "Removing LLM provider");
+        self.breakers.write().expect("RwLock poisoned").remove(name);
         let mut providers = self.providers.write().expect("RwLock poisoned");
         providers.remove(name)
     }
@@ -122,6 +448,108 @@ impl Default for ProviderRegistry {
     }
 }
 
+fn config_error(message: String) -> InferenceError {
+    InferenceError::ConfigError(message)
+}
+
+/// Providers described by the pre-`providers` settings keys
+fn legacy_providers(settings: &InferenceSettings) -> HashMap<String, ProviderSettings> {
+    let provider =
+        |kind, base_url: Option<String>, api_key: &Option<SecretString>| ProviderSettings {
+            kind,
+            base_url,
+            api_key: api_key.clone(),
+            max_retries: None,
+            base_delay_ms: None,
+            max_tokens: None,
+            models: HashMap::new(),
+            cassette: None,
+        };
+
+    let mut providers = HashMap::new();
+    if settings.openai_api_key.is_some() {
+        let base_url = settings
+            .openai_base_url
+            .clone()
+            .unwrap_or_else(|| LEGACY_OPENAI_BASE_URL.to_string());
+        providers.insert(
+            "default".to_string(),
+            provider(
+                ProviderKind::OpenAI,
+                Some(base_url),
+                &settings.openai_api_key,
+            ),
+        );
+    }
+    if settings.anthropic_api_key.is_some() {
+        providers.insert(
+            "anthropic".to_string(),
+            provider(ProviderKind::Anthropic, None, &settings.anthropic_api_key),
+        );
+    }
+    providers
+}
+
+fn build_provider(
+    name: &str,
+    settings: &ProviderSettings,
+) -> Result<Arc<dyn LLMProvider>, InferenceError> {
+    let Some(cassette) = &settings.cassette else {
+        return build_backend(name, settings);
+    };
+    let inner = match cassette.mode {
+        CassetteMode::Strict => None,
+        CassetteMode::Record | CassetteMode::Replay => Some(build_backend(name, settings)?),
+    };
+    let provider = CassetteProvider::open(&cassette.path, cassette.mode, inner)?;
+    debug!(provider_name = %name, mode = ?cassette.mode, "Wrapped LLM provider in a cassette");
+    Ok(Arc::new(provider))
+}
+
+fn build_backend(
+    name: &str,
+    settings: &ProviderSettings,
+) -> Result<Arc<dyn LLMProvider>, InferenceError> {
+    let api_key = || {
+        settings
+            .api_key
+            .clone()
+            .ok_or_else(|| config_error(format!("Provider '{}' requires an api_key", name)))
+    };
+    let base_url = |default: &str| {
+        let mut url = settings
+            .base_url
+            .clone()
+            .unwrap_or_else(|| default.to_string());
+        // Endpoints are joined onto the base URL, which needs its trailing slash
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+        Url::parse(&url)
+            .map_err(|e| config_error(format!("Invalid base_url for provider '{}': {}", name, e)))
+    };
+
+    let provider: Arc<dyn LLMProvider> = match settings.kind {
+        ProviderKind::OpenAI => {
+            let mut config = OpenAIConfig::new(api_key()?, base_url(OPENAI_BASE_URL)?);
+            config.max_retries = settings.max_retries;
+            config.base_delay_ms = settings.base_delay_ms;
+            config.max_tokens = settings.max_tokens;
+            Arc::new(OpenAIProvider::new(config))
+        }
+        ProviderKind::Anthropic => {
+            let mut config = AnthropicConfig::new(api_key()?, base_url(ANTHROPIC_BASE_URL)?);
+            config.max_retries = settings.max_retries;
+            config.base_delay_ms = settings.base_delay_ms;
+            config.max_tokens = settings.max_tokens;
+            Arc::new(AnthropicProvider::new(config))
+        }
+        ProviderKind::Mock => Arc::new(MockProvider::new()),
+    };
+    debug!(provider_name = %name, kind = ?settings.kind, "Built LLM provider from settings");
+    Ok(provider)
+}
+
 // =============================================================================
 // Tests
 // =============================================================================
@@ -141,6 +569,7 @@ mod tests {
         async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse, InferenceError> {
             Ok(ChatResponse {
                 content: self.response.clone(),
+                tool_calls: Vec::new(),
                 usage: None,
             })
         }
@@ -259,13 +688,7 @@ mod tests {
             },
         );
 
-        let request = ChatRequest {
-            model: "gpt-4".to_string(),
-            messages: vec![Message {
-                role: Role::User,
-                content: "Hi".to_string(),
-            }],
-        };
+        let request = ChatRequest::new("gpt-4", vec![Message::new(Role::User, "Hi".to_string())]);
 
         let response = registry.chat("openai", request).await.unwrap();
         assert_eq!(response.content, "Hello from OpenAI");
@@ -275,10 +698,7 @@ mod tests {
     async fn test_registry_chat_provider_not_found() {
         let registry = ProviderRegistry::new();
 
-        let request = ChatRequest {
-            model: "gpt-4".to_string(),
-            messages: vec![],
-        };
+        let request = ChatRequest::new("gpt-4", vec![]);
 
         let result = registry.chat("nonexistent", request).await;
         assert!(result.is_err());
@@ -299,15 +719,347 @@ mod tests {
         );
         registry.set_default("anthropic");
 
-        let request = ChatRequest {
-            model: "claude-3".to_string(),
-            messages: vec![Message {
-                role: Role::User,
-                content: "Hello".to_string(),
-            }],
-        };
+        let request = ChatRequest::new(
+            "claude-3",
+            vec![Message::new(Role::User, "Hello".to_string())],
+        );
 
         let response = registry.chat_default(request).await.unwrap();
         assert_eq!(response.content, "Anthropic response");
     }
+
+    #[test]
+    fn test_registry_resolve_model_names() {
+        let registry = ProviderRegistry::new();
+        registry.register(
+            "openai",
+            MockProvider {
+                response: "OpenAI".to_string(),
+            },
+        );
+        registry.register(
+            "local",
+            MockProvider {
+                response: "Local".to_string(),
+            },
+        );
+        registry.set_default("openai");
+        registry.add_alias("fast", "local/llama-3");
+        registry.add_alias("smart", "gpt-4o");
+
+        let resolved = registry.resolve("local/llama-3").unwrap();
+        assert_eq!(resolved.qualified_name(), "local/llama-3");
+
+        let resolved = registry.resolve("gpt-4o").unwrap();
+        assert_eq!(resolved.qualified_name(), "openai/gpt-4o");
+
+        assert_eq!(
+            registry.resolve("fast").unwrap().qualified_name(),
+            "local/llama-3"
+        );
+        assert_eq!(
+            registry.resolve("smart").unwrap().qualified_name(),
+            "openai/gpt-4o"
+        );
+
+        // Only the first segment names the provider
+        let resolved = registry.resolve("openai/org/model").unwrap();
+        assert_eq!(resolved.provider_name, "openai");
+        assert_eq!(resolved.model, "org/model");
+
+        assert!(matches!(
+            registry.resolve("anthropic/claude-x"),
+            Err(InferenceError::ProviderNotFound(msg)) if msg.contains("anthropic")
+        ));
+
+        // Vendor-style names reach their provider through an alias
+        registry.add_alias("mistralai/devstral", "local/mistralai/devstral");
+        let resolved = registry.resolve("mistralai/devstral").unwrap();
+        assert_eq!(resolved.provider_name, "local");
+        assert_eq!(resolved.model, "mistralai/devstral");
+        assert!(matches!(
+            registry.resolve("mistralai/codestral"),
+            Err(InferenceError::ProviderNotFound(msg)) if msg.contains("mistralai")
+        ));
+        assert!(matches!(
+            ProviderRegistry::new().resolve("gpt-4o"),
+            Err(InferenceError::ProviderNotFound(_))
+        ));
+    }
+
+    fn provider_settings(kind: ProviderKind, api_key: Option<&str>) -> ProviderSettings {
+        ProviderSettings {
+            kind,
+            base_url: None,
+            api_key: api_key.map(|k| SecretString::new(k.into())),
+            max_retries: Some(0),
+            base_delay_ms: None,
+            max_tokens: Some(1024),
+            models: HashMap::new(),
+            cassette: None,
+        }
+    }
+
+    fn inference_settings(providers: Vec<(&str, ProviderSettings)>) -> InferenceSettings {
+        InferenceSettings {
+            openai_api_key: None,
+            anthropic_api_key: None,
+            openai_base_url: None,
+            providers: providers
+                .into_iter()
+                .map(|(name, p)| (name.to_string(), p))
+                .collect(),
+            default_provider: None,
+            stream_patches: false,
+            model_aliases: HashMap::new(),
+            allowed_models: HashMap::new(),
+            fallback_chains: HashMap::new(),
+            circuit_breaker: Default::default(),
+            budgets: Default::default(),
+            context: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_registry_from_settings() {
+        let mut claude = provider_settings(ProviderKind::Anthropic, Some("sk-ant"));
+        claude
+            .models
+            .insert("sonnet".to_string(), "claude-sonnet-4-5".to_string());
+        let mut settings = inference_settings(vec![
+            ("claude", claude),
+            (
+                "openai",
+                provider_settings(ProviderKind::OpenAI, Some("sk")),
+            ),
+            ("local", provider_settings(ProviderKind::Mock, None)),
+        ]);
+        settings.default_provider = Some("openai".to_string());
+        settings
+            .model_aliases
+            .insert("fast".to_string(), "local/llama-3".to_string());
+
+        let registry = ProviderRegistry::from_settings(&settings).unwrap();
+        assert_eq!(registry.len(), 3);
+        assert_eq!(
+            registry.resolve("sonnet").unwrap().qualified_name(),
+            "claude/claude-sonnet-4-5"
+        );
+        assert_eq!(
+            registry.resolve("fast").unwrap().qualified_name(),
+            "local/llama-3"
+        );
+        assert_eq!(
+            registry.resolve("gpt-4o").unwrap().qualified_name(),
+            "openai/gpt-4o"
+        );
+    }
+
+    #[test]
+    fn test_registry_from_settings_rejects_invalid_config() {
+        let invalid =
+            |settings: InferenceSettings, needle: &str| match ProviderRegistry::from_settings(
+                &settings,
+            ) {
+                Err(InferenceError::ConfigError(msg)) => {
+                    assert!(
+                        msg.contains(needle),
+                        "'{}' should mention '{}'",
+                        msg,
+                        needle
+                    )
+                }
+                Err(e) => panic!("unexpected error: {}", e),
+                Ok(_) => panic!("settings should be rejected: {}", needle),
+            };
+
+        invalid(
+            inference_settings(vec![(
+                "claude",
+                provider_settings(ProviderKind::Anthropic, None),
+            )]),
+            "requires an api_key",
+        );
+
+        let mut bad_url = provider_settings(ProviderKind::OpenAI, Some("sk"));
+        bad_url.base_url = Some("not a url".to_string());
+        invalid(inference_settings(vec![("openai", bad_url)]), "base_url");
+
+        invalid(
+            inference_settings(vec![
+                ("a", provider_settings(ProviderKind::Mock, None)),
+                ("b", provider_settings(ProviderKind::Mock, None)),
+            ]),
+            "default_provider",
+        );
+
+        let mut unknown_default =
+            inference_settings(vec![("a", provider_settings(ProviderKind::Mock, None))]);
+        unknown_default.default_provider = Some("b".to_string());
+        invalid(unknown_default, "'b'");
+
+        let mut a = provider_settings(ProviderKind::Mock, None);
+        a.models.insert("fast".to_string(), "x".to_string());
+        let mut conflicting = inference_settings(vec![("a", a)]);
+        conflicting
+            .model_aliases
+            .insert("fast".to_string(), "a/y".to_string());
+        invalid(conflicting, "'fast'");
+
+        let mut dangling =
+            inference_settings(vec![("a", provider_settings(ProviderKind::Mock, None))]);
+        dangling
+            .model_aliases
+            .insert("fast".to_string(), "b/x".to_string());
+        invalid(dangling, "unknown provider 'b'");
+
+        let mut allow_list =
+            inference_settings(vec![("a", provider_settings(ProviderKind::Mock, None))]);
+        allow_list
+            .allowed_models
+            .insert("coder".to_string(), vec!["b/*".to_string()]);
+        invalid(allow_list, "coder");
+
+        let mut chain =
+            inference_settings(vec![("a", provider_settings(ProviderKind::Mock, None))]);
+        chain.fallback_chains.insert(
+            "resilient".to_string(),
+            vec!["a".to_string(), "b/x".to_string()],
+        );
+        invalid(chain, "unknown provider 'b'");
+
+        let mut empty_chain =
+            inference_settings(vec![("a", provider_settings(ProviderKind::Mock, None))]);
+        empty_chain
+            .fallback_chains
+            .insert("resilient".to_string(), vec![]);
+        invalid(empty_chain, "empty");
+    }
+
+    struct RateLimitedProvider;
+
+    #[async_trait]
+    impl LLMProvider for RateLimitedProvider {
+        async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+            Err(InferenceError::RateLimit)
+        }
+    }
+
+    struct ModelEchoProvider;
+
+    #[async_trait]
+    impl LLMProvider for ModelEchoProvider {
+        async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+            Ok(ChatResponse {
+                content: request.model,
+                tool_calls: Vec::new(),
+                usage: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registry_chain_fails_over() {
+        let registry = ProviderRegistry::new();
+        registry.register("busy", RateLimitedProvider);
+        registry.register("backup", ModelEchoProvider);
+        registry
+            .add_chain(
+                "resilient",
+                vec!["busy".to_string(), "backup/small-model".to_string()],
+            )
+            .unwrap();
+        registry.set_default("resilient");
+
+        let request = ChatRequest::new("big-model", vec![Message::user("Hi")]);
+        let response = registry.chat("resilient", request.clone()).await.unwrap();
+        assert_eq!(response.content, "small-model");
+
+        // Bare model names reach the chain as the default provider
+        let resolved = registry.resolve("big-model").unwrap();
+        assert_eq!(resolved.provider_name, "resilient");
+        let response = resolved.provider.chat(request).await.unwrap();
+        assert_eq!(response.content, "small-model");
+
+        assert!(matches!(
+            registry.add_chain("busy", vec!["backup".to_string()]),
+            Err(InferenceError::ConfigError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_registry_breaker_skips_open_provider() {
+        let registry = ProviderRegistry::new();
+        registry.register("busy", RateLimitedProvider);
+        registry.register("backup", ModelEchoProvider);
+        registry
+            .add_chain("resilient", vec!["busy".to_string(), "backup".to_string()])
+            .unwrap();
+        registry.enable_circuit_breakers(BreakerConfig {
+            failure_threshold: 2,
+            cooldown: std::time::Duration::from_secs(60),
+        });
+
+        let request = ChatRequest::new("model", vec![Message::user("Hi")]);
+        for _ in 0..2 {
+            registry.chat("resilient", request.clone()).await.unwrap();
+        }
+        let breaker = registry.breaker("busy").unwrap();
+        assert_eq!(breaker.state(), crate::inference::BreakerState::Open);
+        assert_eq!(
+            registry.breaker("backup").unwrap().state(),
+            crate::inference::BreakerState::Closed
+        );
+
+        let err = registry.chat("busy", request.clone()).await.unwrap_err();
+        assert!(matches!(err, InferenceError::ProviderUnavailable(_)));
+        assert_eq!(
+            registry.chat("resilient", request).await.unwrap().content,
+            "model"
+        );
+    }
+
+    #[test]
+    fn test_registry_from_legacy_settings() {
+        let mut settings = inference_settings(vec![]);
+        settings.openai_api_key = Some(SecretString::new("sk".into()));
+        settings.anthropic_api_key = Some(SecretString::new("sk-ant".into()));
+
+        let registry = ProviderRegistry::from_settings(&settings).unwrap();
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.resolve("gpt-4o").unwrap().provider_name, "default");
+        assert!(registry.get("anthropic").is_some());
+
+        let empty = ProviderRegistry::from_settings(&inference_settings(vec![])).unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_registry_from_settings_wraps_cassettes() {
+        use crate::infrastructure::config::CassetteSettings;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("claude.json");
+        let request = ChatRequest::new("claude-sonnet-4-5", vec![Message::user("Hi")]);
+
+        let mut mock = provider_settings(ProviderKind::Mock, None);
+        mock.cassette = Some(CassetteSettings {
+            path: path.clone(),
+            mode: CassetteMode::Record,
+        });
+        let registry =
+            ProviderRegistry::from_settings(&inference_settings(vec![("claude", mock)])).unwrap();
+        registry.chat("claude", request.clone()).await.unwrap();
+
+        // Strict replay needs no api_key, as the provider is never called
+        let mut claude = provider_settings(ProviderKind::Anthropic, None);
+        claude.cassette = Some(CassetteSettings {
+            path,
+            mode: CassetteMode::Strict,
+        });
+        let registry =
+            ProviderRegistry::from_settings(&inference_settings(vec![("claude", claude)])).unwrap();
+        let response = registry.chat("claude", request).await.unwrap();
+        assert_eq!(response.content, "Hi");
+    }
 }
@@ -0,0 +1,145 @@
+//! Server-Sent Events parsing for streaming provider responses.
+
+use crate::inference::types::InferenceError;
+use futures_util::stream::{self, BoxStream};
+use std::collections::VecDeque;
+
+/// A dispatched SSE event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    /// The `event:` field, if any.
+    pub event: Option<String>,
+    /// The `data:` lines, joined with `\n`.
+    pub data: String,
+}
+
+/// Incremental SSE parser. Bytes may be pushed in arbitrary chunks; events are
+/// returned once their terminating blank line has arrived.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds a chunk of the response body, returning any completed events.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(newline) = self.buffer.iter().position(|&b| b == b'\n') {
+            let mut line: Vec<u8> = self.buffer.drain(..=newline).collect();
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            // Whole lines never split a UTF-8 sequence
+            let line = String::from_utf8_lossy(&line);
+            if let Some(event) = self.process_line(&line) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            if self.data.is_empty() {
+                self.event = None;
+                return None;
+            }
+            return Some(SseEvent {
+                event: self.event.take(),
+                data: std::mem::take(&mut self.data).join("\n"),
+            });
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            // `id` and `retry` only matter for reconnecting clients
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Reads `response` as an SSE stream.
+pub fn events(response: reqwest::Response) -> BoxStream<'static, Result<SseEvent, InferenceError>> {
+    struct State {
+        response: Option<reqwest::Response>,
+        parser: SseParser,
+        pending: VecDeque<SseEvent>,
+    }
+
+    let state = State {
+        response: Some(response),
+        parser: SseParser::new(),
+        pending: VecDeque::new(),
+    };
+
+    Box::pin(stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(event) = state.pending.pop_front() {
+                return Some((Ok(event), state));
+            }
+            let response = state.response.as_mut()?;
+            match response.chunk().await {
+                Ok(Some(chunk)) => state.pending.extend(state.parser.push(&chunk)),
+                Ok(None) => {
+                    state.response = None;
+                    // A final event without its blank line is still delivered
+                    state.pending.extend(state.parser.push(b"\n\n"));
+                }
+                Err(e) => {
+                    state.response = None;
+                    return Some((Err(InferenceError::NetworkError(e.to_string())), state));
+                }
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_events_across_chunks() {
+        let mut parser = SseParser::new();
+        assert!(parser.push(b"event: content_block_delta\r\nda").is_empty());
+        let events = parser.push(b"ta: {\"a\":1}\r\n\r\n: keep-alive\n\ndata: x\ndata: y\n\n");
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: Some("content_block_delta".to_string()),
+                    data: "{\"a\":1}".to_string(),
+                },
+                SseEvent {
+                    event: None,
+                    data: "x\ny".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_multibyte_characters_split_across_chunks() {
+        let mut parser = SseParser::new();
+        let bytes = "data: héllo\n\n".as_bytes();
+        let (head, tail) = bytes.split_at(8);
+        assert!(parser.push(head).is_empty());
+        assert_eq!(parser.push(tail)[0].data, "héllo");
+    }
+}
@@ -0,0 +1,218 @@
+//! Checks of generation parameters and structured output.
+//!
+//! Providers map `ResponseFormat` to their native structured-output
+//! features, which are best-effort; the host validates the answer itself
+//! before it reaches the guest.
+
+use crate::inference::provider::ChatStream;
+use crate::inference::types::{ChatRequest, ChatResponse, InferenceError, ResponseFormat};
+use futures_util::{StreamExt, stream};
+use std::future::ready;
+use std::sync::{Arc, Mutex};
+
+/// Rejects out-of-range parameters and response schemas that do not compile.
+///
+/// # Errors
+/// Returns `ProviderError` describing the first invalid option.
+pub fn check_request(request: &ChatRequest) -> Result<(), InferenceError> {
+    let invalid = |msg: String| {
+        Err(InferenceError::ProviderError(format!(
+            "Invalid request: {}",
+            msg
+        )))
+    };
+
+    if let Some(t) = request.temperature
+        && !(0.0..=2.0).contains(&t)
+    {
+        return invalid(format!("temperature {} is outside 0..=2", t));
+    }
+    if let Some(p) = request.top_p
+        && !(p > 0.0 && p <= 1.0)
+    {
+        return invalid(format!("top_p {} is outside (0, 1]", p));
+    }
+    if request.max_tokens == Some(0) {
+        return invalid("max_tokens must be positive".to_string());
+    }
+    if let Some(ResponseFormat::JsonSchema { name, schema }) = &request.response_format
+        && let Err(e) = jsonschema::validator_for(schema)
+    {
+        return invalid(format!("response schema '{}': {}", name, e));
+    }
+    Ok(())
+}
+
+/// Checks the answer of a completion against `format`. Completions that
+/// call tools have not answered yet and are not checked.
+///
+/// # Errors
+/// Returns `InvalidResponse` if the content is not JSON or does not match
+/// the schema.
+pub fn validate_response(
+    format: &ResponseFormat,
+    response: &ChatResponse,
+) -> Result<(), InferenceError> {
+    if !response.tool_calls.is_empty() {
+        return Ok(());
+    }
+    validate_content(format, &response.content)
+}
+
+fn validate_content(format: &ResponseFormat, content: &str) -> Result<(), InferenceError> {
+    let value: serde_json::Value = serde_json::from_str(content).map_err(|e| {
+        InferenceError::InvalidResponse(format!("Response is not valid JSON: {}", e))
+    })?;
+
+    let ResponseFormat::JsonSchema { name, schema } = format else {
+        return Ok(());
+    };
+    let validator = jsonschema::validator_for(schema).map_err(|e| {
+        InferenceError::InvalidResponse(format!("Invalid response schema '{}': {}", name, e))
+    })?;
+    let errors: Vec<String> = validator
+        .iter_errors(&value)
+        .map(|e| format!("{} at '{}'", e, e.instance_path))
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(InferenceError::InvalidResponse(format!(
+            "Response does not match schema '{}': {}",
+            name,
+            errors.join("; ")
+        )))
+    }
+}
+
+/// Passes `stream` through, then fails with `InvalidResponse` after the last
+/// delta if the streamed content does not match `format`.
+pub fn validate_stream(stream: ChatStream, format: ResponseFormat) -> ChatStream {
+    // Content so far, or `None` once a tool call makes the check moot
+    let content = Arc::new(Mutex::new(Some(String::new())));
+
+    let collected = content.clone();
+    let deltas = stream.inspect(move |delta| {
+        let mut content = collected.lock().expect("Mutex poisoned");
+        match delta {
+            Ok(d) if !d.tool_calls.is_empty() => *content = None,
+            Ok(d) => {
+                if let Some(content) = content.as_mut() {
+                    content.push_str(&d.content);
+                }
+            }
+            Err(_) => *content = None,
+        }
+    });
+    let check = stream::once(async move {
+        let content = content.lock().expect("Mutex poisoned").take();
+        content.and_then(|c| validate_content(&format, &c).err())
+    })
+    .filter_map(|error| ready(error.map(Err)));
+
+    Box::pin(deltas.chain(check))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::types::{ChatDelta, Message, ToolCall};
+    use serde_json::json;
+
+    fn schema() -> ResponseFormat {
+        ResponseFormat::JsonSchema {
+            name: "verdict".to_string(),
+            schema: json!({
+                "type": "object",
+                "properties": { "approved": { "type": "boolean" } },
+                "required": ["approved"]
+            }),
+        }
+    }
+
+    fn response(content: &str) -> ChatResponse {
+        ChatResponse {
+            content: content.to_string(),
+            usage: None,
+            tool_calls: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_check_request_rejects_invalid_options() {
+        let request = || ChatRequest::new("gpt-4o", vec![Message::user("Hi")]);
+        assert!(check_request(&request().with_temperature(0.2).with_top_p(0.9)).is_ok());
+        assert!(check_request(&request().with_temperature(2.5)).is_err());
+        assert!(check_request(&request().with_top_p(0.0)).is_err());
+        assert!(check_request(&request().with_max_tokens(0)).is_err());
+
+        let bad_schema = ResponseFormat::JsonSchema {
+            name: "broken".to_string(),
+            schema: json!({ "type": "no-such-type" }),
+        };
+        match check_request(&request().with_response_format(bad_schema)) {
+            Err(InferenceError::ProviderError(msg)) => assert!(msg.contains("broken"), "{}", msg),
+            other => panic!("expected an invalid schema, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_response_checks_json_and_schema() {
+        assert!(validate_response(&ResponseFormat::Json, &response("[1, 2]")).is_ok());
+        assert!(matches!(
+            validate_response(&ResponseFormat::Json, &response("Sure! Here it is")),
+            Err(InferenceError::InvalidResponse(_))
+        ));
+
+        assert!(validate_response(&schema(), &response(r#"{"approved": true}"#)).is_ok());
+        match validate_response(&schema(), &response(r#"{"approved": "yes"}"#)) {
+            Err(InferenceError::InvalidResponse(msg)) => {
+                assert!(
+                    msg.contains("verdict") && msg.contains("/approved"),
+                    "{}",
+                    msg
+                )
+            }
+            other => panic!("expected a schema mismatch, got {:?}", other),
+        }
+
+        let mut tool_call = response("");
+        tool_call.tool_calls.push(ToolCall {
+            id: "call_1".to_string(),
+            name: "grep".to_string(),
+            arguments: "{}".to_string(),
+        });
+        assert!(validate_response(&schema(), &tool_call).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_validate_stream_fails_after_last_delta() {
+        let deltas = |parts: &[&str]| -> ChatStream {
+            let deltas: Vec<_> = parts
+                .iter()
+                .map(|p| {
+                    Ok(ChatDelta {
+                        content: p.to_string(),
+                        ..ChatDelta::default()
+                    })
+                })
+                .collect();
+            Box::pin(stream::iter(deltas))
+        };
+
+        let valid: Vec<_> = validate_stream(deltas(&[r#"{"appro"#, r#"ved": false}"#]), schema())
+            .collect()
+            .await;
+        assert_eq!(valid.len(), 2);
+        assert!(valid.iter().all(|d| d.is_ok()));
+
+        let invalid: Vec<_> = validate_stream(deltas(&[r#"{"approved""#, ": 1}"]), schema())
+            .collect()
+            .await;
+        assert_eq!(invalid.len(), 3);
+        assert!(matches!(
+            invalid.last(),
+            Some(Err(InferenceError::InvalidResponse(_)))
+        ));
+    }
+}
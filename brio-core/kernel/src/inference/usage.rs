@@ -0,0 +1,397 @@
+//! Token usage accounting and budgets.
+//!
+//! Every completion a plugin makes through the host is recorded in the
+//! kernel database, keyed by plugin, task, provider and model. Budgets from
+//! `inference.budgets` are checked against the recorded spend before each
+//! call, and recorded tokens are exported as `brio_inference_tokens_total`.
+
+use crate::inference::provider::ChatStream;
+use crate::inference::types::{InferenceError, Usage};
+use crate::infrastructure::config::{BudgetSettings, TokenBudget};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Kernel table holding one row per completion
+pub const USAGE_TABLE: &str = "brio_token_usage";
+
+/// Who a completion is accounted to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageScope {
+    pub plugin_id: String,
+    pub task_id: Option<String>,
+    pub provider: String,
+    /// The model name as the provider knows it.
+    pub model: String,
+}
+
+/// Restricts a usage query; unset fields match everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct UsageFilter {
+    pub plugin_id: Option<String>,
+    pub task_id: Option<String>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+}
+
+/// Token totals for one plugin, task, provider and model.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UsageSummary {
+    pub plugin_id: String,
+    pub task_id: Option<String>,
+    pub provider: String,
+    pub model: String,
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+/// Records token usage and enforces budgets.
+#[derive(Clone)]
+pub struct UsageLedger {
+    pool: SqlitePool,
+    budgets: Arc<BudgetSettings>,
+}
+
+impl UsageLedger {
+    /// Creates the ledger, creating its table if needed.
+    pub async fn new(pool: SqlitePool) -> Result<Self, sqlx::Error> {
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {USAGE_TABLE} (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                plugin_id TEXT NOT NULL,
+                task_id TEXT,
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                prompt_tokens INTEGER NOT NULL,
+                completion_tokens INTEGER NOT NULL,
+                total_tokens INTEGER NOT NULL,
+                created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+            )"
+        ))
+        .execute(&pool)
+        .await?;
+
+        Ok(Self {
+            pool,
+            budgets: Arc::new(BudgetSettings::default()),
+        })
+    }
+
+    pub fn with_budgets(mut self, budgets: BudgetSettings) -> Self {
+        self.budgets = Arc::new(budgets);
+        self
+    }
+
+    /// Records the usage of one completion.
+    pub async fn record(&self, scope: &UsageScope, usage: &Usage) -> Result<(), sqlx::Error> {
+        sqlx::query(&format!(
+            "INSERT INTO {USAGE_TABLE} \
+             (plugin_id, task_id, provider, model, prompt_tokens, completion_tokens, total_tokens) \
+             VALUES (?, ?, ?, ?, ?, ?, ?)"
+        ))
+        .bind(&scope.plugin_id)
+        .bind(&scope.task_id)
+        .bind(&scope.provider)
+        .bind(&scope.model)
+        .bind(usage.prompt_tokens)
+        .bind(usage.completion_tokens)
+        .bind(usage.total_tokens)
+        .execute(&self.pool)
+        .await?;
+
+        for (kind, tokens) in [
+            ("prompt", usage.prompt_tokens),
+            ("completion", usage.completion_tokens),
+        ] {
+            metrics::counter!(
+                "brio_inference_tokens_total",
+                "plugin" => scope.plugin_id.clone(),
+                "provider" => scope.provider.clone(),
+                "model" => scope.model.clone(),
+                "kind" => kind
+            )
+            .increment(u64::from(tokens));
+        }
+        Ok(())
+    }
+
+    /// Records usage carried by the deltas of `stream` as they pass through.
+    pub fn record_stream(&self, stream: ChatStream, scope: UsageScope) -> ChatStream {
+        let ledger = self.clone();
+        Box::pin(stream.then(move |delta| {
+            let ledger = ledger.clone();
+            let scope = scope.clone();
+            async move {
+                if let Ok(usage) = delta.as_ref().map(|d| d.usage.as_ref())
+                    && let Some(usage) = usage
+                    && let Err(e) = ledger.record(&scope, usage).await
+                {
+                    warn!(error = %e, "Failed to record streamed token usage");
+                }
+                delta
+            }
+        }))
+    }
+
+    /// Token totals matching `filter`, grouped by plugin, task, provider and model.
+    pub async fn summarize(&self, filter: &UsageFilter) -> Result<Vec<UsageSummary>, sqlx::Error> {
+        let (clause, values) = where_clause(filter);
+        let sql = format!(
+            "SELECT plugin_id, task_id, provider, model, COUNT(*), \
+             SUM(prompt_tokens), SUM(completion_tokens), SUM(total_tokens) \
+             FROM {USAGE_TABLE}{clause} \
+             GROUP BY plugin_id, task_id, provider, model \
+             ORDER BY plugin_id, task_id, provider, model"
+        );
+        let mut query = sqlx::query(&sql);
+        for value in values {
+            query = query.bind(value);
+        }
+
+        let rows = query.fetch_all(&self.pool).await?;
+        rows.iter()
+            .map(|row| {
+                Ok(UsageSummary {
+                    plugin_id: row.try_get(0)?,
+                    task_id: row.try_get(1)?,
+                    provider: row.try_get(2)?,
+                    model: row.try_get(3)?,
+                    requests: row.try_get::<i64, _>(4)? as u64,
+                    prompt_tokens: row.try_get::<i64, _>(5)? as u64,
+                    completion_tokens: row.try_get::<i64, _>(6)? as u64,
+                    total_tokens: row.try_get::<i64, _>(7)? as u64,
+                })
+            })
+            .collect()
+    }
+
+    /// Total tokens recorded for `filter`.
+    pub async fn spent(&self, filter: &UsageFilter) -> Result<u64, sqlx::Error> {
+        let (clause, values) = where_clause(filter);
+        let sql = format!("SELECT COALESCE(SUM(total_tokens), 0) FROM {USAGE_TABLE}{clause}");
+        let mut query = sqlx::query(&sql);
+        for value in values {
+            query = query.bind(value);
+        }
+        let total: i64 = query.fetch_one(&self.pool).await?.try_get(0)?;
+        Ok(total as u64)
+    }
+
+    /// Checks every budget that applies to `scope`. Soft limits are logged
+    /// and counted; a reached hard limit rejects the call. The call that
+    /// crosses a limit is still allowed, so spend may overshoot by one
+    /// completion.
+    ///
+    /// # Errors
+    /// Returns `BudgetExceeded` if a hard limit has been reached, or
+    /// `ProviderError` if the spend cannot be read.
+    pub async fn check_budget(&self, scope: &UsageScope) -> Result<(), InferenceError> {
+        let model = format!("{}/{}", scope.provider, scope.model);
+        let mut checks = Vec::new();
+        if let (Some(budget), Some(task_id)) = (self.budgets.task, &scope.task_id) {
+            let filter = UsageFilter {
+                task_id: Some(task_id.clone()),
+                ..Default::default()
+            };
+            checks.push(("task", task_id.as_str(), budget, filter));
+        }
+        if let Some(budget) = self.budgets.plugins.get(&scope.plugin_id) {
+            let filter = UsageFilter {
+                plugin_id: Some(scope.plugin_id.clone()),
+                ..Default::default()
+            };
+            checks.push(("plugin", scope.plugin_id.as_str(), *budget, filter));
+        }
+        if let Some(budget) = self.budgets.providers.get(&scope.provider) {
+            let filter = UsageFilter {
+                provider: Some(scope.provider.clone()),
+                ..Default::default()
+            };
+            checks.push(("provider", scope.provider.as_str(), *budget, filter));
+        }
+        if let Some(budget) = self.budgets.models.get(&model) {
+            let filter = UsageFilter {
+                provider: Some(scope.provider.clone()),
+                model: Some(scope.model.clone()),
+                ..Default::default()
+            };
+            checks.push(("model", model.as_str(), *budget, filter));
+        }
+
+        for (kind, name, budget, filter) in checks {
+            check_limits(
+                kind,
+                name,
+                budget,
+                self.spent(&filter).await.map_err(|e| {
+                    InferenceError::ProviderError(format!("Failed to read token usage: {}", e))
+                })?,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn check_limits(
+    kind: &str,
+    name: &str,
+    budget: TokenBudget,
+    spent: u64,
+) -> Result<(), InferenceError> {
+    if let Some(hard) = budget.hard_limit
+        && spent >= hard
+    {
+        metrics::counter!("brio_inference_budget_rejections_total", "scope" => kind.to_string())
+            .increment(1);
+        return Err(InferenceError::BudgetExceeded(format!(
+            "{} '{}' has used {} of {} tokens",
+            kind, name, spent, hard
+        )));
+    }
+    if let Some(soft) = budget.soft_limit
+        && spent >= soft
+    {
+        warn!(
+            scope = kind,
+            name,
+            spent,
+            soft_limit = soft,
+            "Token budget soft limit exceeded"
+        );
+        metrics::counter!("brio_inference_budget_warnings_total", "scope" => kind.to_string())
+            .increment(1);
+    }
+    Ok(())
+}
+
+fn where_clause(filter: &UsageFilter) -> (String, Vec<String>) {
+    let fields = [
+        ("plugin_id", &filter.plugin_id),
+        ("task_id", &filter.task_id),
+        ("provider", &filter.provider),
+        ("model", &filter.model),
+    ];
+    let (columns, values): (Vec<_>, Vec<_>) = fields
+        .into_iter()
+        .filter_map(|(column, value)| value.clone().map(|v| (format!("{column} = ?"), v)))
+        .unzip();
+    if columns.is_empty() {
+        (String::new(), values)
+    } else {
+        (format!(" WHERE {}", columns.join(" AND ")), values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    fn usage(total_tokens: u32) -> Usage {
+        Usage {
+            prompt_tokens: total_tokens / 2,
+            completion_tokens: total_tokens - total_tokens / 2,
+            total_tokens,
+        }
+    }
+
+    fn scope(plugin_id: &str, task_id: &str, model: &str) -> UsageScope {
+        UsageScope {
+            plugin_id: plugin_id.to_string(),
+            task_id: Some(task_id.to_string()),
+            provider: "openai".to_string(),
+            model: model.to_string(),
+        }
+    }
+
+    async fn ledger() -> UsageLedger {
+        let pool = SqlitePoolOptions::new()
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        UsageLedger::new(pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_ledger_summarizes_usage() {
+        let ledger = ledger().await;
+        ledger
+            .record(&scope("coder", "t1", "gpt-4o"), &usage(10))
+            .await
+            .unwrap();
+        ledger
+            .record(&scope("coder", "t1", "gpt-4o"), &usage(20))
+            .await
+            .unwrap();
+        ledger
+            .record(&scope("reviewer", "t2", "gpt-4o-mini"), &usage(5))
+            .await
+            .unwrap();
+
+        let all = ledger.summarize(&UsageFilter::default()).await.unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].plugin_id, "coder");
+        assert_eq!(all[0].requests, 2);
+        assert_eq!(all[0].prompt_tokens, 15);
+        assert_eq!(all[0].total_tokens, 30);
+
+        let filter = UsageFilter {
+            task_id: Some("t2".to_string()),
+            ..Default::default()
+        };
+        let task = ledger.summarize(&filter).await.unwrap();
+        assert_eq!(task.len(), 1);
+        assert_eq!(task[0].model, "gpt-4o-mini");
+        assert_eq!(ledger.spent(&filter).await.unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_ledger_enforces_budgets() {
+        let mut budgets = BudgetSettings {
+            task: Some(TokenBudget {
+                soft_limit: Some(10),
+                hard_limit: Some(100),
+            }),
+            ..Default::default()
+        };
+        budgets.models.insert(
+            "openai/gpt-4o".to_string(),
+            TokenBudget {
+                soft_limit: None,
+                hard_limit: Some(50),
+            },
+        );
+        let ledger = ledger().await.with_budgets(budgets);
+
+        let coder = scope("coder", "t1", "gpt-4o");
+        ledger.check_budget(&coder).await.unwrap();
+        ledger.record(&coder, &usage(60)).await.unwrap();
+        // Past the soft task limit, but the model limit is what rejects
+        match ledger.check_budget(&coder).await {
+            Err(InferenceError::BudgetExceeded(msg)) => {
+                assert!(msg.contains("model 'openai/gpt-4o'"), "{}", msg)
+            }
+            other => panic!("expected BudgetExceeded, got {:?}", other),
+        }
+
+        let mini = scope("coder", "t1", "gpt-4o-mini");
+        ledger.check_budget(&mini).await.unwrap();
+        ledger.record(&mini, &usage(40)).await.unwrap();
+        match ledger.check_budget(&mini).await {
+            Err(InferenceError::BudgetExceeded(msg)) => {
+                assert!(msg.contains("task 't1'"), "{}", msg)
+            }
+            other => panic!("expected BudgetExceeded, got {:?}", other),
+        }
+
+        // Other tasks have budgets of their own
+        ledger
+            .check_budget(&scope("coder", "t2", "gpt-4o-mini"))
+            .await
+            .unwrap();
+    }
+}
@@ -21,6 +21,25 @@ pub enum AuditEvent {
         old_val: String,
         new_val: String,
     },
+    ResourceLimitExceeded {
+        plugin_id: String,
+        resource: String,
+        requested: usize,
+        limit: usize,
+    },
+    PluginLoaded {
+        plugin_id: String,
+        version: Option<String>,
+        path: String,
+    },
+    PluginReloaded {
+        plugin_id: String,
+        version: Option<String>,
+        path: String,
+    },
+    PluginUnloaded {
+        plugin_id: String,
+    },
 }
 
 /// Logs an audit event to the dedicated audit channel as structured JSON.
@@ -57,5 +76,19 @@ mod tests {
             old_val: "80".into(),
             new_val: "8080".into(),
         });
+        log_audit(AuditEvent::ResourceLimitExceeded {
+            plugin_id: "agent".into(),
+            resource: "memory".into(),
+            requested: 2048,
+            limit: 1024,
+        });
+        log_audit(AuditEvent::PluginReloaded {
+            plugin_id: "agent".into(),
+            version: Some("0.2.0".into()),
+            path: "plugins/agent.wasm".into(),
+        });
+        log_audit(AuditEvent::PluginUnloaded {
+            plugin_id: "agent".into(),
+        });
     }
 }
@@ -1,6 +1,10 @@
+use crate::engine::budget::CpuBudget;
+use crate::engine::limits::ResourceLimits;
+use crate::store::TableGrant;
 use config::{Config, ConfigError, Environment};
 use secrecy::SecretString;
 use serde::Deserialize;
+use std::collections::HashMap;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Settings {
@@ -11,12 +15,175 @@ pub struct Settings {
     pub inference: Option<InferenceSettings>,
     #[serde(default)]
     pub sandbox: SandboxSettings,
+    #[serde(default)]
+    pub engine: EngineSettings,
+    #[serde(default)]
+    pub plugins: PluginSettings,
+    #[serde(default)]
+    pub storage: StorageSettings,
+    #[serde(default)]
+    pub planner: PlannerSettings,
 }
 
-#[derive(Debug, Deserialize, Clone, Default)]
+/// Backing for the host `planner` interface.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PlannerSettings {
+    /// Planner plugin to delegate to; defaults to the first registered one.
+    pub plugin: Option<String>,
+    /// Provider for the inference planner; defaults to the registry default.
+    pub provider: Option<String>,
+    #[serde(default = "default_planner_model")]
+    pub model: String,
+}
+
+impl Default for PlannerSettings {
+    fn default() -> Self {
+        Self {
+            plugin: None,
+            provider: None,
+            model: default_planner_model(),
+        }
+    }
+}
+
+fn default_planner_model() -> String {
+    crate::planner::inference::DEFAULT_PLANNER_MODEL.to_string()
+}
+
+/// Plugin SQL access beyond each plugin's own `<plugin_id>_` tables.
+#[derive(Debug, Deserialize, Clone)]
+pub struct StorageSettings {
+    /// Shared tables keyed by name, with the plugins allowed to use them.
+    #[serde(default = "default_shared_tables")]
+    pub shared_tables: HashMap<String, TableGrant>,
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        Self {
+            shared_tables: default_shared_tables(),
+        }
+    }
+}
+
+/// The task queue written by the foreman and driven by the supervisor.
+fn default_shared_tables() -> HashMap<String, TableGrant> {
+    HashMap::from([(
+        "tasks".to_string(),
+        TableGrant {
+            read: vec![],
+            write: vec!["supervisor".to_string(), "foreman".to_string()],
+        },
+    )])
+}
+
+/// Plugin directory hot reload.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PluginSettings {
+    /// Reload plugins when the plugins directory changes.
+    #[serde(default = "default_true")]
+    pub watch: bool,
+    /// Interval between scans of the plugins directory.
+    #[serde(default = "default_watch_interval_ms")]
+    pub watch_interval_ms: u64,
+    /// How long a replaced plugin version may finish in-flight invocations.
+    #[serde(default = "default_drain_timeout_ms")]
+    pub drain_timeout_ms: u64,
+}
+
+impl Default for PluginSettings {
+    fn default() -> Self {
+        Self {
+            watch: true,
+            watch_interval_ms: default_watch_interval_ms(),
+            drain_timeout_ms: default_drain_timeout_ms(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_watch_interval_ms() -> u64 {
+    1000
+}
+
+fn default_drain_timeout_ms() -> u64 {
+    30_000
+}
+
+/// WASM runtime limits applied to plugin invocations.
+#[derive(Debug, Deserialize, Clone)]
+pub struct EngineSettings {
+    /// Interval between epoch ticks; epoch deadlines are expressed in these ticks.
+    /// A host call the guest awaits counts as at most one tick.
+    #[serde(default = "default_epoch_tick_ms")]
+    pub epoch_tick_ms: u64,
+    /// Budget for plugins without an explicit entry in `plugin_budgets`.
+    #[serde(default)]
+    pub default_budget: CpuBudget,
+    /// Per-plugin budget overrides keyed by plugin ID.
+    #[serde(default)]
+    pub plugin_budgets: HashMap<String, CpuBudget>,
+    /// Memory and table limits for plugins without an entry in `plugin_limits`.
+    #[serde(default)]
+    pub default_limits: ResourceLimits,
+    /// Per-plugin resource limit overrides keyed by plugin ID.
+    #[serde(default)]
+    pub plugin_limits: HashMap<String, ResourceLimits>,
+    /// Directory for precompiled components; compiled artifacts stay in memory only when unset.
+    #[serde(default)]
+    pub component_cache_dir: Option<String>,
+}
+
+impl Default for EngineSettings {
+    fn default() -> Self {
+        Self {
+            epoch_tick_ms: default_epoch_tick_ms(),
+            default_budget: CpuBudget::default(),
+            plugin_budgets: HashMap::new(),
+            default_limits: ResourceLimits::default(),
+            plugin_limits: HashMap::new(),
+            component_cache_dir: None,
+        }
+    }
+}
+
+fn default_epoch_tick_ms() -> u64 {
+    100
+}
+
+#[derive(Debug, Deserialize, Clone)]
 pub struct SandboxSettings {
     #[serde(default)]
     pub allowed_paths: Vec<String>,
+    /// Globs, relative to a session's base, of paths sessions never copy,
+    /// hash or commit (e.g. `target`, `**/*.log`).
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Also exclude what `.gitignore` and `.brioignore` files in the base
+    /// ignore.
+    #[serde(default = "default_true")]
+    pub ignore_files: bool,
+    /// Seconds without `session-fs` file access or commit attempts after
+    /// which an open session is rolled back, reclaiming sessions left behind
+    /// by crashed agents. Access through WASI preopens does not count.
+    /// Checked on startup and whenever a session begins; unset keeps sessions
+    /// until committed or rolled back.
+    #[serde(default)]
+    pub session_ttl_secs: Option<u64>,
+}
+
+impl Default for SandboxSettings {
+    fn default() -> Self {
+        Self {
+            allowed_paths: Vec::new(),
+            exclude: Vec::new(),
+            ignore_files: true,
+            session_ttl_secs: None,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -50,9 +217,164 @@ pub struct MeshSettings {
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct InferenceSettings {
+    /// Legacy single-provider keys, used only when `providers` is empty.
     pub openai_api_key: Option<SecretString>,
     pub anthropic_api_key: Option<SecretString>,
     pub openai_base_url: Option<String>,
+    /// Named providers, e.g. `BRIO__INFERENCE__PROVIDERS__CLAUDE__KIND=anthropic`.
+    #[serde(default)]
+    pub providers: HashMap<String, ProviderSettings>,
+    /// Provider or fallback chain serving bare model names; required with
+    /// several providers.
+    pub default_provider: Option<String>,
+    /// Named chains of `provider` or `provider/model` entries, tried in order
+    /// while providers are rate limited, unreachable or circuit-broken.
+    #[serde(default)]
+    pub fallback_chains: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerSettings,
+    /// Broadcast streamed completion deltas to WebSocket clients.
+    #[serde(default)]
+    pub stream_patches: bool,
+    /// Short model names, e.g. `fast = "local/llama-3"`.
+    #[serde(default)]
+    pub model_aliases: HashMap<String, String>,
+    /// Models each plugin may use, as `provider/model` or `provider/*`.
+    /// Plugins without an entry may use any model.
+    #[serde(default)]
+    pub allowed_models: HashMap<String, Vec<String>>,
+    /// Token budgets checked before each guest `chat`.
+    #[serde(default)]
+    pub budgets: BudgetSettings,
+    #[serde(default)]
+    pub context: ContextSettings,
+}
+
+/// Compaction of chat histories that outgrow the model's context window.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ContextSettings {
+    /// Off by default: guests then see `ContextLengthExceeded` as-is.
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub strategy: crate::inference::ContextStrategy,
+    /// Tokens kept free for the answer when the request sets no `max_tokens`.
+    #[serde(default = "default_reserve_tokens")]
+    pub reserve_tokens: u32,
+    /// Model writing summaries for `summarize`; defaults to the called model.
+    pub summary_model: Option<String>,
+    /// Context windows by model or `provider/model`, overriding the
+    /// built-in catalog.
+    #[serde(default)]
+    pub context_windows: HashMap<String, u32>,
+}
+
+impl Default for ContextSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            strategy: Default::default(),
+            reserve_tokens: default_reserve_tokens(),
+            summary_model: None,
+            context_windows: HashMap::new(),
+        }
+    }
+}
+
+fn default_reserve_tokens() -> u32 {
+    4096
+}
+
+/// Token budgets by scope. Spend is the `total_tokens` recorded in the usage
+/// ledger; a call is rejected once a hard limit has been reached.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct BudgetSettings {
+    /// Applies to every task (agent run) separately.
+    pub task: Option<TokenBudget>,
+    /// By plugin id.
+    #[serde(default)]
+    pub plugins: HashMap<String, TokenBudget>,
+    /// By provider name.
+    #[serde(default)]
+    pub providers: HashMap<String, TokenBudget>,
+    /// By `provider/model`.
+    #[serde(default)]
+    pub models: HashMap<String, TokenBudget>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenBudget {
+    /// Spend above which calls are logged and counted, but still allowed.
+    pub soft_limit: Option<u64>,
+    /// Spend at which further calls fail with `BudgetExceeded`.
+    pub hard_limit: Option<u64>,
+}
+
+/// Per-provider circuit breakers.
+#[derive(Debug, Deserialize, Clone)]
+pub struct CircuitBreakerSettings {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Consecutive rate limit or network failures that open a breaker.
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    /// How long an open breaker rejects calls before probing the provider.
+    #[serde(default = "default_cooldown_ms")]
+    pub cooldown_ms: u64,
+}
+
+impl Default for CircuitBreakerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            failure_threshold: default_failure_threshold(),
+            cooldown_ms: default_cooldown_ms(),
+        }
+    }
+}
+
+fn default_failure_threshold() -> u32 {
+    crate::inference::breaker::DEFAULT_FAILURE_THRESHOLD
+}
+
+fn default_cooldown_ms() -> u64 {
+    crate::inference::breaker::DEFAULT_COOLDOWN.as_millis() as u64
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// OpenAI-compatible chat completions API (OpenAI, OpenRouter, local servers).
+    OpenAI,
+    Anthropic,
+    /// Offline provider echoing the last message.
+    Mock,
+}
+
+/// A provider registered in the `ProviderRegistry` under its settings key.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ProviderSettings {
+    pub kind: ProviderKind,
+    /// Defaults to the vendor's public API.
+    pub base_url: Option<String>,
+    /// Required for `openai` and `anthropic`.
+    pub api_key: Option<SecretString>,
+    pub max_retries: Option<u32>,
+    pub base_delay_ms: Option<u64>,
+    pub max_tokens: Option<u32>,
+    /// Aliases for this provider's models, e.g. `sonnet = "claude-sonnet-4-5"`.
+    #[serde(default)]
+    pub models: HashMap<String, String>,
+    /// Records or replays the provider's completions.
+    pub cassette: Option<CassetteSettings>,
+}
+
+/// A cassette file wrapping a provider. In `strict` mode the provider itself
+/// is never called, so it needs no `api_key`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct CassetteSettings {
+    pub path: std::path::PathBuf,
+    pub mode: crate::inference::CassetteMode,
 }
 
 impl Settings {
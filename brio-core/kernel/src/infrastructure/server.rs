@@ -1,8 +1,17 @@
+use crate::inference::{UsageFilter, UsageLedger, UsageSummary};
 use crate::infrastructure::config::Settings;
+use crate::vfs::diff::FileDiff;
+use crate::vfs::manager::SessionManager;
 use crate::ws::{Broadcaster, handler::ws_router};
-use axum::{Router, routing::get};
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::get,
+};
 use metrics_exporter_prometheus::PrometheusBuilder;
 use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
 
 #[cfg(unix)]
 use pprof::protos::Message;
@@ -48,6 +57,45 @@ async fn pprof_profile() -> impl axum::response::IntoResponse {
     }
 }
 
+/// Token usage totals, filtered by `plugin_id`, `task_id`, `provider` and
+/// `model` query parameters.
+async fn usage_report(
+    State(usage): State<UsageLedger>,
+    Query(filter): Query<UsageFilter>,
+) -> Result<Json<Vec<UsageSummary>>, (StatusCode, String)> {
+    usage.summarize(&filter).await.map(Json).map_err(|e| {
+        tracing::error!("Failed to query token usage: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    })
+}
+
+/// Per-file unified diffs of what a session changed, before it is committed.
+/// The session trees are read on the blocking pool, off the session lock.
+async fn session_diff(
+    State(sessions): State<Arc<Mutex<SessionManager>>>,
+    Path(session_id): Path<String>,
+) -> Result<Json<Vec<FileDiff>>, (StatusCode, String)> {
+    let failed = |e: String| {
+        tracing::error!("Failed to diff session {}: {}", session_id, e);
+        (StatusCode::INTERNAL_SERVER_ERROR, e)
+    };
+    let inputs = {
+        let manager = sessions.lock().expect("Mutex poisoned");
+        if manager.session(&session_id).is_none() {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("Session not found: {}", session_id),
+            ));
+        }
+        manager.diff_inputs(&session_id).map_err(failed)?
+    };
+    tokio::task::spawn_blocking(move || inputs.diff())
+        .await
+        .map_err(|e| failed(format!("Diff task failed: {}", e)))?
+        .map(Json)
+        .map_err(failed)
+}
+
 #[cfg(not(unix))]
 async fn pprof_profile() -> impl axum::response::IntoResponse {
     (
@@ -57,7 +105,12 @@ async fn pprof_profile() -> impl axum::response::IntoResponse {
 }
 
 /// Runs the control plane HTTP server with WebSocket support.
-pub async fn run_server(config: &Settings, broadcaster: Broadcaster) -> anyhow::Result<()> {
+pub async fn run_server(
+    config: &Settings,
+    broadcaster: Broadcaster,
+    usage: UsageLedger,
+    sessions: Arc<Mutex<SessionManager>>,
+) -> anyhow::Result<()> {
     let builder = PrometheusBuilder::new();
     let handle = builder
         .install_recorder()
@@ -69,7 +122,18 @@ pub async fn run_server(config: &Settings, broadcaster: Broadcaster) -> anyhow::
         .route("/metrics", get(move || std::future::ready(handle.render())))
         .route("/debug/pprof/profile", get(pprof_profile));
 
-    let app = control_plane.merge(ws_router(broadcaster));
+    let usage_api = Router::new()
+        .route("/api/v1/usage", get(usage_report))
+        .with_state(usage);
+
+    let sessions_api = Router::new()
+        .route("/api/v1/sessions/{id}/diff", get(session_diff))
+        .with_state(sessions);
+
+    let app = control_plane
+        .merge(usage_api)
+        .merge(sessions_api)
+        .merge(ws_router(broadcaster));
 
     let addr_str = format!("{}:{}", config.server.host, config.server.port);
     let addr: SocketAddr = addr_str.parse()?;
@@ -3,6 +3,7 @@ pub mod host;
 pub mod inference;
 pub mod infrastructure;
 pub mod mesh;
+pub mod planner;
 pub mod registry;
 pub mod store;
 pub mod vfs;
@@ -3,7 +3,7 @@ use brio_kernel::host::BrioHostState;
 use brio_kernel::infrastructure::{audit, config::Settings, server, telemetry::TelemetryBuilder};
 use secrecy::ExposeSecret;
 use tokio::signal;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -34,8 +34,28 @@ async fn main() -> anyhow::Result<()> {
     let engine =
         wasmtime::Engine::new(&engine_config).context("Failed to create Wasmtime engine")?;
 
+    // Advance the engine epoch so per-plugin epoch deadlines can fire
+    let _epoch_ticker = brio_kernel::engine::budget::spawn_epoch_ticker(
+        engine.clone(),
+        std::time::Duration::from_millis(config.engine.epoch_tick_ms),
+    );
+
     // Initialize Plugin Registry
-    let mut plugin_registry = brio_kernel::registry::PluginRegistry::new(engine);
+    let mut plugin_registry = brio_kernel::registry::PluginRegistry::new(engine)
+        .with_drain_timeout(std::time::Duration::from_millis(
+            config.plugins.drain_timeout_ms,
+        ))
+        .with_cpu_budgets(
+            config.engine.default_budget,
+            config.engine.plugin_budgets.clone(),
+        )
+        .with_resource_limits(
+            config.engine.default_limits,
+            config.engine.plugin_limits.clone(),
+        );
+    if let Some(ref dir) = config.engine.component_cache_dir {
+        plugin_registry = plugin_registry.with_component_cache_dir(dir);
+    }
     let plugins_dir = std::env::current_dir().unwrap_or_default().join("plugins");
 
     // Scan for plugins
@@ -52,29 +72,17 @@ async fn main() -> anyhow::Result<()> {
 
     let db_url = config.database.url.expose_secret();
 
-    // Clean Code: Configure Provider (DIP)
-    let openai_key = config
-        .inference
-        .as_ref()
-        .and_then(|i| i.openai_api_key.clone())
-        .unwrap_or_else(|| secrecy::SecretString::new("sk-placeholder".into()));
-
-    let openai_base = config
-        .inference
-        .as_ref()
-        .and_then(|i| i.openai_base_url.clone())
-        .unwrap_or("https://openrouter.ai/api/v1/".to_string());
-
-    let provider_config = brio_kernel::inference::OpenAIConfig::new(
-        openai_key,
-        reqwest::Url::parse(&openai_base).context("Invalid OpenAI Base URL")?,
-    );
-    let provider = brio_kernel::inference::OpenAIProvider::new(provider_config);
-
-    // Create registry (common for both modes)
-    let registry = brio_kernel::inference::ProviderRegistry::new();
-    registry.register_arc("default", std::sync::Arc::new(provider));
-    registry.set_default("default");
+    // Build and validate the configured LLM providers
+    let registry = match &config.inference {
+        Some(inference) => brio_kernel::inference::ProviderRegistry::from_settings(inference)
+            .context("Invalid inference configuration")?,
+        None => brio_kernel::inference::ProviderRegistry::new(),
+    };
+    if registry.is_empty() {
+        warn!("No inference providers configured; guest inference calls will fail");
+    } else {
+        info!(providers = ?registry.list_providers(), "Inference providers configured");
+    }
 
     // Check for distributed config
     let mesh_config = config.mesh.clone();
@@ -90,31 +98,69 @@ async fn main() -> anyhow::Result<()> {
 
     let state = if let Some(ref id) = node_id {
         info!("Initializing in Distributed Mode (Node ID: {})", id);
-        std::sync::Arc::new(
-            BrioHostState::new_distributed(
-                db_url,
-                registry,
-                Some(plugin_registry.clone()),
-                id.clone(),
-                config.sandbox.clone(),
-            )
-            .await
-            .context("Failed to initialize distributed host state")?,
+        BrioHostState::new_distributed(
+            db_url,
+            registry,
+            Some(plugin_registry.clone()),
+            id.clone(),
+            config.sandbox.clone(),
         )
+        .await
+        .context("Failed to initialize distributed host state")?
     } else {
         info!("Initializing in Standalone Mode");
-        std::sync::Arc::new(
-            BrioHostState::new(
-                db_url,
-                registry,
-                Some(plugin_registry.clone()),
-                config.sandbox.clone(),
-            )
-            .await
-            .context("Failed to initialize host state")?,
+        BrioHostState::new(
+            db_url,
+            registry,
+            Some(plugin_registry.clone()),
+            config.sandbox.clone(),
         )
+        .await
+        .context("Failed to initialize host state")?
     };
 
+    let mut planner =
+        brio_kernel::planner::InferencePlanner::new().with_model(config.planner.model.clone());
+    if let Some(provider) = &config.planner.provider {
+        planner = planner.with_provider(provider.clone());
+    }
+    let mut state = state
+        .with_shared_tables(config.storage.shared_tables.clone())
+        .with_planner(std::sync::Arc::new(planner))
+        .with_stream_patches(config.inference.as_ref().is_some_and(|i| i.stream_patches))
+        .with_allowed_models(
+            config
+                .inference
+                .as_ref()
+                .map(|i| i.allowed_models.clone())
+                .unwrap_or_default(),
+        )
+        .with_token_budgets(
+            config
+                .inference
+                .as_ref()
+                .map(|i| i.budgets.clone())
+                .unwrap_or_default(),
+        )
+        .with_context_management(
+            config
+                .inference
+                .as_ref()
+                .map(|i| i.context.clone())
+                .unwrap_or_default(),
+        );
+    if let Some(plugin_id) = &config.planner.plugin {
+        state = state.with_planner_plugin(plugin_id.clone());
+    }
+
+    // Sessions persisted by an earlier run were restored; the rest are abandoned
+    match state.cleanup_orphaned_sessions() {
+        Ok(0) => {}
+        Ok(n) => info!("Removed {} abandoned session directories", n),
+        Err(e) => warn!("Failed to clean up abandoned sessions: {}", e),
+    }
+    let state = std::sync::Arc::new(state);
+
     // Start gRPC server if distributed
     if let Some(id) = node_id {
         let state_clone = state.clone();
@@ -146,10 +192,21 @@ async fn main() -> anyhow::Result<()> {
         });
     }
 
+    // Hot reload plugins as the directory changes
+    if config.plugins.watch {
+        brio_kernel::registry::spawn_plugin_watcher(
+            state.clone(),
+            plugins_dir.clone(),
+            std::time::Duration::from_millis(config.plugins.watch_interval_ms),
+        );
+    }
+
     let broadcaster = state.broadcaster().clone();
+    let usage = state.usage().clone();
+    let sessions = state.sessions();
     let server_config = config.clone();
     tokio::spawn(async move {
-        if let Err(e) = server::run_server(&server_config, broadcaster).await {
+        if let Err(e) = server::run_server(&server_config, broadcaster, usage, sessions).await {
             error!("Control Plane failed: {:?}", e);
         }
     });
@@ -17,6 +17,15 @@ impl EventBus {
         subs.entry(topic).or_default().insert(plugin_id);
     }
 
+    /// Removes every subscription held by `plugin_id`.
+    pub fn unsubscribe_all(&self, plugin_id: &str) {
+        let mut subs = self.subscriptions.write().expect("RwLock poisoned");
+        for subscribers in subs.values_mut() {
+            subscribers.remove(plugin_id);
+        }
+        subs.retain(|_, subscribers| !subscribers.is_empty());
+    }
+
     pub fn subscribers(&self, topic: &str) -> Vec<String> {
         let subs = self.subscriptions.read().expect("RwLock poisoned");
         subs.get(topic)
@@ -0,0 +1,211 @@
+use super::{Plan, Planner, PlannerError, Subtask};
+use crate::inference::{ChatRequest, LLMProvider, Message};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashSet;
+use tracing::debug;
+
+/// Model used when none is configured.
+pub const DEFAULT_PLANNER_MODEL: &str = "gpt-4o-mini";
+
+const SYSTEM_PROMPT: &str = "You are the planner of a multi-agent coding system. \
+Decompose the user's objective into a short, ordered list of concrete subtasks, each \
+small enough for a single agent. If the objective is already a single step, return \
+no steps. Reply with JSON only, in the form \
+{\"steps\": [{\"id\": \"step-1\", \"description\": \"...\"}]}, \
+using unique, short ids.";
+
+/// Plans by prompting an LLM for a JSON plan.
+pub struct InferencePlanner {
+    provider: Option<String>,
+    model: String,
+}
+
+impl Default for InferencePlanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InferencePlanner {
+    pub fn new() -> Self {
+        Self {
+            provider: None,
+            model: DEFAULT_PLANNER_MODEL.to_string(),
+        }
+    }
+
+    /// Uses the named provider instead of the registry's default.
+    pub fn with_provider(mut self, provider: impl Into<String>) -> Self {
+        self.provider = Some(provider.into());
+        self
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// The model as the host resolves it: `provider/model` or bare.
+    fn model_name(&self) -> String {
+        match &self.provider {
+            Some(provider) => format!("{}/{}", provider, self.model),
+            None => self.model.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl Planner for InferencePlanner {
+    async fn decompose(
+        &self,
+        inference: &dyn LLMProvider,
+        objective: &str,
+    ) -> Result<Plan, PlannerError> {
+        let request = ChatRequest::new(
+            self.model_name(),
+            vec![Message::system(SYSTEM_PROMPT), Message::user(objective)],
+        );
+
+        let response = inference.chat(request).await?;
+        let plan = parse_plan(&response.content)?;
+        debug!(steps = plan.steps.len(), "Decomposed objective");
+        Ok(plan)
+    }
+}
+
+#[derive(Deserialize)]
+struct RawPlan {
+    steps: Vec<RawSubtask>,
+}
+
+#[derive(Deserialize)]
+struct RawSubtask {
+    #[serde(default)]
+    id: Option<String>,
+    description: String,
+}
+
+/// Parses a model reply into a plan, tolerating surrounding prose or code
+/// fences. Missing ids are filled in as `step-<n>`.
+fn parse_plan(reply: &str) -> Result<Plan, PlannerError> {
+    let json = match (reply.find('{'), reply.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &reply[start..=end],
+        _ => return Err(PlannerError::InvalidPlan("reply contains no JSON".into())),
+    };
+    let raw: RawPlan =
+        serde_json::from_str(json).map_err(|e| PlannerError::InvalidPlan(e.to_string()))?;
+
+    let mut ids = HashSet::new();
+    let mut steps = Vec::with_capacity(raw.steps.len());
+    for (index, step) in raw.steps.into_iter().enumerate() {
+        let description = step.description.trim().to_string();
+        if description.is_empty() {
+            return Err(PlannerError::InvalidPlan(format!(
+                "step {} has no description",
+                index + 1
+            )));
+        }
+        let id = match step.id.map(|id| id.trim().to_string()) {
+            Some(id) if !id.is_empty() => id,
+            _ => format!("step-{}", index + 1),
+        };
+        if !ids.insert(id.clone()) {
+            return Err(PlannerError::InvalidPlan(format!(
+                "duplicate step id '{}'",
+                id
+            )));
+        }
+        steps.push(Subtask { id, description });
+    }
+    Ok(Plan { steps })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inference::{ChatResponse, InferenceError};
+    use std::sync::{Arc, Mutex};
+
+    struct ScriptedProvider {
+        reply: String,
+        requests: Arc<Mutex<Vec<ChatRequest>>>,
+    }
+
+    #[async_trait]
+    impl LLMProvider for ScriptedProvider {
+        async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+            self.requests.lock().expect("Mutex poisoned").push(request);
+            Ok(ChatResponse {
+                content: self.reply.clone(),
+                usage: None,
+                tool_calls: Vec::new(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decompose_prompts_the_model() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let inference = ScriptedProvider {
+            reply: "```json\n{\"steps\": [{\"id\": \"tests\", \"description\": \"Write tests\"}, \
+                    {\"description\": \"Fix the parser\"}]}\n```"
+                .to_string(),
+            requests: requests.clone(),
+        };
+
+        let planner = InferencePlanner::new().with_model("planner-model");
+        let plan = planner
+            .decompose(&inference, "Fix the parser bug")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            plan.steps,
+            vec![
+                Subtask {
+                    id: "tests".to_string(),
+                    description: "Write tests".to_string()
+                },
+                Subtask {
+                    id: "step-2".to_string(),
+                    description: "Fix the parser".to_string()
+                },
+            ]
+        );
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0].model, "planner-model");
+        assert_eq!(requests[0].messages[1].content, "Fix the parser bug");
+    }
+
+    #[tokio::test]
+    async fn test_decompose_qualifies_configured_provider() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let inference = ScriptedProvider {
+            reply: r#"{"steps": []}"#.to_string(),
+            requests: requests.clone(),
+        };
+
+        let planner = InferencePlanner::new()
+            .with_provider("fast")
+            .with_model("planner-model");
+        let plan = planner.decompose(&inference, "anything").await.unwrap();
+
+        assert!(plan.steps.is_empty());
+        assert_eq!(requests.lock().unwrap()[0].model, "fast/planner-model");
+    }
+
+    #[test]
+    fn test_parse_plan_rejects_invalid_replies() {
+        assert!(parse_plan("I cannot help with that").is_err());
+        assert!(parse_plan(r#"{"steps": [{"description": ""}]}"#).is_err());
+        assert!(
+            parse_plan(
+                r#"{"steps": [{"id": "a", "description": "x"}, {"id": "a", "description": "y"}]}"#
+            )
+            .is_err()
+        );
+        assert_eq!(parse_plan(r#"{"steps": []}"#).unwrap(), Plan::default());
+    }
+}
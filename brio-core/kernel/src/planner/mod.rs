@@ -0,0 +1,50 @@
+//! Task decomposition for the host `planner` interface.
+//!
+//! `BrioHostState` answers `planner::decompose` with a planner plugin when one
+//! is registered, and otherwise with its host planner (by default an
+//! [`InferencePlanner`]). The host planner prompts models through the
+//! caller's view of the host, so its calls are allow-listed, budgeted and
+//! accounted like the caller's own.
+
+pub mod inference;
+
+pub use inference::InferencePlanner;
+
+use crate::inference::{InferenceError, LLMProvider};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// A sub-step of a larger task.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Subtask {
+    pub id: String,
+    pub description: String,
+}
+
+/// A sequence of steps achieving an objective. An empty plan means the
+/// objective needs no decomposition.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Plan {
+    pub steps: Vec<Subtask>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PlannerError {
+    #[error("Inference failed: {0}")]
+    Inference(#[from] InferenceError),
+    #[error("Invalid plan: {0}")]
+    InvalidPlan(String),
+    #[error("Planner plugin '{0}' failed: {1}")]
+    Plugin(String, String),
+}
+
+#[async_trait]
+pub trait Planner: Send + Sync {
+    /// Decomposes a high-level objective into actionable subtasks, prompting
+    /// models, if at all, through `inference`.
+    async fn decompose(
+        &self,
+        inference: &dyn LLMProvider,
+        objective: &str,
+    ) -> Result<Plan, PlannerError>;
+}
@@ -0,0 +1,219 @@
+//! Plugin manifests.
+//!
+//! A plugin is either a bare `<id>.wasm` with an optional sidecar `<id>.toml`,
+//! or a directory containing `plugin.toml` next to its component:
+//!
+//! ```toml
+//! id = "coder"
+//! version = "0.1.0"
+//! component = "coder.wasm"          # optional, defaults to "<id>.wasm"
+//! permissions = ["ai:inference", "fs:write"]
+//! exports = ["agent-runner"]
+//! subscriptions = ["task.created"]
+//! ```
+
+use crate::store::policy::KERNEL_SCOPE;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// File name of the manifest inside a plugin directory.
+pub const MANIFEST_FILE: &str = "plugin.toml";
+
+/// Permissions a manifest may request.
+pub const KNOWN_PERMISSIONS: &[&str] = &[
+    "mesh:send",
+    "storage:read",
+    "storage:write",
+    "ai:inference",
+    "fs:read",
+    "fs:write",
+    "net:outbound",
+];
+
+/// Interfaces a plugin may export.
+pub const KNOWN_EXPORTS: &[&str] = &["agent-runner", "event-handler", "tool", "planner"];
+
+#[derive(Debug, Error)]
+pub enum ManifestError {
+    #[error("Failed to read manifest {0:?}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("Failed to parse manifest {0:?}: {1}")]
+    Parse(PathBuf, toml::de::Error),
+    #[error("Invalid plugin id '{0}': use lowercase letters, digits and '-'")]
+    InvalidId(String),
+    #[error("Plugin id '{0}' is reserved for the kernel")]
+    ReservedId(String),
+    #[error("Invalid version '{0}': {1}")]
+    InvalidVersion(String, semver::Error),
+    #[error("Unknown permission '{0}'")]
+    UnknownPermission(String),
+    #[error("Unknown exported interface '{0}'")]
+    UnknownExport(String),
+    #[error("Invalid subscription topic '{0}'")]
+    InvalidTopic(String),
+    #[error("Invalid component path '{0}': must be a file next to the manifest")]
+    InvalidComponent(String),
+}
+
+/// Declared identity and capabilities of a plugin.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginManifest {
+    pub id: String,
+    pub version: String,
+    #[serde(default)]
+    pub component: Option<String>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    #[serde(default)]
+    pub exports: Vec<String>,
+    #[serde(default)]
+    pub subscriptions: Vec<String>,
+}
+
+impl PluginManifest {
+    /// Reads and validates the manifest at `path`.
+    pub fn load(path: &Path) -> Result<Self, ManifestError> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| ManifestError::Io(path.to_path_buf(), e))?;
+        Self::parse(&contents).map_err(|e| match e {
+            ManifestError::Parse(_, err) => ManifestError::Parse(path.to_path_buf(), err),
+            other => other,
+        })
+    }
+
+    /// Parses and validates a manifest from TOML.
+    pub fn parse(contents: &str) -> Result<Self, ManifestError> {
+        let manifest: Self =
+            toml::from_str(contents).map_err(|e| ManifestError::Parse(PathBuf::new(), e))?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    fn validate(&self) -> Result<(), ManifestError> {
+        let valid_id = !self.id.is_empty()
+            && self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !valid_id {
+            return Err(ManifestError::InvalidId(self.id.clone()));
+        }
+        // The id is the plugin's SQL table prefix
+        if self.id == KERNEL_SCOPE {
+            return Err(ManifestError::ReservedId(self.id.clone()));
+        }
+
+        semver::Version::parse(&self.version)
+            .map_err(|e| ManifestError::InvalidVersion(self.version.clone(), e))?;
+
+        if let Some(permission) = self
+            .permissions
+            .iter()
+            .find(|p| !KNOWN_PERMISSIONS.contains(&p.as_str()))
+        {
+            return Err(ManifestError::UnknownPermission(permission.clone()));
+        }
+
+        if let Some(export) = self
+            .exports
+            .iter()
+            .find(|e| !KNOWN_EXPORTS.contains(&e.as_str()))
+        {
+            return Err(ManifestError::UnknownExport(export.clone()));
+        }
+
+        if let Some(topic) = self
+            .subscriptions
+            .iter()
+            .find(|t| t.is_empty() || t.chars().any(char::is_whitespace))
+        {
+            return Err(ManifestError::InvalidTopic(topic.clone()));
+        }
+
+        if let Some(component) = &self.component {
+            let path = Path::new(component);
+            if path.components().count() != 1 || path.file_name().is_none() {
+                return Err(ManifestError::InvalidComponent(component.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the component file relative to the manifest's directory.
+    pub fn component_path(&self, dir: &Path) -> PathBuf {
+        match &self.component {
+            Some(component) => dir.join(component),
+            None => dir.join(format!("{}.wasm", self.id)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_valid_manifest() {
+        let manifest = PluginManifest::parse(
+            r#"
+            id = "coder"
+            version = "0.1.0"
+            permissions = ["ai:inference", "fs:write"]
+            exports = ["agent-runner"]
+            subscriptions = ["task.created"]
+            "#,
+        )
+        .expect("valid manifest");
+
+        assert_eq!(manifest.id, "coder");
+        assert_eq!(manifest.permissions, vec!["ai:inference", "fs:write"]);
+        assert_eq!(
+            manifest.component_path(Path::new("/plugins/coder")),
+            PathBuf::from("/plugins/coder/coder.wasm")
+        );
+    }
+
+    #[test]
+    fn test_rejects_unknown_permission() {
+        let result = PluginManifest::parse(
+            r#"
+            id = "coder"
+            version = "0.1.0"
+            permissions = ["root"]
+            "#,
+        );
+        assert!(matches!(result, Err(ManifestError::UnknownPermission(p)) if p == "root"));
+    }
+
+    #[test]
+    fn test_rejects_invalid_version_and_id() {
+        let result = PluginManifest::parse("id = \"coder\"\nversion = \"latest\"");
+        assert!(matches!(result, Err(ManifestError::InvalidVersion(..))));
+
+        let result = PluginManifest::parse("id = \"Coder Agent\"\nversion = \"1.0.0\"");
+        assert!(matches!(result, Err(ManifestError::InvalidId(..))));
+
+        let result = PluginManifest::parse("id = \"coder_v2\"\nversion = \"1.0.0\"");
+        assert!(matches!(result, Err(ManifestError::InvalidId(..))));
+
+        let result = PluginManifest::parse("id = \"brio\"\nversion = \"1.0.0\"");
+        assert!(matches!(result, Err(ManifestError::ReservedId(..))));
+    }
+
+    #[test]
+    fn test_rejects_component_outside_plugin_dir() {
+        let result = PluginManifest::parse(
+            "id = \"coder\"\nversion = \"1.0.0\"\ncomponent = \"../other.wasm\"",
+        );
+        assert!(matches!(result, Err(ManifestError::InvalidComponent(..))));
+    }
+
+    #[test]
+    fn test_rejects_unknown_fields() {
+        let result = PluginManifest::parse("id = \"coder\"\nversion = \"1.0.0\"\nadmin = true");
+        assert!(matches!(result, Err(ManifestError::Parse(..))));
+    }
+}
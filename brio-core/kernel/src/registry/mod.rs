@@ -0,0 +1,594 @@
+pub mod manifest;
+pub mod watcher;
+pub mod world;
+
+use crate::engine::budget::CpuBudget;
+use crate::engine::cache::ComponentCache;
+use crate::engine::limits::ResourceLimits;
+use crate::engine::linker::create_linker;
+use crate::host::BrioHostState;
+use crate::infrastructure::audit::{self, AuditEvent};
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::fs;
+use tokio::sync::OwnedRwLockReadGuard;
+use tracing::{debug, error, info, warn};
+use wasmtime::component::InstancePre;
+use wasmtime::{Engine, Store};
+
+pub use manifest::{ManifestError, PluginManifest};
+pub use watcher::spawn_plugin_watcher;
+pub use world::{ComponentInterfaces, PluginWorld, WorldError};
+
+/// How long an unloaded plugin version may keep running before it is abandoned.
+const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Metadata about a loaded plugin.
+///
+/// Plugins loaded without a manifest get no permissions.
+#[derive(Debug, Clone)]
+pub struct PluginMetadata {
+    pub id: String,
+    pub path: PathBuf,
+    pub version: Option<String>,
+    /// Detected from the component's exports at registration.
+    pub world: PluginWorld,
+    pub permissions: Vec<String>,
+    pub exports: Vec<String>,
+    pub subscriptions: Vec<String>,
+}
+
+/// A change applied by [`PluginRegistry::sync_directory`].
+#[derive(Debug, Clone)]
+pub enum PluginChange {
+    Loaded(PluginMetadata),
+    Reloaded(PluginMetadata),
+    Unloaded(PluginMetadata),
+}
+
+impl PluginChange {
+    pub fn metadata(&self) -> &PluginMetadata {
+        match self {
+            Self::Loaded(m) | Self::Reloaded(m) | Self::Unloaded(m) => m,
+        }
+    }
+}
+
+/// Size and modification time of every file a plugin was loaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Fingerprint(Vec<(PathBuf, u64, Option<SystemTime>)>);
+
+impl Fingerprint {
+    fn of(paths: &[&Path]) -> Self {
+        Self(
+            paths
+                .iter()
+                .map(|path| {
+                    let meta = std::fs::metadata(path).ok();
+                    (
+                        path.to_path_buf(),
+                        meta.as_ref().map(|m| m.len()).unwrap_or(0),
+                        meta.and_then(|m| m.modified().ok()),
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+/// A plugin found on disk, not yet compiled.
+struct PluginSource {
+    id: String,
+    path: PathBuf,
+    manifest: Option<PluginManifest>,
+    fingerprint: Fingerprint,
+}
+
+/// One loaded, validated and linked version of a plugin.
+///
+/// Invocations hold a read lease on `inflight`; retiring the version takes
+/// the write lock, which waits for those invocations to finish.
+struct LoadedPlugin {
+    metadata: PluginMetadata,
+    fingerprint: Fingerprint,
+    pre: InstancePre<BrioHostState>,
+    component_key: String,
+    inflight: Arc<tokio::sync::RwLock<()>>,
+}
+
+/// Keeps a plugin version loaded while an invocation is running.
+pub struct PluginLease {
+    pub metadata: PluginMetadata,
+    pub pre: InstancePre<BrioHostState>,
+    _guard: OwnedRwLockReadGuard<()>,
+}
+
+/// Registry for managing dynamic plugins.
+pub struct PluginRegistry {
+    plugins: RwLock<HashMap<String, Arc<LoadedPlugin>>>,
+    engine: Engine,
+    components: Arc<ComponentCache>,
+    sync_lock: tokio::sync::Mutex<()>,
+    /// Sources that failed validation, so unchanged files are not retried.
+    rejected: Mutex<HashMap<String, Fingerprint>>,
+    drain_timeout: Duration,
+    default_budget: CpuBudget,
+    budgets: HashMap<String, CpuBudget>,
+    default_limits: ResourceLimits,
+    limits: HashMap<String, ResourceLimits>,
+}
+
+impl PluginRegistry {
+    /// Creates a new, empty registry.
+    pub fn new(engine: Engine) -> Self {
+        Self {
+            plugins: RwLock::new(HashMap::new()),
+            components: Arc::new(ComponentCache::new(engine.clone())),
+            sync_lock: tokio::sync::Mutex::new(()),
+            rejected: Mutex::new(HashMap::new()),
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+            engine,
+            default_budget: CpuBudget::unlimited(),
+            budgets: HashMap::new(),
+            default_limits: ResourceLimits::unlimited(),
+            limits: HashMap::new(),
+        }
+    }
+
+    /// Persists compiled components under `dir` so restarts skip compilation.
+    pub fn with_component_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.components = Arc::new(ComponentCache::new(self.engine.clone()).with_disk_cache(dir));
+        self
+    }
+
+    /// Sets how long unloaded plugin versions may finish in-flight invocations.
+    pub fn with_drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// Sets the CPU budgets applied to plugin invocations.
+    /// `overrides` are keyed by plugin ID and take precedence over `default`.
+    pub fn with_cpu_budgets(
+        mut self,
+        default: CpuBudget,
+        overrides: HashMap<String, CpuBudget>,
+    ) -> Self {
+        self.default_budget = default;
+        self.budgets = overrides;
+        self
+    }
+
+    /// Sets the memory and table limits applied to plugin stores.
+    /// `overrides` are keyed by plugin ID and take precedence over `default`.
+    pub fn with_resource_limits(
+        mut self,
+        default: ResourceLimits,
+        overrides: HashMap<String, ResourceLimits>,
+    ) -> Self {
+        self.default_limits = default;
+        self.limits = overrides;
+        self
+    }
+
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /// Returns the CPU budget for a plugin.
+    pub fn cpu_budget(&self, plugin_id: &str) -> CpuBudget {
+        self.budgets
+            .get(plugin_id)
+            .copied()
+            .unwrap_or(self.default_budget)
+    }
+
+    /// Returns the resource limits for a plugin.
+    pub fn resource_limits(&self, plugin_id: &str) -> ResourceLimits {
+        self.limits
+            .get(plugin_id)
+            .copied()
+            .unwrap_or(self.default_limits)
+    }
+
+    /// Scans a directory for plugins and registers them.
+    ///
+    /// Picks up `<id>.wasm` files (with an optional `<id>.toml` manifest) and
+    /// subdirectories containing a `plugin.toml`. Plugins with an invalid
+    /// manifest, or whose component imports interfaces the kernel does not
+    /// provide or exports no known world, are refused and logged; the
+    /// remaining plugins still load.
+    pub async fn load_from_directory<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.sync_directory(path).await.map(|_| ())
+    }
+
+    /// Brings the registry in line with the contents of `path`.
+    ///
+    /// New plugins are loaded, changed ones are relinked and swapped in, and
+    /// plugins whose files disappeared are unloaded. Replaced versions are
+    /// drained before this returns. An update that fails validation keeps the
+    /// previous version running.
+    pub async fn sync_directory<P: AsRef<Path>>(&self, path: P) -> Result<Vec<PluginChange>> {
+        let _sync = self.sync_lock.lock().await;
+        let path = path.as_ref();
+        if !path.exists() {
+            warn!("Plugin directory does not exist: {:?}", path);
+            return Ok(Vec::new());
+        }
+
+        let scanned = scan_directory(path).await?;
+        let current = self.plugins.read().expect("RwLock poisoned").clone();
+
+        let mut changes = Vec::new();
+        let mut updated = HashMap::new();
+        let mut retired = Vec::new();
+        let mut seen = HashSet::new();
+
+        for source in scanned {
+            let id = source.id.clone();
+            seen.insert(id.clone());
+            let existing = current.get(&id);
+            if existing.is_some_and(|p| p.fingerprint == source.fingerprint)
+                || self.is_rejected(&source)
+            {
+                continue;
+            }
+
+            let path = source.path.clone();
+            let fingerprint = source.fingerprint.clone();
+            // Compiling and linking is CPU-bound, keep it off the async workers
+            let engine = self.engine.clone();
+            let components = self.components.clone();
+            let prepared =
+                tokio::task::spawn_blocking(move || prepare(&engine, &components, source))
+                    .await
+                    .unwrap_or_else(|e| Err(anyhow::anyhow!("Plugin preparation failed: {}", e)));
+            let plugin = match prepared {
+                Ok(plugin) => Arc::new(plugin),
+                Err(e) => {
+                    if existing.is_some() {
+                        error!(
+                            plugin_id = %id,
+                            "Keeping previous plugin version, update failed: {:#}", e
+                        );
+                    } else {
+                        error!("Refusing to load plugin from {:?}: {:#}", path, e);
+                    }
+                    self.rejected
+                        .lock()
+                        .expect("Mutex poisoned")
+                        .insert(id, fingerprint);
+                    continue;
+                }
+            };
+
+            let metadata = plugin.metadata.clone();
+            if let Some(existing) = existing {
+                info!("Reloading plugin: {} from {:?}", id, path);
+                changes.push(PluginChange::Reloaded(metadata));
+                retired.push(existing.clone());
+            } else {
+                info!(
+                    "Loading plugin: {} ({}) from {:?}",
+                    id, metadata.world, path
+                );
+                if metadata.version.is_none() {
+                    warn!(plugin_id = %id, "Plugin has no manifest, granting no permissions");
+                }
+                changes.push(PluginChange::Loaded(metadata));
+            }
+            updated.insert(id, plugin);
+        }
+        self.rejected
+            .lock()
+            .expect("Mutex poisoned")
+            .retain(|id, _| seen.contains(id) && !updated.contains_key(id));
+
+        let removed: Vec<Arc<LoadedPlugin>> = current
+            .values()
+            .filter(|p| !seen.contains(&p.metadata.id))
+            .cloned()
+            .collect();
+
+        {
+            let mut plugins = self.plugins.write().expect("RwLock poisoned");
+            for plugin in &removed {
+                info!("Unloading plugin: {}", plugin.metadata.id);
+                plugins.remove(&plugin.metadata.id);
+                changes.push(PluginChange::Unloaded(plugin.metadata.clone()));
+            }
+            plugins.extend(updated);
+        }
+        retired.extend(removed);
+
+        for change in &changes {
+            audit::log_audit(audit_event(change));
+        }
+
+        futures_util::future::join_all(retired.iter().map(|plugin| self.retire(plugin))).await;
+
+        Ok(changes)
+    }
+
+    fn is_rejected(&self, source: &PluginSource) -> bool {
+        self.rejected
+            .lock()
+            .expect("Mutex poisoned")
+            .get(&source.id)
+            .is_some_and(|f| *f == source.fingerprint)
+    }
+
+    /// Waits for in-flight invocations of a replaced version, then frees its
+    /// compiled component unless another loaded version shares it.
+    async fn retire(&self, plugin: &LoadedPlugin) {
+        let id = &plugin.metadata.id;
+        match tokio::time::timeout(self.drain_timeout, plugin.inflight.clone().write_owned()).await
+        {
+            Ok(_) => debug!(plugin_id = %id, "Drained previous plugin version"),
+            Err(_) => warn!(
+                plugin_id = %id,
+                timeout_ms = self.drain_timeout.as_millis() as u64,
+                "Timed out draining previous plugin version"
+            ),
+        }
+
+        let shared = self
+            .plugins
+            .read()
+            .expect("RwLock poisoned")
+            .values()
+            .any(|p| p.component_key == plugin.component_key);
+        if !shared {
+            self.components.evict(&plugin.component_key);
+        }
+    }
+
+    /// Instantiates a plugin by ID.
+    pub async fn instantiate(
+        &self,
+        plugin_id: &str,
+        host_state: BrioHostState,
+    ) -> Result<Store<BrioHostState>> {
+        let lease = self.acquire(plugin_id)?;
+
+        // Create a view of host state with plugin context
+        let plugin_state = host_state
+            .with_plugin_context(plugin_id.to_string(), lease.metadata.permissions.clone())
+            .with_resource_limits(self.resource_limits(plugin_id));
+
+        let mut store = Store::new(&self.engine, plugin_state);
+        store.data_mut().init_wasi()?;
+        store.limiter(|state| state.limiter_mut());
+        let budget = self.cpu_budget(plugin_id);
+        budget.apply(&mut store)?;
+
+        if let Err(e) = lease.pre.instantiate_async(&mut store).await {
+            store.data().limiter().audit_instantiation_error(&e);
+            let fuel_consumed = budget.consumed(&store);
+            return Err(budget.classify(e, fuel_consumed));
+        }
+
+        Ok(store)
+    }
+
+    /// Leases the current version of a plugin for one invocation.
+    ///
+    /// While the lease is held, a hot reload or unload of the plugin waits
+    /// before releasing the version the lease refers to.
+    pub fn acquire(&self, plugin_id: &str) -> Result<PluginLease> {
+        // A version being drained has already been replaced in the map, so a
+        // second lookup observes its successor.
+        for _ in 0..2 {
+            let plugin = self.entry(plugin_id)?;
+            if let Ok(guard) = plugin.inflight.clone().try_read_owned() {
+                return Ok(PluginLease {
+                    metadata: plugin.metadata.clone(),
+                    pre: plugin.pre.clone(),
+                    _guard: guard,
+                });
+            }
+        }
+        Err(anyhow::anyhow!("Plugin is being unloaded: {}", plugin_id))
+    }
+
+    /// Returns the pre-linked component for a plugin.
+    ///
+    /// Compiled components are shared through the content-addressed cache, and
+    /// the linked `InstancePre` is kept per plugin version so invocations only
+    /// pay for instantiation.
+    pub fn instance_pre(&self, plugin_id: &str) -> Result<InstancePre<BrioHostState>> {
+        Ok(self.entry(plugin_id)?.pre.clone())
+    }
+
+    fn entry(&self, plugin_id: &str) -> Result<Arc<LoadedPlugin>> {
+        self.plugins
+            .read()
+            .expect("RwLock poisoned")
+            .get(plugin_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Plugin not found: {}", plugin_id))
+    }
+
+    pub fn list_plugins(&self) -> Vec<PluginMetadata> {
+        self.plugins
+            .read()
+            .expect("RwLock poisoned")
+            .values()
+            .map(|p| p.metadata.clone())
+            .collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<PluginMetadata> {
+        self.plugins
+            .read()
+            .expect("RwLock poisoned")
+            .get(id)
+            .map(|p| p.metadata.clone())
+    }
+}
+
+fn audit_event(change: &PluginChange) -> AuditEvent {
+    match change {
+        PluginChange::Loaded(m) => AuditEvent::PluginLoaded {
+            plugin_id: m.id.clone(),
+            version: m.version.clone(),
+            path: m.path.display().to_string(),
+        },
+        PluginChange::Reloaded(m) => AuditEvent::PluginReloaded {
+            plugin_id: m.id.clone(),
+            version: m.version.clone(),
+            path: m.path.display().to_string(),
+        },
+        PluginChange::Unloaded(m) => AuditEvent::PluginUnloaded {
+            plugin_id: m.id.clone(),
+        },
+    }
+}
+
+/// Compiles, validates and links a plugin source.
+fn prepare(
+    engine: &Engine,
+    components: &ComponentCache,
+    source: PluginSource,
+) -> Result<LoadedPlugin> {
+    let (component_key, component) = components
+        .load_keyed(&source.path)
+        .context("Failed to load component")?;
+
+    let interfaces = ComponentInterfaces::of(&component, engine);
+    interfaces.check_imports()?;
+    let world = interfaces.classify()?;
+    if let Some(manifest) = &source.manifest {
+        interfaces.check_declared(&manifest.exports)?;
+    }
+
+    let linker = create_linker(engine)?;
+    let pre = linker
+        .instantiate_pre(&component)
+        .with_context(|| format!("Failed to link plugin '{}'", source.id))?;
+    debug!(plugin_id = %source.id, world = %world, "Linked plugin");
+
+    Ok(LoadedPlugin {
+        metadata: metadata_from(source.id, &source.path, source.manifest, world),
+        fingerprint: source.fingerprint,
+        pre,
+        component_key,
+        inflight: Arc::new(tokio::sync::RwLock::new(())),
+    })
+}
+
+/// Reads every plugin in `path`, refusing (and logging) invalid manifests.
+async fn scan_directory(path: &Path) -> Result<Vec<PluginSource>> {
+    let mut paths = Vec::new();
+    let mut entries = fs::read_dir(path).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        paths.push(entry.path());
+    }
+    // Deterministic order decides which of two plugins claiming an id wins
+    paths.sort();
+
+    let mut sources: Vec<PluginSource> = Vec::new();
+    for path in paths {
+        let result = if path.is_dir() {
+            let manifest_path = path.join(manifest::MANIFEST_FILE);
+            if !manifest_path.exists() {
+                continue;
+            }
+            read_manifest_dir(&manifest_path)
+        } else if path.extension().and_then(|s| s.to_str()) == Some("wasm") {
+            read_wasm(&path)
+        } else {
+            continue;
+        };
+
+        let result = result.and_then(|source| {
+            if let Some(existing) = sources.iter().find(|s| s.id == source.id) {
+                anyhow::bail!(
+                    "Plugin id '{}' already registered from {:?}",
+                    source.id,
+                    existing.path
+                );
+            }
+            Ok(source)
+        });
+
+        match result {
+            Ok(source) => sources.push(source),
+            Err(e) => error!("Refusing to load plugin from {:?}: {:#}", path, e),
+        }
+    }
+    Ok(sources)
+}
+
+/// Reads a bare component, using its sidecar `<id>.toml` if present.
+fn read_wasm(path: &Path) -> Result<PluginSource> {
+    let sidecar = path.with_extension("toml");
+    if sidecar.exists() {
+        let manifest = PluginManifest::load(&sidecar)?;
+        return Ok(PluginSource {
+            id: manifest.id.clone(),
+            path: path.to_path_buf(),
+            manifest: Some(manifest),
+            fingerprint: Fingerprint::of(&[path, &sidecar]),
+        });
+    }
+
+    let id = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown")
+        .to_string();
+    Ok(PluginSource {
+        id,
+        path: path.to_path_buf(),
+        manifest: None,
+        fingerprint: Fingerprint::of(&[path]),
+    })
+}
+
+/// Reads the plugin described by a `plugin.toml` inside a plugin directory.
+fn read_manifest_dir(manifest_path: &Path) -> Result<PluginSource> {
+    let manifest = PluginManifest::load(manifest_path)?;
+    let dir = manifest_path.parent().unwrap_or(Path::new("."));
+    let component = manifest.component_path(dir);
+    if !component.is_file() {
+        anyhow::bail!("Component {:?} declared by manifest not found", component);
+    }
+    Ok(PluginSource {
+        id: manifest.id.clone(),
+        fingerprint: Fingerprint::of(&[&component, manifest_path]),
+        path: component,
+        manifest: Some(manifest),
+    })
+}
+
+fn metadata_from(
+    id: String,
+    path: &Path,
+    manifest: Option<PluginManifest>,
+    world: PluginWorld,
+) -> PluginMetadata {
+    match manifest {
+        Some(manifest) => PluginMetadata {
+            id,
+            path: path.to_path_buf(),
+            version: Some(manifest.version),
+            world,
+            permissions: manifest.permissions,
+            exports: manifest.exports,
+            subscriptions: manifest.subscriptions,
+        },
+        None => PluginMetadata {
+            id,
+            path: path.to_path_buf(),
+            version: None,
+            world,
+            permissions: vec![],
+            exports: vec![],
+            subscriptions: vec![],
+        },
+    }
+}
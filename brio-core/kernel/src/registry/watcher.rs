@@ -0,0 +1,133 @@
+//! Hot reload of the plugins directory.
+//!
+//! The directory is polled rather than watched through OS notifications so the
+//! behavior is identical on every platform and over network filesystems.
+
+use super::{PluginChange, PluginMetadata};
+use crate::host::BrioHostState;
+use crate::ws::WsPatch;
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, warn};
+
+/// Spawns a background task syncing the host's plugin registry with `dir`
+/// every `interval`.
+///
+/// Each change updates manifest subscriptions on the event bus and is
+/// broadcast to WebSocket clients as a patch on `/plugins/<id>`.
+pub fn spawn_plugin_watcher(
+    state: Arc<BrioHostState>,
+    dir: PathBuf,
+    interval: Duration,
+) -> JoinHandle<()> {
+    debug!(dir = ?dir, interval_ms = interval.as_millis() as u64, "Starting plugin watcher");
+    tokio::spawn(async move {
+        let Some(registry) = state.plugin_registry() else {
+            warn!("Plugin watcher started without a plugin registry");
+            return;
+        };
+
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // The first tick fires immediately; the directory was just loaded
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+            match registry.sync_directory(&dir).await {
+                Ok(changes) => {
+                    for change in &changes {
+                        apply_change(&state, change);
+                    }
+                }
+                Err(e) => error!("Failed to sync plugins from {:?}: {:#}", dir, e),
+            }
+        }
+    })
+}
+
+/// Propagates a registry change to the event bus and WebSocket clients.
+pub fn apply_change(state: &BrioHostState, change: &PluginChange) {
+    let metadata = change.metadata();
+    let bus = state.event_bus();
+    bus.unsubscribe_all(&metadata.id);
+    if !matches!(change, PluginChange::Unloaded(_)) {
+        for topic in &metadata.subscriptions {
+            bus.subscribe(topic.clone(), metadata.id.clone());
+        }
+    }
+
+    match plugin_patch(change) {
+        Ok(patch) => {
+            if let Err(e) = state.broadcast_patch(patch) {
+                debug!("No WebSocket clients for plugin patch: {}", e);
+            }
+        }
+        Err(e) => error!(plugin_id = %metadata.id, "Failed to build plugin patch: {}", e),
+    }
+}
+
+/// Builds the JSON Patch describing `change` under `/plugins`.
+pub fn plugin_patch(change: &PluginChange) -> Result<WsPatch, serde_json::Error> {
+    let metadata = change.metadata();
+    let path = format!("/plugins/{}", escape_pointer(&metadata.id));
+    let operation = match change {
+        PluginChange::Loaded(m) => json!({ "op": "add", "path": path, "value": plugin_value(m) }),
+        PluginChange::Reloaded(m) => {
+            json!({ "op": "replace", "path": path, "value": plugin_value(m) })
+        }
+        PluginChange::Unloaded(_) => json!({ "op": "remove", "path": path }),
+    };
+    serde_json::from_value(json!([operation])).map(WsPatch::new)
+}
+
+fn plugin_value(metadata: &PluginMetadata) -> serde_json::Value {
+    json!({
+        "id": metadata.id,
+        "version": metadata.version,
+        "world": metadata.world,
+        "permissions": metadata.permissions,
+        "exports": metadata.exports,
+        "subscriptions": metadata.subscriptions,
+    })
+}
+
+/// Escapes a JSON Pointer reference token (RFC 6901).
+fn escape_pointer(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::PluginWorld;
+
+    fn metadata(id: &str) -> PluginMetadata {
+        PluginMetadata {
+            id: id.to_string(),
+            path: PathBuf::from(format!("plugins/{}.wasm", id)),
+            version: Some("0.1.0".to_string()),
+            world: PluginWorld::Agent,
+            permissions: vec![],
+            exports: vec![],
+            subscriptions: vec![],
+        }
+    }
+
+    #[test]
+    fn test_plugin_patch_operations() -> Result<(), serde_json::Error> {
+        let loaded = plugin_patch(&PluginChange::Loaded(metadata("coder")))?.to_json();
+        assert!(
+            loaded
+                .unwrap()
+                .contains(r#""op":"add","path":"/plugins/coder""#)
+        );
+
+        let unloaded = plugin_patch(&PluginChange::Unloaded(metadata("a/b")))?.to_json();
+        assert!(unloaded.unwrap().contains(r#""path":"/plugins/a~1b""#));
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+//! Component type inspection.
+//!
+//! Plugins are classified by the `brio:core` interfaces they export, and are
+//! rejected up front if they import anything the kernel does not link.
+
+use crate::engine::linker::provides_import;
+use serde::Serialize;
+use std::fmt;
+use thiserror::Error;
+use wasmtime::Engine;
+use wasmtime::component::Component;
+
+/// Package whose exports determine a plugin's world.
+const BRIO_PACKAGE: &str = "brio:core/";
+
+/// The role a plugin plays, derived from its exports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PluginWorld {
+    /// `smart-agent`: exports `agent-runner` and `event-handler`.
+    Agent,
+    /// Exports `event-handler` only.
+    EventHandler,
+    /// Exports `tool` or one of the `tool-*` interfaces.
+    Tool,
+    /// Exports `planner`.
+    Planner,
+}
+
+impl PluginWorld {
+    /// Whether the plugin can be invoked through `agent-runner`.
+    pub fn runs_tasks(&self) -> bool {
+        matches!(self, Self::Agent)
+    }
+
+    /// Whether the plugin can receive pub-sub events.
+    pub fn handles_events(&self) -> bool {
+        matches!(self, Self::Agent | Self::EventHandler)
+    }
+}
+
+impl fmt::Display for PluginWorld {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Agent => f.write_str("agent"),
+            Self::EventHandler => f.write_str("event-handler"),
+            Self::Tool => f.write_str("tool"),
+            Self::Planner => f.write_str("planner"),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WorldError {
+    #[error("Component imports interfaces the kernel does not provide: {}", .0.join(", "))]
+    UnsupportedImports(Vec<String>),
+    #[error("Component exports no known brio:core interface (exports: [{}])", .0.join(", "))]
+    UnknownWorld(Vec<String>),
+    #[error("Agents must export both 'agent-runner' and 'event-handler'")]
+    IncompleteAgent,
+    #[error("Manifest declares export '{0}' which the component does not provide")]
+    MissingDeclaredExport(String),
+}
+
+/// Import and export names of a component, without version suffixes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ComponentInterfaces {
+    pub imports: Vec<String>,
+    pub exports: Vec<String>,
+}
+
+impl ComponentInterfaces {
+    pub fn of(component: &Component, engine: &Engine) -> Self {
+        let ty = component.component_type();
+        Self {
+            imports: ty
+                .imports(engine)
+                .map(|(name, _)| unversioned(name).to_string())
+                .collect(),
+            exports: ty
+                .exports(engine)
+                .map(|(name, _)| unversioned(name).to_string())
+                .collect(),
+        }
+    }
+
+    /// Rejects imports the kernel's linker does not define.
+    pub fn check_imports(&self) -> Result<(), WorldError> {
+        let unsupported: Vec<String> = self
+            .imports
+            .iter()
+            .filter(|name| !provides_import(name))
+            .cloned()
+            .collect();
+        if unsupported.is_empty() {
+            Ok(())
+        } else {
+            Err(WorldError::UnsupportedImports(unsupported))
+        }
+    }
+
+    /// Derives the plugin's world from its `brio:core` exports.
+    pub fn classify(&self) -> Result<PluginWorld, WorldError> {
+        let has = |interface: &str| self.brio_exports().any(|e| e == interface);
+
+        if has("agent-runner") {
+            return if has("event-handler") {
+                Ok(PluginWorld::Agent)
+            } else {
+                Err(WorldError::IncompleteAgent)
+            };
+        }
+        if has("event-handler") {
+            return Ok(PluginWorld::EventHandler);
+        }
+        if has("planner") {
+            return Ok(PluginWorld::Planner);
+        }
+        if self.brio_exports().any(is_tool_interface) {
+            return Ok(PluginWorld::Tool);
+        }
+        Err(WorldError::UnknownWorld(self.exports.clone()))
+    }
+
+    /// Checks that every export declared in a manifest is really exported.
+    /// `tool` matches any of the `tool-*` interfaces.
+    pub fn check_declared(&self, declared: &[String]) -> Result<(), WorldError> {
+        for export in declared {
+            let found = if export == "tool" {
+                self.brio_exports().any(is_tool_interface)
+            } else {
+                self.brio_exports().any(|e| e == export)
+            };
+            if !found {
+                return Err(WorldError::MissingDeclaredExport(export.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    fn brio_exports(&self) -> impl Iterator<Item = &str> {
+        self.exports
+            .iter()
+            .filter_map(|e| e.strip_prefix(BRIO_PACKAGE))
+    }
+}
+
+fn is_tool_interface(name: &str) -> bool {
+    name == "tool" || name.starts_with("tool-")
+}
+
+fn unversioned(name: &str) -> &str {
+    name.split_once('@').map_or(name, |(base, _)| base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interfaces(imports: &[&str], exports: &[&str]) -> ComponentInterfaces {
+        ComponentInterfaces {
+            imports: imports.iter().map(|s| s.to_string()).collect(),
+            exports: exports.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_classify_worlds() {
+        let agent = interfaces(&[], &["brio:core/agent-runner", "brio:core/event-handler"]);
+        assert_eq!(agent.classify().unwrap(), PluginWorld::Agent);
+
+        let handler = interfaces(&[], &["brio:core/event-handler"]);
+        assert_eq!(handler.classify().unwrap(), PluginWorld::EventHandler);
+
+        let tool = interfaces(&[], &["brio:core/tool-grep", "brio:core/tool-read-file"]);
+        assert_eq!(tool.classify().unwrap(), PluginWorld::Tool);
+
+        let planner = interfaces(&[], &["brio:core/planner"]);
+        assert_eq!(planner.classify().unwrap(), PluginWorld::Planner);
+    }
+
+    #[test]
+    fn test_classify_rejects_incomplete_and_unknown() {
+        let runner_only = interfaces(&[], &["brio:core/agent-runner"]);
+        assert!(matches!(
+            runner_only.classify(),
+            Err(WorldError::IncompleteAgent)
+        ));
+
+        let foreign = interfaces(&[], &["acme:widgets/run"]);
+        assert!(matches!(
+            foreign.classify(),
+            Err(WorldError::UnknownWorld(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_imports() {
+        let supported = interfaces(
+            &[
+                "brio:core/logging",
+                "brio:ai/inference",
+                "wasi:cli/stdout@0.2.3",
+            ],
+            &["brio:core/tool"],
+        );
+        assert!(supported.check_imports().is_ok());
+
+        let unsupported = interfaces(&["acme:widgets/store"], &["brio:core/tool"]);
+        match unsupported.check_imports() {
+            Err(WorldError::UnsupportedImports(names)) => {
+                assert_eq!(names, vec!["acme:widgets/store"])
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_check_declared_exports() {
+        let tool = interfaces(&[], &["brio:core/tool-grep"]);
+        assert!(tool.check_declared(&["tool".to_string()]).is_ok());
+        assert!(matches!(
+            tool.check_declared(&["agent-runner".to_string()]),
+            Err(WorldError::MissingDeclaredExport(_))
+        ));
+    }
+
+    #[test]
+    fn test_unversioned() {
+        assert_eq!(unversioned("wasi:io/streams@0.2.0"), "wasi:io/streams");
+        assert_eq!(unversioned("brio:core/logging"), "brio:core/logging");
+    }
+}
@@ -6,10 +6,10 @@ use sqlx::sqlite::SqlitePoolOptions;
 async fn setup_store() -> Result<(SqlStore, sqlx::SqlitePool)> {
     let pool = SqlitePoolOptions::new().connect("sqlite::memory:").await?;
 
-    let store = SqlStore::new(pool.clone(), Box::new(PrefixPolicy));
+    let store = SqlStore::new(pool.clone(), Box::new(PrefixPolicy::new()));
 
     // Setup initial schema
-    sqlx::query("CREATE TABLE agent_1_data (id INTEGER PRIMARY KEY, content TEXT)")
+    sqlx::query("CREATE TABLE agent1_data (id INTEGER PRIMARY KEY, content TEXT)")
         .execute(&pool)
         .await?;
 
@@ -23,15 +23,15 @@ async fn test_store_query_success() -> Result<()> {
     // Insert data (bypass store policy for setup or use valid query)
     store
         .execute(
-            "agent_1",
-            "INSERT INTO agent_1_data (content) VALUES (?)",
+            "agent1",
+            "INSERT INTO agent1_data (content) VALUES (?)",
             vec!["hello".to_string()],
         )
         .await?;
 
     // Query data
     let rows = store
-        .query("agent_1", "SELECT * FROM agent_1_data", vec![])
+        .query("agent1", "SELECT * FROM agent1_data", vec![])
         .await?;
 
     assert_eq!(rows.len(), 1);
@@ -46,7 +46,7 @@ async fn test_store_policy_violation() -> Result<()> {
 
     // Try to access table with wrong scope
     let result = store
-        .query("agent_2", "SELECT * FROM agent_1_data", vec![])
+        .query("agent2", "SELECT * FROM agent1_data", vec![])
         .await;
 
     assert!(result.is_err());
@@ -64,16 +64,16 @@ async fn test_store_generic_types() -> Result<()> {
 
     store
         .execute(
-            "agent_1",
-            "INSERT INTO agent_1_data (id, content) VALUES (99, 'test')",
+            "agent1",
+            "INSERT INTO agent1_data (id, content) VALUES (99, 'test')",
             vec![],
         )
         .await?;
 
     let rows = store
         .query(
-            "agent_1",
-            "SELECT id, content FROM agent_1_data WHERE id = 99",
+            "agent1",
+            "SELECT id, content FROM agent1_data WHERE id = 99",
             vec![],
         )
         .await?;
@@ -2,7 +2,7 @@ pub mod r#impl;
 pub mod policy;
 
 pub use r#impl::{SqlStore, StoreError};
-pub use policy::{PolicyError, PrefixPolicy, QueryPolicy};
+pub use policy::{PolicyError, PrefixPolicy, QueryPolicy, TableGrant};
 
 #[cfg(test)]
 mod integration_tests;
@@ -1,19 +1,39 @@
+use serde::Deserialize;
 use sqlparser::{
-    ast::{TableFactor, Visit, Visitor},
+    ast::{Statement, TableFactor, Visit, Visitor},
     dialect::GenericDialect,
     parser::Parser,
 };
+use std::collections::HashMap;
 use std::ops::ControlFlow;
+use std::sync::Arc;
 use thiserror::Error;
 
+/// Scope of the kernel's own tables (`brio_token_usage`, ...). No plugin may
+/// use it.
+pub const KERNEL_SCOPE: &str = "brio";
+
 #[derive(Debug, Error)]
 pub enum PolicyError {
     #[error("SQL Parse Error: {0}")]
     ParseError(String),
     #[error("Access Denied: Table '{0}' does not match scope '{1}'")]
     ScopeViolation(String, String),
+    #[error("Access Denied: Scope '{1}' may only read shared table '{0}'")]
+    ReadOnlyGrant(String, String),
     #[error("Policy Violation: {0}")]
     Violation(String),
+    #[error("Access Denied: '{0}' cannot be a SQL scope")]
+    InvalidScope(String),
+}
+
+/// Checks that `scope` owns its `{scope}_` prefix alone: it may not contain
+/// `_` (so `coder` cannot reach `coder_v2`'s tables) nor be the kernel's.
+pub fn check_scope(scope: &str) -> Result<(), PolicyError> {
+    if scope.is_empty() || scope.contains('_') || scope == KERNEL_SCOPE {
+        return Err(PolicyError::InvalidScope(scope.to_string()));
+    }
+    Ok(())
 }
 
 /// Defines the authorization contract for SQL execution.
@@ -22,18 +42,74 @@ pub trait QueryPolicy: Send + Sync {
     fn authorize(&self, scope: &str, sql: &str) -> Result<(), PolicyError>;
 }
 
-/// A strict policy that ensures all accessed tables start with `{scope}_`.
-pub struct PrefixPolicy;
+impl<P: QueryPolicy + ?Sized> QueryPolicy for Arc<P> {
+    fn authorize(&self, scope: &str, sql: &str) -> Result<(), PolicyError> {
+        (**self).authorize(scope, sql)
+    }
+}
+
+/// Scopes allowed to access a shared table outside their own prefix.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct TableGrant {
+    /// Scopes that may only read the table.
+    #[serde(default)]
+    pub read: Vec<String>,
+    /// Scopes that may read and modify the table.
+    #[serde(default)]
+    pub write: Vec<String>,
+}
+
+impl TableGrant {
+    fn allows(&self, scope: &str, write: bool) -> bool {
+        self.write.iter().any(|s| s == scope) || (!write && self.read.iter().any(|s| s == scope))
+    }
+
+    fn mentions(&self, scope: &str) -> bool {
+        self.allows(scope, false)
+    }
+}
+
+/// A strict policy that ensures all accessed tables start with `{scope}_`,
+/// except shared tables explicitly granted to the scope.
+///
+/// Any statement other than a `SELECT` needs a write grant on every shared
+/// table it references.
+#[derive(Debug, Clone, Default)]
+pub struct PrefixPolicy {
+    grants: HashMap<String, TableGrant>,
+}
+
+impl PrefixPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grants access to a shared table.
+    pub fn with_grant(mut self, table: impl Into<String>, grant: TableGrant) -> Self {
+        self.grants.insert(table.into(), grant);
+        self
+    }
+
+    pub fn with_grants(mut self, grants: HashMap<String, TableGrant>) -> Self {
+        self.grants.extend(grants);
+        self
+    }
+}
 
 impl QueryPolicy for PrefixPolicy {
     fn authorize(&self, scope: &str, sql: &str) -> Result<(), PolicyError> {
+        check_scope(scope)?;
         let dialect = GenericDialect {};
         let ast =
             Parser::parse_sql(&dialect, sql).map_err(|e| PolicyError::ParseError(e.to_string()))?;
 
         for statement in ast {
             // We use a Visitor to traverse the AST and find all table references.
-            let mut visitor = TableVisitor { scope };
+            let mut visitor = TableVisitor {
+                scope,
+                grants: &self.grants,
+                write: !matches!(statement, Statement::Query(_)),
+            };
             if let ControlFlow::Break(err) = statement.visit(&mut visitor) {
                 return Err(err);
             }
@@ -45,6 +121,8 @@ impl QueryPolicy for PrefixPolicy {
 
 struct TableVisitor<'a> {
     scope: &'a str,
+    grants: &'a HashMap<String, TableGrant>,
+    write: bool,
 }
 
 impl<'a> Visitor for TableVisitor<'a> {
@@ -58,11 +136,24 @@ impl<'a> Visitor for TableVisitor<'a> {
             let table_name = ident.value.as_str();
             let expected_prefix = format!("{}_", self.scope);
 
-            if !table_name.starts_with(&expected_prefix) {
-                return ControlFlow::Break(PolicyError::ScopeViolation(
-                    table_name.to_string(),
-                    self.scope.to_string(),
-                ));
+            if table_name.starts_with(&expected_prefix) {
+                return ControlFlow::Continue(());
+            }
+
+            match self.grants.get(table_name) {
+                Some(grant) if grant.allows(self.scope, self.write) => {}
+                Some(grant) if grant.mentions(self.scope) => {
+                    return ControlFlow::Break(PolicyError::ReadOnlyGrant(
+                        table_name.to_string(),
+                        self.scope.to_string(),
+                    ));
+                }
+                _ => {
+                    return ControlFlow::Break(PolicyError::ScopeViolation(
+                        table_name.to_string(),
+                        self.scope.to_string(),
+                    ));
+                }
             }
         }
         ControlFlow::Continue(())
@@ -75,37 +166,93 @@ mod tests {
 
     #[test]
     fn test_valid_scope_query() {
-        let policy = PrefixPolicy;
-        let sql = "SELECT * FROM agent_1_data WHERE id = 1";
-        assert!(policy.authorize("agent_1", sql).is_ok());
+        let policy = PrefixPolicy::new();
+        let sql = "SELECT * FROM agent1_data WHERE id = 1";
+        assert!(policy.authorize("agent1", sql).is_ok());
     }
 
     #[test]
     fn test_invalid_scope_query() {
-        let policy = PrefixPolicy;
+        let policy = PrefixPolicy::new();
         let sql = "SELECT * FROM system_config";
-        assert!(policy.authorize("agent_1", sql).is_err());
+        assert!(policy.authorize("agent1", sql).is_err());
     }
 
     #[test]
     fn test_join_scope_violation() {
-        let policy = PrefixPolicy;
-        let sql = "SELECT * FROM agent_1_data JOIN system_users ON agent_1_data.user_id = system_users.id";
-        assert!(policy.authorize("agent_1", sql).is_err());
+        let policy = PrefixPolicy::new();
+        let sql =
+            "SELECT * FROM agent1_data JOIN system_users ON agent1_data.user_id = system_users.id";
+        assert!(policy.authorize("agent1", sql).is_err());
     }
 
     #[test]
     fn test_quoted_identifiers() {
-        let policy = PrefixPolicy;
-        // In SQL, "agent_1_data" is a valid identifier. sqlparser handles quotes.
-        let sql = "SELECT * FROM \"agent_1_data\"";
-        assert!(policy.authorize("agent_1", sql).is_ok());
+        let policy = PrefixPolicy::new();
+        // In SQL, "agent1_data" is a valid identifier. sqlparser handles quotes.
+        let sql = "SELECT * FROM \"agent1_data\"";
+        assert!(policy.authorize("agent1", sql).is_ok());
     }
 
     #[test]
     fn test_drop_table_valid() {
-        let policy = PrefixPolicy;
-        let sql = "DROP TABLE agent_1_temp";
-        assert!(policy.authorize("agent_1", sql).is_ok());
+        let policy = PrefixPolicy::new();
+        let sql = "DROP TABLE agent1_temp";
+        assert!(policy.authorize("agent1", sql).is_ok());
+    }
+
+    #[test]
+    fn test_scopes_cannot_overlap() {
+        let policy = PrefixPolicy::new();
+        // `_` in ids would let `coder` reach `coder_v2_*`
+        assert!(matches!(
+            policy.authorize("coder_v2", "SELECT * FROM coder_v2_notes"),
+            Err(PolicyError::InvalidScope(_))
+        ));
+        assert!(
+            policy
+                .authorize("coder", "SELECT * FROM coder_v2_notes")
+                .is_ok()
+        );
+        // The kernel's tables are nobody's
+        for sql in [
+            "DELETE FROM brio_token_usage",
+            "UPDATE brio_vfs_sessions SET base_path = '/'",
+        ] {
+            assert!(matches!(
+                policy.authorize("brio", sql),
+                Err(PolicyError::InvalidScope(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_shared_table_grants() {
+        let policy = PrefixPolicy::new().with_grant(
+            "tasks",
+            TableGrant {
+                read: vec!["reviewer".to_string()],
+                write: vec!["supervisor".to_string()],
+            },
+        );
+
+        let select = "SELECT * FROM tasks JOIN supervisor_notes ON tasks.id = supervisor_notes.id";
+        assert!(policy.authorize("supervisor", select).is_ok());
+        assert!(
+            policy
+                .authorize("supervisor", "UPDATE tasks SET status = ? WHERE id = ?")
+                .is_ok()
+        );
+
+        assert!(policy.authorize("reviewer", "SELECT * FROM tasks").is_ok());
+        assert!(matches!(
+            policy.authorize("reviewer", "DELETE FROM tasks"),
+            Err(PolicyError::ReadOnlyGrant(..))
+        ));
+
+        assert!(matches!(
+            policy.authorize("coder", "SELECT * FROM tasks"),
+            Err(PolicyError::ScopeViolation(..))
+        ));
     }
 }
@@ -1,11 +1,10 @@
-use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use super::exclude::ExcludeRules;
+use super::hashing::{self, FileManifest};
+use serde::Serialize;
 use std::fs;
-use std::io::{self, Read};
+use std::io;
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
 use tracing::{debug, info, warn};
-use walkdir::WalkDir;
 
 #[derive(Debug)]
 pub enum FileChange {
@@ -14,100 +13,6 @@ pub enum FileChange {
     Deleted(PathBuf),
 }
 
-#[derive(Debug, Clone)]
-struct FileMetadata {
-    size: u64,
-    #[allow(dead_code)]
-    modified: SystemTime,
-    hash: Option<String>,
-}
-
-fn compute_hash(path: &Path) -> io::Result<String> {
-    let mut file = fs::File::open(path)?;
-    let mut hasher = Sha256::new();
-    let mut buffer = [0; 8192];
-
-    loop {
-        let count = file.read(&mut buffer)?;
-        if count == 0 {
-            break;
-        }
-        hasher.update(&buffer[..count]);
-    }
-
-    Ok(hex::encode(hasher.finalize()))
-}
-
-fn scan_directory(root: &Path) -> io::Result<HashMap<PathBuf, FileMetadata>> {
-    let mut map = HashMap::new();
-
-    for entry in WalkDir::new(root) {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.is_file()
-            && let Ok(relative) = path.strip_prefix(root)
-        {
-            let metadata = fs::metadata(path)?;
-            map.insert(
-                relative.to_path_buf(),
-                FileMetadata {
-                    size: metadata.len(),
-                    modified: metadata.modified()?,
-                    hash: None,
-                },
-            );
-        }
-    }
-
-    Ok(map)
-}
-
-pub fn compute_diff(session_path: &Path, base_path: &Path) -> io::Result<Vec<FileChange>> {
-    let session_files = scan_directory(session_path)?;
-    let base_files = scan_directory(base_path)?;
-    let mut changes = Vec::new();
-
-    for (rel_path, session_meta) in &session_files {
-        match base_files.get(rel_path) {
-            Some(base_meta) => {
-                // Short-circuit: If size differs, it IS modified.
-                if session_meta.size != base_meta.size {
-                    changes.push(FileChange::Modified(rel_path.clone()));
-                    continue;
-                }
-
-                // If sizes match, verify with hash.
-                let s_hash = match &session_meta.hash {
-                    Some(h) => h.clone(),
-                    None => compute_hash(&session_path.join(rel_path))?,
-                };
-
-                let b_hash = match &base_meta.hash {
-                    Some(h) => h.clone(),
-                    None => compute_hash(&base_path.join(rel_path))?,
-                };
-
-                if s_hash != b_hash {
-                    changes.push(FileChange::Modified(rel_path.clone()));
-                }
-            }
-            None => {
-                changes.push(FileChange::Added(rel_path.clone()));
-            }
-        }
-    }
-
-    // Check for Deleted
-    for rel_path in base_files.keys() {
-        if !session_files.contains_key(rel_path) {
-            changes.push(FileChange::Deleted(rel_path.clone()));
-        }
-    }
-
-    Ok(changes)
-}
-
 pub fn apply_changes(
     session_path: &Path,
     base_path: &Path,
@@ -167,11 +72,11 @@ pub fn apply_changes(
                     fs::create_dir_all(parent)?;
                 }
 
-                // If final destination is a directory (and we are replacing it with a file),
-                // we must remove the directory first.
+                // A file replacing a directory: the deletions above emptied
+                // it, and anything still inside is not ours to remove
                 if final_dest.is_dir() {
-                    debug!("Removing conflicting directory at {:?}", final_dest);
-                    fs::remove_dir_all(&final_dest)?;
+                    debug!("Removing emptied directory at {:?}", final_dest);
+                    remove_empty_dir(&final_dest)?;
                 }
 
                 if let Err(e) = fs::rename(&staged_file, &final_dest) {
@@ -199,3 +104,183 @@ pub fn apply_changes(
     );
     Ok(())
 }
+
+/// Removes `dir` and the directories in it, failing if any holds a file.
+fn remove_empty_dir(dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            return Err(io::Error::other(format!(
+                "Refusing to replace {:?}: it holds {:?}",
+                dir,
+                entry.path()
+            )));
+        }
+        remove_empty_dir(&entry.path())?;
+    }
+    fs::remove_dir(dir)
+}
+
+/// How a file differs between a session and the base it began from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+    /// Moved without changing its content.
+    Renamed,
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Modified => "modified",
+            Self::Deleted => "deleted",
+            Self::Renamed => "renamed",
+        }
+    }
+}
+
+/// One changed file of a session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileDiff {
+    /// Relative to the session root; the old path for deletions.
+    pub path: PathBuf,
+    /// Where a renamed file came from.
+    pub old_path: Option<PathBuf>,
+    pub kind: ChangeKind,
+    pub binary: bool,
+    /// Unified diff, empty for binary files and renames.
+    pub patch: String,
+}
+
+/// Per-file unified diffs of `session_dir` against the snapshot in
+/// `ancestor_dir`, hashed in `ancestor`, sorted by path. Excluded paths are
+/// left out.
+///
+/// A deleted file whose content reappears at an added path is reported as
+/// a single rename.
+pub fn session_diff(
+    ancestor_dir: &Path,
+    ancestor: &FileManifest,
+    session_dir: &Path,
+    excludes: &ExcludeRules,
+) -> Result<Vec<FileDiff>, String> {
+    let session = hashing::compute_file_manifest(session_dir, excludes)?;
+    let read = |dir: &Path, rel: &Path| {
+        fs::read(dir.join(rel)).map_err(|e| format!("Failed to read {:?}: {}", rel, e))
+    };
+
+    let mut deleted: Vec<&PathBuf> = ancestor
+        .keys()
+        .filter(|p| !session.contains_key(*p) && !excludes.is_excluded(p, false))
+        .collect();
+    let mut diffs = Vec::new();
+    for (rel, hash) in &session {
+        let old = match ancestor.get(rel) {
+            Some(old_hash) if old_hash == hash => continue,
+            Some(_) => Some(read(ancestor_dir, rel)?),
+            None => None,
+        };
+        if old.is_none()
+            && let Some(i) = deleted.iter().position(|p| ancestor.get(*p) == Some(hash))
+        {
+            let from = deleted.remove(i);
+            let new = read(session_dir, rel)?;
+            diffs.push(FileDiff {
+                path: rel.clone(),
+                old_path: Some(from.clone()),
+                kind: ChangeKind::Renamed,
+                binary: as_text(&new).is_none(),
+                patch: String::new(),
+            });
+            continue;
+        }
+        let kind = match old {
+            Some(_) => ChangeKind::Modified,
+            None => ChangeKind::Added,
+        };
+        diffs.push(file_diff(
+            rel,
+            kind,
+            old.as_deref(),
+            Some(&read(session_dir, rel)?),
+        ));
+    }
+    for rel in deleted {
+        diffs.push(file_diff(
+            rel,
+            ChangeKind::Deleted,
+            Some(&read(ancestor_dir, rel)?),
+            None,
+        ));
+    }
+
+    diffs.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(diffs)
+}
+
+/// Everything [`session_diff`] reads about a session, owned so the diff can
+/// run without holding the session manager.
+#[derive(Debug, Clone)]
+pub struct DiffInputs {
+    pub ancestor_dir: PathBuf,
+    pub ancestor: FileManifest,
+    pub session_dir: PathBuf,
+    pub excludes: ExcludeRules,
+}
+
+impl DiffInputs {
+    pub fn diff(&self) -> Result<Vec<FileDiff>, String> {
+        session_diff(
+            &self.ancestor_dir,
+            &self.ancestor,
+            &self.session_dir,
+            &self.excludes,
+        )
+    }
+}
+
+fn file_diff(rel: &Path, kind: ChangeKind, old: Option<&[u8]>, new: Option<&[u8]>) -> FileDiff {
+    let (old_text, new_text) = (old.map(as_text), new.map(as_text));
+    let patch = match (old_text, new_text) {
+        (Some(None), _) | (_, Some(None)) => None,
+        (old, new) => {
+            let name = rel.display();
+            let mut options = diffy::DiffOptions::new();
+            options
+                .set_original_filename(match old {
+                    Some(_) => format!("a/{}", name),
+                    None => "/dev/null".to_string(),
+                })
+                .set_modified_filename(match new {
+                    Some(_) => format!("b/{}", name),
+                    None => "/dev/null".to_string(),
+                });
+            let (old, new) = (old.flatten(), new.flatten());
+            Some(
+                options
+                    .create_patch(old.as_deref().unwrap_or(""), new.as_deref().unwrap_or(""))
+                    .to_string(),
+            )
+        }
+    };
+
+    FileDiff {
+        path: rel.to_path_buf(),
+        old_path: None,
+        kind,
+        binary: patch.is_none(),
+        patch: patch.unwrap_or_default(),
+    }
+}
+
+/// The content as text, unless it looks binary (a NUL byte or invalid UTF-8).
+pub(crate) fn as_text(bytes: &[u8]) -> Option<String> {
+    if bytes.contains(&0) {
+        return None;
+    }
+    String::from_utf8(bytes.to_vec()).ok()
+}
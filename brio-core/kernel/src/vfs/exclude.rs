@@ -0,0 +1,221 @@
+//! Paths sessions leave alone.
+//!
+//! A session's base may hold build output, dependencies and VCS state that
+//! agents have no business copying. Excluded paths are never copied into a
+//! session, hashed, diffed or committed back; changes an agent makes under
+//! them stay in the session.
+//!
+//! Paths are excluded by the `sandbox.exclude` globs, by `.gitignore` and
+//! `.brioignore` files anywhere in the base (unless `sandbox.ignore_files`
+//! is off), and `.git` always.
+
+use crate::infrastructure::config::SandboxSettings;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Ignore files read in every directory, in increasing precedence.
+pub const IGNORE_FILES: &[&str] = &[".gitignore", ".brioignore"];
+
+/// Never part of a session, whatever the settings.
+const ALWAYS_EXCLUDED: &str = ".git";
+
+#[derive(Debug, Clone)]
+pub struct ExcludeRules {
+    globs: GlobSet,
+    /// Ignore files by the directory holding them, relative to the root,
+    /// outermost first.
+    ignores: Vec<(PathBuf, Gitignore)>,
+}
+
+impl Default for ExcludeRules {
+    fn default() -> Self {
+        Self {
+            globs: GlobSet::empty(),
+            ignores: Vec::new(),
+        }
+    }
+}
+
+impl ExcludeRules {
+    /// Reads the rules for the directory tree at `root`.
+    pub fn load(root: &Path, settings: &SandboxSettings) -> Result<Self, String> {
+        let mut globs = GlobSetBuilder::new();
+        for pattern in &settings.exclude {
+            let glob = Glob::new(pattern)
+                .map_err(|e| format!("Invalid exclude pattern '{}': {}", pattern, e))?;
+            globs.add(glob);
+        }
+        let mut rules = Self {
+            globs: globs
+                .build()
+                .map_err(|e| format!("Invalid exclude patterns: {}", e))?,
+            ignores: Vec::new(),
+        };
+        if settings.ignore_files {
+            rules.load_ignore_files(root, Path::new(""))?;
+        }
+        Ok(rules)
+    }
+
+    /// Adds the ignore files of `root/dir`, then of its subdirectories that
+    /// are not excluded by then.
+    fn load_ignore_files(&mut self, root: &Path, dir: &Path) -> Result<(), String> {
+        let abs = root.join(dir);
+        let mut builder = GitignoreBuilder::new(dir);
+        let mut found = false;
+        for name in IGNORE_FILES {
+            let file = abs.join(name);
+            if file.is_file() {
+                if let Some(e) = builder.add(&file) {
+                    return Err(format!("Invalid ignore file {:?}: {}", file, e));
+                }
+                found = true;
+            }
+        }
+        if found {
+            let gitignore = builder
+                .build()
+                .map_err(|e| format!("Invalid ignore files in {:?}: {}", abs, e))?;
+            self.ignores.push((dir.to_path_buf(), gitignore));
+        }
+
+        let entries =
+            fs::read_dir(&abs).map_err(|e| format!("Failed to read directory {:?}: {}", abs, e))?;
+        let mut subdirs = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+            let rel = dir.join(entry.file_name());
+            if entry.file_type().is_ok_and(|t| t.is_dir()) && !self.is_excluded(&rel, true) {
+                subdirs.push(rel);
+            }
+        }
+        subdirs.sort();
+        for subdir in subdirs {
+            self.load_ignore_files(root, &subdir)?;
+        }
+        Ok(())
+    }
+
+    /// Whether `rel`, relative to the root, or a directory above it is
+    /// excluded.
+    pub fn is_excluded(&self, rel: &Path, is_dir: bool) -> bool {
+        let mut prefix = PathBuf::new();
+        let mut components = rel.components().peekable();
+        while let Some(component) = components.next() {
+            prefix.push(component);
+            let prefix_is_dir = is_dir || components.peek().is_some();
+            if prefix.file_name().is_some_and(|n| n == ALWAYS_EXCLUDED) && prefix_is_dir {
+                return true;
+            }
+            if self.globs.is_match(&prefix) {
+                return true;
+            }
+        }
+
+        // The innermost ignore file with an opinion decides, as in git
+        for (dir, gitignore) in self.ignores.iter().rev() {
+            if !rel.starts_with(dir) || rel == dir {
+                continue;
+            }
+            match gitignore.matched_path_or_any_parents(rel, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+
+    /// Whether `root` holds an excluded entry at `rel` or at a directory
+    /// above it, such as a `target/` directory where `rel` is a file
+    /// `target`. Writing `rel` there would replace what is excluded.
+    pub fn shadows_excluded(&self, root: &Path, rel: &Path) -> bool {
+        let mut prefix = PathBuf::new();
+        rel.components().any(|component| {
+            prefix.push(component);
+            fs::symlink_metadata(root.join(&prefix))
+                .is_ok_and(|meta| self.is_excluded(&prefix, meta.is_dir()))
+        })
+    }
+
+    /// Walks `root` without entering or yielding excluded paths.
+    pub fn walk<'a>(
+        &'a self,
+        root: &'a Path,
+    ) -> impl Iterator<Item = walkdir::Result<walkdir::DirEntry>> + 'a {
+        WalkDir::new(root).into_iter().filter_entry(move |entry| {
+            entry
+                .path()
+                .strip_prefix(root)
+                .map(|rel| {
+                    rel.as_os_str().is_empty() || !self.is_excluded(rel, entry.file_type().is_dir())
+                })
+                .unwrap_or(true)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(exclude: &[&str]) -> SandboxSettings {
+        SandboxSettings {
+            exclude: exclude.iter().map(|p| p.to_string()).collect(),
+            ..SandboxSettings::default()
+        }
+    }
+
+    #[test]
+    fn test_rules_combine_globs_and_nested_ignore_files() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        fs::create_dir_all(root.join("web/dist")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(root.join(".brioignore"), "secrets.env\n").unwrap();
+        fs::write(root.join("web/.gitignore"), "dist/\n!keep.log\n").unwrap();
+
+        let rules = ExcludeRules::load(root, &settings(&["**/node_modules"])).unwrap();
+        let excluded = |path: &str| rules.is_excluded(Path::new(path), false);
+
+        assert!(excluded("target/debug/brio"));
+        assert!(excluded("build.log"));
+        assert!(excluded("secrets.env"));
+        assert!(excluded("web/dist/app.js"));
+        assert!(excluded("web/node_modules/react/index.js"));
+        assert!(excluded(".git/HEAD"));
+        assert!(!excluded("web/keep.log"));
+        assert!(!excluded("src/main.rs"));
+        assert!(!excluded(".gitignore"));
+        assert!(!excluded(".github/workflows/ci.yml"));
+
+        let ignored = ExcludeRules::load(
+            root,
+            &SandboxSettings {
+                ignore_files: false,
+                ..SandboxSettings::default()
+            },
+        )
+        .unwrap();
+        assert!(!ignored.is_excluded(Path::new("build.log"), false));
+    }
+
+    #[test]
+    fn test_shadows_excluded_entries() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+
+        let rules = ExcludeRules::load(root, &SandboxSettings::default()).unwrap();
+        // A file `target` is not excluded, but the directory it would replace is
+        assert!(!rules.is_excluded(Path::new("target"), false));
+        assert!(rules.shadows_excluded(root, Path::new("target")));
+        assert!(!rules.shadows_excluded(root, Path::new("src/target")));
+        assert!(!rules.shadows_excluded(root, Path::new(".gitignore")));
+    }
+}
@@ -0,0 +1,227 @@
+//! File operations inside a session directory.
+//!
+//! Paths are relative to the session root. Absolute paths and `..` are
+//! rejected, and a path whose resolution through symlinks leaves the root is
+//! a security violation, so guests cannot reach the base or the host through
+//! their session.
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Directory,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStat {
+    pub kind: EntryKind,
+    pub size: u64,
+    /// Unix seconds
+    pub modified: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    pub kind: EntryKind,
+    pub size: u64,
+}
+
+/// Resolves `path` against `root`, which must exist. The result may not
+/// exist yet, but everything of it that does lies inside `root`.
+pub fn resolve(root: &Path, path: &str) -> Result<PathBuf, String> {
+    let mut relative = PathBuf::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                return Err(format!("Invalid path '{}': '..' is not allowed", path));
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(format!(
+                    "Invalid path '{}': paths are relative to the session",
+                    path
+                ));
+            }
+        }
+    }
+
+    let root = dunce::canonicalize(root)
+        .map_err(|e| format!("Failed to resolve session root {:?}: {}", root, e))?;
+    let target = root.join(&relative);
+
+    // The longest existing prefix decides where the path really points
+    let mut existing = target.as_path();
+    while fs::symlink_metadata(existing).is_err() {
+        existing = existing.parent().unwrap_or(&root);
+    }
+    let resolved = dunce::canonicalize(existing).map_err(|_| {
+        format!(
+            "Security Violation: '{}' is a symlink that does not resolve",
+            path
+        )
+    })?;
+    if !resolved.starts_with(&root) {
+        return Err(format!(
+            "Security Violation: '{}' resolves outside the session",
+            path
+        ));
+    }
+    Ok(target)
+}
+
+/// Like [`resolve`], for operations that may not touch the root itself.
+fn resolve_entry(root: &Path, path: &str) -> Result<PathBuf, String> {
+    let target = resolve(root, path)?;
+    if dunce::canonicalize(root).ok().as_deref() == Some(target.as_path()) {
+        return Err(format!("Invalid path '{}': the session root", path));
+    }
+    Ok(target)
+}
+
+pub fn read_file(root: &Path, path: &str) -> Result<Vec<u8>, String> {
+    let target = resolve(root, path)?;
+    fs::read(&target).map_err(|e| format!("Failed to read '{}': {}", path, e))
+}
+
+/// Writes `contents` to `path`, creating or truncating it and any missing
+/// parent directories.
+pub fn write_file(root: &Path, path: &str, contents: &[u8]) -> Result<(), String> {
+    let target = resolve_entry(root, path)?;
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create parent of '{}': {}", path, e))?;
+    }
+    fs::write(&target, contents).map_err(|e| format!("Failed to write '{}': {}", path, e))
+}
+
+/// Entries of a directory, sorted by name.
+pub fn list_dir(root: &Path, path: &str) -> Result<Vec<DirEntry>, String> {
+    let target = resolve(root, path)?;
+    let entries = fs::read_dir(&target).map_err(|e| format!("Failed to list '{}': {}", path, e))?;
+
+    let mut listing = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to list '{}': {}", path, e))?;
+        let metadata = entry
+            .metadata()
+            .map_err(|e| format!("Failed to stat {:?}: {}", entry.path(), e))?;
+        listing.push(DirEntry {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            kind: kind_of(&metadata),
+            size: metadata.len(),
+        });
+    }
+    listing.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(listing)
+}
+
+/// `None` if nothing exists at `path`.
+pub fn stat(root: &Path, path: &str) -> Result<Option<FileStat>, String> {
+    let target = resolve(root, path)?;
+    if !target.exists() {
+        return Ok(None);
+    }
+    let metadata =
+        fs::metadata(&target).map_err(|e| format!("Failed to stat '{}': {}", path, e))?;
+    Ok(Some(FileStat {
+        kind: kind_of(&metadata),
+        size: metadata.len(),
+        modified: metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs()),
+    }))
+}
+
+/// Deletes a file, or a directory with everything in it.
+pub fn delete(root: &Path, path: &str) -> Result<(), String> {
+    let target = resolve_entry(root, path)?;
+    let result = if target.is_dir() {
+        fs::remove_dir_all(&target)
+    } else {
+        fs::remove_file(&target)
+    };
+    result.map_err(|e| format!("Failed to delete '{}': {}", path, e))
+}
+
+/// Moves `from` to `to`, replacing a file at `to` and creating its parent
+/// directories.
+pub fn rename(root: &Path, from: &str, to: &str) -> Result<(), String> {
+    let source = resolve_entry(root, from)?;
+    let target = resolve_entry(root, to)?;
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create parent of '{}': {}", to, e))?;
+    }
+    fs::rename(&source, &target)
+        .map_err(|e| format!("Failed to rename '{}' to '{}': {}", from, to, e))
+}
+
+fn kind_of(metadata: &fs::Metadata) -> EntryKind {
+    if metadata.is_dir() {
+        EntryKind::Directory
+    } else {
+        EntryKind::File
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_rejects_escapes() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        fs::create_dir(root.path().join("src")).unwrap();
+
+        assert!(resolve(root.path(), "src/new/file.rs").is_ok());
+        assert!(resolve(root.path(), "./src").is_ok());
+        assert!(resolve(root.path(), "../etc/passwd").is_err());
+        assert!(resolve(root.path(), "src/../../x").is_err());
+        assert!(resolve(root.path(), "/etc/passwd").is_err());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::symlink;
+            symlink(outside.path(), root.path().join("escape")).unwrap();
+            symlink(root.path().join("src"), root.path().join("inside")).unwrap();
+            symlink(root.path().join("missing"), root.path().join("dangling")).unwrap();
+
+            let err = resolve(root.path(), "escape/secret.txt").unwrap_err();
+            assert!(err.contains("Security Violation"), "{}", err);
+            assert!(resolve(root.path(), "inside/lib.rs").is_ok());
+            assert!(resolve(root.path(), "dangling").is_err());
+        }
+    }
+
+    #[test]
+    fn test_file_operations() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+
+        write_file(root, "src/lib.rs", b"fn a() {}").unwrap();
+        assert_eq!(read_file(root, "src/lib.rs").unwrap(), b"fn a() {}");
+        assert_eq!(stat(root, "nope").unwrap(), None);
+        let stat = stat(root, "src/lib.rs").unwrap().unwrap();
+        assert_eq!((stat.kind, stat.size), (EntryKind::File, 9));
+
+        rename(root, "src/lib.rs", "src/core/lib.rs").unwrap();
+        let names: Vec<(String, EntryKind)> = list_dir(root, "src")
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.name, e.kind))
+            .collect();
+        assert_eq!(names, vec![("core".to_string(), EntryKind::Directory)]);
+
+        assert!(delete(root, ".").is_err());
+        delete(root, "src").unwrap();
+        assert!(list_dir(root, "").unwrap().is_empty());
+    }
+}
@@ -1,43 +1,47 @@
+use super::exclude::ExcludeRules;
 use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::fs;
 use std::io::Read;
-use std::path::Path;
-use walkdir::WalkDir;
+use std::path::{Path, PathBuf};
 
-/// Computes a combined hash of all files in a directory for conflict detection.
-pub fn compute_directory_hash(path: &Path) -> Result<String, String> {
-    let mut hasher = Sha256::new();
-    let mut count = 0;
+/// Content hash of every file in a directory, by relative path.
+pub type FileManifest = BTreeMap<PathBuf, String>;
+
+/// Hashes every file under `path` that is not excluded, for per-file
+/// conflict detection.
+pub fn compute_file_manifest(path: &Path, excludes: &ExcludeRules) -> Result<FileManifest, String> {
+    let mut manifest = FileManifest::new();
 
-    for entry in WalkDir::new(path).sort_by_file_name() {
+    for entry in excludes.walk(path) {
         let entry = entry.map_err(|e| format!("Failed to walk directory: {}", e))?;
         let file_path = entry.path();
 
         if file_path.is_file() {
-            // Include relative path in hash to detect renames
             let relative = file_path
                 .strip_prefix(path)
                 .map_err(|e| format!("Failed to strip prefix: {}", e))?;
-            hasher.update(relative.to_string_lossy().as_bytes());
-
-            // Include file content hash
-            let mut file = fs::File::open(file_path)
-                .map_err(|e| format!("Failed to open file {:?}: {}", file_path, e))?;
-            let mut buffer = [0u8; 8192];
-            loop {
-                let bytes_read = file
-                    .read(&mut buffer)
-                    .map_err(|e| format!("Failed to read file {:?}: {}", file_path, e))?;
-                if bytes_read == 0 {
-                    break;
-                }
-                hasher.update(&buffer[..bytes_read]);
-            }
-            count += 1;
+            manifest.insert(relative.to_path_buf(), hash_file(file_path)?);
         }
     }
 
-    // Include file count to detect deletions
-    hasher.update(count.to_string().as_bytes());
+    Ok(manifest)
+}
+
+/// SHA-256 of a file's content, hex-encoded.
+pub fn hash_file(file_path: &Path) -> Result<String, String> {
+    let mut hasher = Sha256::new();
+    let mut file = fs::File::open(file_path)
+        .map_err(|e| format!("Failed to open file {:?}: {}", file_path, e))?;
+    let mut buffer = [0u8; 8192];
+    loop {
+        let bytes_read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read file {:?}: {}", file_path, e))?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
     Ok(hex::encode(hasher.finalize()))
 }
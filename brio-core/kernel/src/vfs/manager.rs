@@ -1,24 +1,66 @@
+use super::exclude::ExcludeRules;
+use super::hashing::FileManifest;
+use super::merge::{self, CommitError, ConflictKind, ConflictReport, FileConflict};
 use super::{diff, hashing, policy::SandboxPolicy, reflink};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tracing::{debug, info, instrument, warn};
 use uuid::Uuid;
 
-/// Represents a session with its base path and snapshot hash
-struct SessionInfo {
-    base_path: PathBuf,
-    /// Hash of the base directory at session start (for conflict detection)
-    base_snapshot_hash: String,
+/// Directory under the session root holding each session's base snapshot
+const SNAPSHOT_DIR: &str = ".snapshots";
+
+/// File accesses move `last_active` forward at most this often, which bounds
+/// how often the host persists it
+pub const ACTIVITY_RESOLUTION_SECS: i64 = 60;
+
+/// A session's metadata, as persisted by
+/// [`SessionStore`](super::persistence::SessionStore).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionRecord {
+    pub id: String,
+    pub base_path: PathBuf,
+    /// Per-file hashes of the base at session start, the common ancestor
+    /// for three-way merges
+    pub base_manifest: FileManifest,
+    /// Plugin that began the session
+    pub owner_plugin: Option<String>,
+    pub task_id: Option<String>,
+    /// Unix seconds
+    pub created_at: i64,
+    /// Unix seconds of the last file access through `session-fs`, commit
+    /// attempt or rebase, in steps of [`ACTIVITY_RESOLUTION_SECS`]
+    pub last_active: i64,
+    /// Files a conflicting commit rebased that the session has not resolved
+    pub pending_conflicts: BTreeMap<PathBuf, PendingConflict>,
+}
+
+/// A conflicting file of a session, as left by the rebase.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingConflict {
+    pub kind: ConflictKind,
+    /// Hash of the session copy after the rebase, `None` if it had none
+    pub rebased_hash: Option<String>,
 }
 
 use crate::infrastructure::config::SandboxSettings;
 
+/// Current time in Unix seconds
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
 pub struct SessionManager {
-    // Map SessionID -> SessionInfo
-    sessions: HashMap<String, SessionInfo>,
+    // Map SessionID -> SessionRecord
+    sessions: HashMap<String, SessionRecord>,
     root_temp_dir: PathBuf,
     policy: SandboxPolicy,
+    sandbox: SandboxSettings,
 }
 
 impl SessionManager {
@@ -29,21 +71,68 @@ impl SessionManager {
             sessions: HashMap::new(),
             root_temp_dir: temp,
             policy: SandboxPolicy::new(&sandbox).map_err(|e| e.to_string())?,
+            sandbox,
         })
     }
 
-    /// Cleans up the temporary session directory.
+    /// Keeps session and snapshot directories under `dir` instead of the
+    /// system temp directory.
+    pub fn with_root_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.root_temp_dir = dir.into();
+        self
+    }
+
+    /// Metadata of a tracked session.
+    pub fn session(&self, session_id: &str) -> Option<&SessionRecord> {
+        self.sessions.get(session_id)
+    }
+
+    /// Tracks sessions begun before a restart. Sessions whose directories
+    /// are gone cannot be resumed; what is left of them is removed, and
+    /// their ids are returned.
+    pub fn restore_sessions(&mut self, records: Vec<SessionRecord>) -> Vec<String> {
+        let mut lost = Vec::new();
+        for record in records {
+            if self.root_temp_dir.join(&record.id).is_dir()
+                && self.snapshot_path(&record.id).is_dir()
+            {
+                debug!("Restored session {} for {:?}", record.id, record.base_path);
+                self.sessions.insert(record.id.clone(), record);
+                continue;
+            }
+            warn!("Session {} lost its directories; dropping it", record.id);
+            if let Err(e) = self.cleanup_session_dir(&record.id) {
+                warn!("{}", e);
+            }
+            lost.push(record.id);
+        }
+        lost
+    }
+
+    /// Exclusion rules of a session, read from its snapshot so that they stay
+    /// as they were when the session began.
+    fn excludes(&self, session_id: &str) -> Result<ExcludeRules, String> {
+        ExcludeRules::load(&self.snapshot_path(session_id), &self.sandbox)
+    }
+
+    /// Copy of the base as it was when the session began.
+    fn snapshot_path(&self, session_id: &str) -> PathBuf {
+        self.root_temp_dir.join(SNAPSHOT_DIR).join(session_id)
+    }
+
+    /// Cleans up the temporary session directory and its base snapshot.
     /// This is called automatically after commit or rollback.
     fn cleanup_session_dir(&self, session_id: &str) -> Result<(), String> {
-        let session_path = self.root_temp_dir.join(session_id);
-        if session_path.exists() {
-            fs::remove_dir_all(&session_path).map_err(|e| {
-                format!(
-                    "Failed to cleanup session directory {:?}: {}",
-                    session_path, e
-                )
-            })?;
-            debug!("Cleaned up session directory: {:?}", session_path);
+        for path in [
+            self.root_temp_dir.join(session_id),
+            self.snapshot_path(session_id),
+        ] {
+            if path.exists() {
+                fs::remove_dir_all(&path).map_err(|e| {
+                    format!("Failed to cleanup session directory {:?}: {}", path, e)
+                })?;
+                debug!("Cleaned up session directory: {:?}", path);
+            }
         }
         Ok(())
     }
@@ -59,8 +148,19 @@ impl SessionManager {
     }
 
     /// Creates a new session by copying (reflink) the base directory.
-    #[instrument(skip(self))]
     pub fn begin_session(&mut self, base_path: String) -> Result<String, String> {
+        self.begin_session_for(base_path, None, None)
+    }
+
+    /// Like [`begin_session`](Self::begin_session), recording the plugin and
+    /// task the session belongs to.
+    #[instrument(skip(self))]
+    pub fn begin_session_for(
+        &mut self,
+        base_path: String,
+        owner_plugin: Option<String>,
+        task_id: Option<String>,
+    ) -> Result<String, String> {
         // Security: Path Sandboxing (Delegated to Policy)
         let canonical_base = dunce::canonicalize(&base_path)
             .map_err(|e| format!("Invalid base path '{}': {}", base_path, e))?;
@@ -83,65 +183,103 @@ impl SessionManager {
             session_id, canonical_base
         );
 
-        // Compute snapshot hash before copying (Delegated to hashing)
-        let base_snapshot_hash = hashing::compute_directory_hash(&canonical_base)?;
-
-        // Perform Reflink Copy
-        reflink::copy_dir_reflink(&canonical_base, &session_path)
+        // Snapshot the base, then work on a copy of the snapshot so both
+        // start out identical (Reflink Copy)
+        let snapshot_path = self.snapshot_path(&session_id);
+        let excludes = ExcludeRules::load(&canonical_base, &self.sandbox)?;
+        reflink::copy_dir_reflink(&canonical_base, &snapshot_path, &excludes)
+            .map_err(|e| format!("Failed to snapshot base: {}", e))?;
+        let base_manifest = hashing::compute_file_manifest(&snapshot_path, &excludes)?;
+        reflink::copy_dir_reflink(&snapshot_path, &session_path, &excludes)
             .map_err(|e| format!("Failed to create session copy: {}", e))?;
 
         // Store session mapping with snapshot
+        let created_at = unix_now();
         self.sessions.insert(
             session_id.clone(),
-            SessionInfo {
+            SessionRecord {
+                id: session_id.clone(),
                 base_path: canonical_base,
-                base_snapshot_hash,
+                base_manifest,
+                owner_plugin,
+                task_id,
+                created_at,
+                last_active: created_at,
+                pending_conflicts: BTreeMap::new(),
             },
         );
 
         Ok(session_id)
     }
 
-    /// Commits changes from the session back to the base directory.
-    /// Returns an error if the base directory has been modified since session start.
+    /// Commits changes from the session back to the base directory,
+    /// three-way merging them with changes made to the base since the
+    /// session began.
+    ///
+    /// Fails with `CommitError::Conflict` when a file changed on both sides
+    /// cannot be merged; nothing is committed then, and the conflicting files
+    /// are rebased (see [`ConflictReport`]) so the session can be fixed and
+    /// committed again. Until each of them is fixed, commits fail with the
+    /// same `CommitError::Conflict`.
     /// Automatically cleans up the session directory after successful commit.
     #[instrument(skip(self))]
-    pub fn commit_session(&mut self, session_id: String) -> Result<(), String> {
+    pub fn commit_session(&mut self, session_id: String) -> Result<(), CommitError> {
+        if let Some(record) = self.sessions.get_mut(&session_id) {
+            record.last_active = unix_now();
+        }
+        let unresolved = self.unresolved_conflicts(&session_id)?;
+        if !unresolved.is_empty() {
+            warn!(
+                "Session {} has {} unresolved conflict(s)",
+                session_id,
+                unresolved.len()
+            );
+            return Err(CommitError::Conflict(ConflictReport {
+                session_id,
+                conflicts: unresolved,
+            }));
+        }
+
         let session_info = self
             .sessions
             .get(&session_id)
             .ok_or_else(|| format!("Session not found: {}", session_id))?;
 
         let base_path = session_info.base_path.clone();
-        let original_hash = session_info.base_snapshot_hash.clone();
         let session_path = self.root_temp_dir.join(&session_id);
+        let snapshot_path = self.snapshot_path(&session_id);
 
         if !session_path.exists() {
             self.sessions.remove(&session_id);
-            return Err(format!("Session directory lost: {:?}", session_path));
+            return Err(format!("Session directory lost: {:?}", session_path).into());
         }
 
-        // Conflict detection: re-hash base and compare
-        let current_hash = hashing::compute_directory_hash(&base_path)?;
-        if current_hash != original_hash {
+        // Conflict detection: per file, against the snapshot
+        let plan = merge::plan(
+            &snapshot_path,
+            &session_info.base_manifest,
+            &base_path,
+            &session_path,
+            &self.excludes(&session_id)?,
+        )?;
+        if !plan.conflicts.is_empty() {
+            let report = self.rebase_conflicts(&session_id, plan.conflicts)?;
             warn!(
-                "Conflict detected for session {}: base directory has been modified",
-                session_id
+                "Conflict detected for session {}: {} file(s) changed on both sides",
+                session_id,
+                report.conflicts.len()
             );
-            return Err(format!(
-                "Conflict: base directory '{}' has been modified since session started. \
-                 Original hash: {}, Current hash: {}",
-                base_path.display(),
-                original_hash,
-                current_hash
-            ));
+            return Err(CommitError::Conflict(report));
         }
 
         info!("Committing session {} to {:?}", session_id, base_path);
 
-        // 1. Compute Diff
-        let changes = diff::compute_diff(&session_path, &base_path)
-            .map_err(|e| format!("Failed to compute diff: {}", e))?;
+        // 1. Write merged files into the session, to be applied with the rest
+        for (rel, text) in &plan.merged {
+            fs::write(session_path.join(rel), text)
+                .map_err(|e| format!("Failed to write merged file {:?}: {}", rel, e))?;
+        }
+        let changes = plan.changes;
 
         if changes.is_empty() {
             info!("No changes to commit for session {}", session_id);
@@ -166,6 +304,121 @@ impl SessionManager {
         Ok(())
     }
 
+    /// Conflicting files still as the rebase left them, or holding conflict
+    /// markers. Resolved files are forgotten.
+    fn unresolved_conflicts(&mut self, session_id: &str) -> Result<Vec<FileConflict>, String> {
+        let session_path = self.root_temp_dir.join(session_id);
+        let info = self
+            .sessions
+            .get_mut(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+
+        let mut unresolved = Vec::new();
+        for (rel, pending) in &info.pending_conflicts {
+            let copy = session_path.join(rel);
+            let hash = if copy.is_file() {
+                Some(hashing::hash_file(&copy)?)
+            } else {
+                None
+            };
+            let marked = hash.is_some()
+                && fs::read(&copy)
+                    .map(|content| merge::has_conflict_markers(&content))
+                    .map_err(|e| format!("Failed to read {:?}: {}", rel, e))?;
+            if hash == pending.rebased_hash || marked {
+                unresolved.push(FileConflict {
+                    path: rel.clone(),
+                    kind: pending.kind,
+                });
+            }
+        }
+        info.pending_conflicts
+            .retain(|rel, _| unresolved.iter().any(|c| c.path == *rel));
+        Ok(unresolved)
+    }
+
+    /// Makes the current base the ancestor of each conflicting file, writes
+    /// conflict markers into the session copies of text files, restores
+    /// files the session deleted, and records the files as pending.
+    fn rebase_conflicts(
+        &mut self,
+        session_id: &str,
+        conflicts: Vec<(merge::FileConflict, Option<String>)>,
+    ) -> Result<ConflictReport, String> {
+        let session_path = self.root_temp_dir.join(session_id);
+        let snapshot_path = self.snapshot_path(session_id);
+        let info = self
+            .sessions
+            .get_mut(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+
+        let mut report = ConflictReport {
+            session_id: session_id.to_string(),
+            conflicts: Vec::new(),
+        };
+        for (conflict, marked) in conflicts {
+            let rel = &conflict.path;
+            let base_file = info.base_path.join(rel);
+            let ancestor = snapshot_path.join(rel);
+            if base_file.is_file() {
+                copy_file(&base_file, &ancestor)?;
+                info.base_manifest
+                    .insert(rel.clone(), hashing::hash_file(&base_file)?);
+            } else {
+                if ancestor.exists() {
+                    fs::remove_file(&ancestor)
+                        .map_err(|e| format!("Failed to update snapshot {:?}: {}", rel, e))?;
+                }
+                info.base_manifest.remove(rel);
+            }
+
+            let copy = session_path.join(rel);
+            match (conflict.kind, marked) {
+                (ConflictKind::Content, Some(marked)) => fs::write(&copy, marked)
+                    .map_err(|e| format!("Failed to write conflict markers {:?}: {}", rel, e))?,
+                (ConflictKind::Deleted, _) if !copy.exists() && base_file.is_file() => {
+                    copy_file(&base_file, &copy)?
+                }
+                _ => {}
+            }
+            let rebased_hash = if copy.is_file() {
+                Some(hashing::hash_file(&copy)?)
+            } else {
+                None
+            };
+            info.pending_conflicts.insert(
+                rel.clone(),
+                PendingConflict {
+                    kind: conflict.kind,
+                    rebased_hash,
+                },
+            );
+            report.conflicts.push(conflict);
+        }
+        Ok(report)
+    }
+
+    /// What the session changed since it began (or since a conflicting
+    /// commit rebased it), as per-file unified diffs.
+    pub fn diff_session(&self, session_id: &str) -> Result<Vec<diff::FileDiff>, String> {
+        self.diff_inputs(session_id)?.diff()
+    }
+
+    /// What a diff of the session reads, for callers that would rather not
+    /// hold the manager while the session tree is hashed.
+    pub fn diff_inputs(&self, session_id: &str) -> Result<diff::DiffInputs, String> {
+        let info = self
+            .sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        Ok(diff::DiffInputs {
+            ancestor_dir: self.snapshot_path(session_id),
+            ancestor: info.base_manifest.clone(),
+            session_dir: self.root_temp_dir.join(session_id),
+            excludes: self.excludes(session_id)?,
+        })
+    }
+
     /// Rolls back a session, discarding all changes without applying them.
     /// This removes the session from tracking and cleans up the temp directory.
     #[instrument(skip(self))]
@@ -186,13 +439,53 @@ impl SessionManager {
         Ok(())
     }
 
+    /// Records a file access to a session. Returns the new `last_active`
+    /// when it moved, to be persisted.
+    pub fn touch_session(&mut self, session_id: &str) -> Option<i64> {
+        let record = self.sessions.get_mut(session_id)?;
+        let now = unix_now();
+        if now - record.last_active < ACTIVITY_RESOLUTION_SECS {
+            return None;
+        }
+        record.last_active = now;
+        Some(now)
+    }
+
+    /// Rolls back the sessions unused for `sandbox.session_ttl_secs`, which
+    /// their owners most likely abandoned, and returns their ids.
+    pub fn expire_sessions(&mut self) -> Vec<String> {
+        let Some(ttl) = self.sandbox.session_ttl_secs else {
+            return Vec::new();
+        };
+        let cutoff = unix_now().saturating_sub(ttl as i64);
+        let mut expired: Vec<String> = self
+            .sessions
+            .values()
+            .filter(|record| record.last_active < cutoff)
+            .map(|record| record.id.clone())
+            .collect();
+        expired.sort();
+        for session_id in &expired {
+            let owner = self.sessions[session_id].owner_plugin.clone();
+            warn!(
+                "Session {} of {:?} unused for {}s; rolling it back",
+                session_id, owner, ttl
+            );
+            if let Err(e) = self.rollback_session(session_id.clone()) {
+                warn!("{}", e);
+            }
+        }
+        expired
+    }
+
     /// Returns the number of active sessions.
     pub fn active_session_count(&self) -> usize {
         self.sessions.len()
     }
 
     /// Cleans up all orphaned session directories that are not being tracked.
-    /// This can be called on startup to recover from crashes.
+    /// This can be called on startup to recover from crashes, once persisted
+    /// sessions have been restored so that only abandoned ones are reaped.
     #[instrument(skip(self))]
     pub fn cleanup_orphaned_sessions(&self) -> Result<usize, String> {
         let mut cleaned = 0;
@@ -210,6 +503,10 @@ impl SessionManager {
 
             if path.is_dir() {
                 let dir_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+                if dir_name == SNAPSHOT_DIR {
+                    cleaned += self.cleanup_orphaned_snapshots(&path)?;
+                    continue;
+                }
 
                 // If this directory is not tracked, it's orphaned
                 if !self.sessions.contains_key(dir_name) {
@@ -228,6 +525,35 @@ impl SessionManager {
 
         Ok(cleaned)
     }
+
+    /// Removes snapshots of sessions that are not being tracked.
+    fn cleanup_orphaned_snapshots(&self, snapshots: &Path) -> Result<usize, String> {
+        let mut cleaned = 0;
+        let entries = fs::read_dir(snapshots)
+            .map_err(|e| format!("Failed to read snapshot directory: {}", e))?;
+        for entry in entries {
+            let path = entry
+                .map_err(|e| format!("Failed to read directory entry: {}", e))?
+                .path();
+            let dir_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if !self.sessions.contains_key(dir_name) {
+                fs::remove_dir_all(&path)
+                    .map_err(|e| format!("Failed to remove orphaned snapshot {:?}: {}", path, e))?;
+                cleaned += 1;
+            }
+        }
+        Ok(cleaned)
+    }
+}
+
+fn copy_file(from: &Path, to: &Path) -> Result<(), String> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory {:?}: {}", parent, e))?;
+    }
+    fs::copy(from, to)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to copy {:?} to {:?}: {}", from, to, e))
 }
 
 impl Default for SessionManager {
@@ -236,6 +562,7 @@ impl Default for SessionManager {
             sessions: HashMap::new(),
             root_temp_dir: std::env::temp_dir().join("brio"),
             policy: SandboxPolicy::new_empty(),
+            sandbox: SandboxSettings::default(),
         }
     }
 }
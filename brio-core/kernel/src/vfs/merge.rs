@@ -0,0 +1,308 @@
+//! Three-way merge of a session into its base directory.
+//!
+//! The common ancestor is the snapshot of the base taken by `begin_session`.
+//! A file changed on one side only takes that side's version; a text file
+//! changed on both is merged line by line, and anything else changed on both
+//! sides is a conflict.
+//!
+//! A session change whose path holds an excluded entry in the base (a file
+//! `target` where the base has an ignored `target/`) is left in the session
+//! like changes under excluded paths.
+
+use super::diff::{FileChange, as_text};
+use super::exclude::ExcludeRules;
+use super::hashing::{self, FileManifest};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictKind {
+    /// Overlapping edits to a text file. The session copy holds the merge
+    /// with conflict markers (`ours` is the base, `theirs` the session).
+    Content,
+    /// Both sides changed a binary file.
+    Binary,
+    /// One side deleted a file the other changed. A file the session deleted
+    /// is restored from the base, to be deleted again or edited.
+    Deleted,
+}
+
+impl ConflictKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Content => "content",
+            Self::Binary => "binary",
+            Self::Deleted => "deleted",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileConflict {
+    /// Relative to the base directory.
+    pub path: PathBuf,
+    pub kind: ConflictKind,
+}
+
+/// The files changed both in a session and in its base since the session
+/// began. Nothing has been committed; the session is rebased onto the
+/// current base for these files, so what it holds when committed again is
+/// the resolution. Commits are refused until every such file has changed
+/// since the rebase and holds no conflict markers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictReport {
+    pub session_id: String,
+    pub conflicts: Vec<FileConflict>,
+}
+
+impl fmt::Display for ConflictReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let paths: Vec<String> = self
+            .conflicts
+            .iter()
+            .map(|c| format!("{} ({})", c.path.display(), c.kind.as_str()))
+            .collect();
+        write!(
+            f,
+            "Conflict: session {} and its base both changed {}",
+            self.session_id,
+            paths.join(", ")
+        )
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CommitError {
+    #[error("{0}")]
+    Conflict(ConflictReport),
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl From<String> for CommitError {
+    fn from(e: String) -> Self {
+        Self::Failed(e)
+    }
+}
+
+/// What committing a session takes.
+#[derive(Debug, Default)]
+pub(crate) struct MergePlan {
+    /// Changes to copy from the session to the base.
+    pub changes: Vec<FileChange>,
+    /// Merged text to write to the session copy before the changes apply.
+    pub merged: Vec<(PathBuf, String)>,
+    /// With the conflict-marked text of `Content` conflicts.
+    pub conflicts: Vec<(FileConflict, Option<String>)>,
+}
+
+/// Compares the session and the base with their common ancestor, whose
+/// files are in `ancestor_dir` and hashed in `ancestor`. Excluded paths are
+/// left out on every side.
+pub(crate) fn plan(
+    ancestor_dir: &Path,
+    ancestor: &FileManifest,
+    base_dir: &Path,
+    session_dir: &Path,
+    excludes: &ExcludeRules,
+) -> Result<MergePlan, String> {
+    let base = hashing::compute_file_manifest(base_dir, excludes)?;
+    let session = hashing::compute_file_manifest(session_dir, excludes)?;
+    let paths: BTreeSet<&PathBuf> = ancestor
+        .keys()
+        .chain(base.keys())
+        .chain(session.keys())
+        .filter(|path| !excludes.is_excluded(path, false))
+        .collect();
+
+    let mut plan = MergePlan::default();
+    for path in paths {
+        let (a, b, s) = (ancestor.get(path), base.get(path), session.get(path));
+        if s == a || s == b {
+            // Untouched by the session, or the same change on both sides
+            continue;
+        }
+        if excludes.shadows_excluded(base_dir, path) {
+            continue;
+        }
+        if b == a {
+            plan.changes.push(match (b, s) {
+                (_, None) => FileChange::Deleted(path.clone()),
+                (None, Some(_)) => FileChange::Added(path.clone()),
+                (Some(_), Some(_)) => FileChange::Modified(path.clone()),
+            });
+            continue;
+        }
+
+        let conflict = |kind| FileConflict {
+            path: path.clone(),
+            kind,
+        };
+        if b.is_none() || s.is_none() {
+            plan.conflicts.push((conflict(ConflictKind::Deleted), None));
+            continue;
+        }
+
+        let read = |dir: &Path| {
+            fs::read(dir.join(path)).map_err(|e| format!("Failed to read {:?}: {}", path, e))
+        };
+        let original = match a {
+            Some(_) => read(ancestor_dir)?,
+            None => Vec::new(),
+        };
+        match (
+            as_text(&original),
+            as_text(&read(base_dir)?),
+            as_text(&read(session_dir)?),
+        ) {
+            (Some(original), Some(ours), Some(theirs)) => {
+                match diffy::merge(&original, &ours, &theirs) {
+                    Ok(merged) => {
+                        plan.merged.push((path.clone(), merged));
+                        plan.changes.push(FileChange::Modified(path.clone()));
+                    }
+                    Err(marked) => plan
+                        .conflicts
+                        .push((conflict(ConflictKind::Content), Some(marked))),
+                }
+            }
+            _ => plan.conflicts.push((conflict(ConflictKind::Binary), None)),
+        }
+    }
+    Ok(plan)
+}
+
+/// Whether a text still holds the markers of a conflicting merge.
+pub(crate) fn has_conflict_markers(content: &[u8]) -> bool {
+    String::from_utf8_lossy(content)
+        .lines()
+        .any(|line| line.starts_with("<<<<<<< ") || line.starts_with(">>>>>>> "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Dirs {
+        _root: tempfile::TempDir,
+        ancestor: PathBuf,
+        base: PathBuf,
+        session: PathBuf,
+    }
+
+    /// Three copies of `files`.
+    fn dirs(files: &[(&str, &str)]) -> Dirs {
+        let root = tempfile::tempdir().unwrap();
+        let [ancestor, base, session] = ["ancestor", "base", "session"].map(|name| {
+            let dir = root.path().join(name);
+            for (path, content) in files {
+                fs::create_dir_all(dir.join(path).parent().unwrap()).unwrap();
+                fs::write(dir.join(path), content).unwrap();
+            }
+            dir
+        });
+        Dirs {
+            _root: root,
+            ancestor,
+            base,
+            session,
+        }
+    }
+
+    fn plan_for(dirs: &Dirs) -> MergePlan {
+        plan_with(dirs, &ExcludeRules::default())
+    }
+
+    fn plan_with(dirs: &Dirs, excludes: &ExcludeRules) -> MergePlan {
+        let manifest = hashing::compute_file_manifest(&dirs.ancestor, excludes).unwrap();
+        plan(
+            &dirs.ancestor,
+            &manifest,
+            &dirs.base,
+            &dirs.session,
+            excludes,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_plan_takes_one_sided_changes() {
+        let dirs = dirs(&[("README.md", "hello\n"), ("src/lib.rs", "fn a() {}\n")]);
+        fs::write(dirs.base.join("README.md"), "hello, human\n").unwrap();
+        fs::write(dirs.session.join("src/lib.rs"), "fn b() {}\n").unwrap();
+        fs::write(dirs.session.join("NEW.md"), "new\n").unwrap();
+
+        let plan = plan_for(&dirs);
+        assert!(plan.conflicts.is_empty());
+        assert!(plan.merged.is_empty());
+        let mut changes: Vec<String> = plan.changes.iter().map(|c| format!("{:?}", c)).collect();
+        changes.sort();
+        assert_eq!(
+            changes,
+            vec![r#"Added("NEW.md")"#, r#"Modified("src/lib.rs")"#]
+        );
+    }
+
+    #[test]
+    fn test_plan_merges_text_and_reports_conflicts() {
+        let dirs = dirs(&[
+            ("notes.txt", "one\ntwo\nthree\n"),
+            ("clash.txt", "a\n"),
+            ("image.bin", "\0\x01"),
+            ("gone.txt", "x\n"),
+        ]);
+        fs::write(dirs.base.join("notes.txt"), "ONE\ntwo\nthree\n").unwrap();
+        fs::write(dirs.session.join("notes.txt"), "one\ntwo\nTHREE\n").unwrap();
+        fs::write(dirs.base.join("clash.txt"), "base\n").unwrap();
+        fs::write(dirs.session.join("clash.txt"), "session\n").unwrap();
+        fs::write(dirs.base.join("image.bin"), "\0\x02").unwrap();
+        fs::write(dirs.session.join("image.bin"), "\0\x03").unwrap();
+        fs::remove_file(dirs.base.join("gone.txt")).unwrap();
+        fs::write(dirs.session.join("gone.txt"), "y\n").unwrap();
+
+        let plan = plan_for(&dirs);
+        assert_eq!(
+            plan.merged,
+            vec![(PathBuf::from("notes.txt"), "ONE\ntwo\nTHREE\n".to_string())]
+        );
+        let conflicts: Vec<(&str, ConflictKind)> = plan
+            .conflicts
+            .iter()
+            .map(|(c, _)| (c.path.to_str().unwrap(), c.kind))
+            .collect();
+        assert_eq!(
+            conflicts,
+            vec![
+                ("clash.txt", ConflictKind::Content),
+                ("gone.txt", ConflictKind::Deleted),
+                ("image.bin", ConflictKind::Binary),
+            ]
+        );
+        let marked = plan.conflicts[0].1.as_deref().unwrap();
+        assert!(marked.contains("<<<<<<< ours\nbase\n"));
+        assert!(marked.contains("session\n>>>>>>> theirs"));
+    }
+
+    #[test]
+    fn test_plan_leaves_changes_over_excluded_base_entries() {
+        let dirs = dirs(&[(".gitignore", "target/\n"), ("src/lib.rs", "fn a() {}\n")]);
+        fs::create_dir_all(dirs.base.join("target/debug")).unwrap();
+        fs::write(dirs.base.join("target/debug/app"), "build").unwrap();
+        fs::write(dirs.session.join("target"), "not a directory\n").unwrap();
+        fs::write(dirs.session.join("src/lib.rs"), "fn b() {}\n").unwrap();
+
+        let excludes = ExcludeRules::load(
+            &dirs.ancestor,
+            &crate::infrastructure::config::SandboxSettings::default(),
+        )
+        .unwrap();
+        let plan = plan_with(&dirs, &excludes);
+        let changes: Vec<String> = plan.changes.iter().map(|c| format!("{:?}", c)).collect();
+        assert_eq!(changes, vec![r#"Modified("src/lib.rs")"#]);
+        assert!(plan.conflicts.is_empty());
+    }
+}
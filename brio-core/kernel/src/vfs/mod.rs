@@ -1,6 +1,10 @@
 pub mod diff;
+pub mod exclude;
+pub mod files;
 pub(crate) mod hashing;
 pub mod manager;
+pub mod merge;
+pub mod persistence;
 pub(crate) mod policy;
 pub mod reflink;
 #[cfg(test)]
@@ -0,0 +1,197 @@
+//! Session metadata in the kernel database, so sessions survive restarts.
+//!
+//! The host saves a session when it begins and whenever a commit attempt
+//! leaves it open, updates its `last_active` as it is used, deletes it once
+//! committed, and restores every saved session
+//! into the [`SessionManager`](super::manager::SessionManager) on startup.
+
+use super::hashing::FileManifest;
+use super::manager::SessionRecord;
+use sqlx::{Row, SqlitePool};
+use std::path::PathBuf;
+
+/// Kernel table holding one row per open session
+pub const SESSIONS_TABLE: &str = "brio_vfs_sessions";
+
+#[derive(Clone)]
+pub struct SessionStore {
+    pool: SqlitePool,
+}
+
+impl SessionStore {
+    /// Creates the store, creating its table if needed.
+    pub async fn new(pool: SqlitePool) -> Result<Self, sqlx::Error> {
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {SESSIONS_TABLE} (
+                id TEXT PRIMARY KEY,
+                base_path TEXT NOT NULL,
+                base_manifest TEXT NOT NULL,
+                owner_plugin TEXT,
+                task_id TEXT,
+                created_at INTEGER NOT NULL,
+                pending_conflicts TEXT NOT NULL DEFAULT '{{}}',
+                last_active INTEGER NOT NULL DEFAULT 0
+            )"
+        ))
+        .execute(&pool)
+        .await?;
+        add_missing_column(&pool, "pending_conflicts", "TEXT NOT NULL DEFAULT '{}'").await?;
+        if add_missing_column(&pool, "last_active", "INTEGER NOT NULL DEFAULT 0").await? {
+            sqlx::query(&format!(
+                "UPDATE {SESSIONS_TABLE} SET last_active = created_at"
+            ))
+            .execute(&pool)
+            .await?;
+        }
+
+        Ok(Self { pool })
+    }
+
+    /// Inserts or replaces the row of `record`.
+    pub async fn save(&self, record: &SessionRecord) -> Result<(), sqlx::Error> {
+        let manifest = serde_json::to_string(&record.base_manifest)
+            .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+        let conflicts = serde_json::to_string(&record.pending_conflicts)
+            .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+        sqlx::query(&format!(
+            "INSERT OR REPLACE INTO {SESSIONS_TABLE} \
+             (id, base_path, base_manifest, owner_plugin, task_id, created_at, pending_conflicts, \
+             last_active) VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+        ))
+        .bind(&record.id)
+        .bind(record.base_path.to_string_lossy())
+        .bind(manifest)
+        .bind(&record.owner_plugin)
+        .bind(&record.task_id)
+        .bind(record.created_at)
+        .bind(conflicts)
+        .bind(record.last_active)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Updates the `last_active` of a saved session.
+    pub async fn touch(&self, session_id: &str, last_active: i64) -> Result<(), sqlx::Error> {
+        sqlx::query(&format!(
+            "UPDATE {SESSIONS_TABLE} SET last_active = ? WHERE id = ?"
+        ))
+        .bind(last_active)
+        .bind(session_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn remove(&self, session_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(&format!("DELETE FROM {SESSIONS_TABLE} WHERE id = ?"))
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Every saved session, oldest first.
+    pub async fn load(&self) -> Result<Vec<SessionRecord>, sqlx::Error> {
+        let rows = sqlx::query(&format!(
+            "SELECT id, base_path, base_manifest, owner_plugin, task_id, created_at, \
+             pending_conflicts, last_active FROM {SESSIONS_TABLE} ORDER BY created_at, id"
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let manifest: String = row.try_get(2)?;
+                let base_manifest: FileManifest = serde_json::from_str(&manifest)
+                    .map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+                let conflicts: String = row.try_get(6)?;
+                let pending_conflicts = serde_json::from_str(&conflicts)
+                    .map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+                Ok(SessionRecord {
+                    id: row.try_get(0)?,
+                    base_path: PathBuf::from(row.try_get::<String, _>(1)?),
+                    base_manifest,
+                    owner_plugin: row.try_get(3)?,
+                    task_id: row.try_get(4)?,
+                    created_at: row.try_get(5)?,
+                    pending_conflicts,
+                    last_active: row.try_get(7)?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Adds a column introduced after the table was first created. Returns
+/// whether it was missing.
+async fn add_missing_column(
+    pool: &SqlitePool,
+    column: &str,
+    definition: &str,
+) -> Result<bool, sqlx::Error> {
+    let columns: Vec<String> = sqlx::query_scalar(&format!(
+        "SELECT name FROM pragma_table_info('{SESSIONS_TABLE}')"
+    ))
+    .fetch_all(pool)
+    .await?;
+    if columns.iter().any(|name| name == column) {
+        return Ok(false);
+    }
+    sqlx::query(&format!(
+        "ALTER TABLE {SESSIONS_TABLE} ADD COLUMN {column} {definition}"
+    ))
+    .execute(pool)
+    .await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::manager::PendingConflict;
+    use crate::vfs::merge::ConflictKind;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::collections::BTreeMap;
+
+    #[tokio::test]
+    async fn test_store_round_trips_sessions() -> anyhow::Result<()> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await?;
+        let store = SessionStore::new(pool).await?;
+
+        let mut record = SessionRecord {
+            id: "sess-1".to_string(),
+            base_path: PathBuf::from("/work/src"),
+            base_manifest: FileManifest::from([(PathBuf::from("lib.rs"), "abc".to_string())]),
+            owner_plugin: Some("coder".to_string()),
+            task_id: None,
+            created_at: 1_700_000_000,
+            last_active: 1_700_000_000,
+            pending_conflicts: BTreeMap::new(),
+        };
+        store.save(&record).await?;
+        record
+            .base_manifest
+            .insert(PathBuf::from("src/main.rs"), "def".to_string());
+        record.pending_conflicts.insert(
+            PathBuf::from("lib.rs"),
+            PendingConflict {
+                kind: ConflictKind::Deleted,
+                rebased_hash: None,
+            },
+        );
+        store.save(&record).await?;
+        assert_eq!(store.load().await?, vec![record.clone()]);
+
+        store.touch("sess-1", 1_700_000_600).await?;
+        record.last_active = 1_700_000_600;
+        assert_eq!(store.load().await?, vec![record]);
+
+        store.remove("sess-1").await?;
+        assert!(store.load().await?.is_empty());
+        Ok(())
+    }
+}
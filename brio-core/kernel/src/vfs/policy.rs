@@ -94,6 +94,7 @@ mod tests {
                     .ok_or_else(|| anyhow::anyhow!("Invalid path"))?
                     .to_string(),
             ],
+            ..Default::default()
         };
 
         let policy = SandboxPolicy::new(&settings).map_err(|e| anyhow::anyhow!(e))?;
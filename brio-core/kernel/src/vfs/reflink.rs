@@ -1,16 +1,17 @@
+use super::exclude::ExcludeRules;
 use reflink;
 use std::fs;
 use std::path::Path;
 use tracing::{debug, info};
-use walkdir::WalkDir;
 
 /// Recursively copies a directory using reflink if possible, falling back to standard copy.
-pub fn copy_dir_reflink(src: &Path, dst: &Path) -> std::io::Result<()> {
+/// Paths excluded by `excludes` are skipped.
+pub fn copy_dir_reflink(src: &Path, dst: &Path, excludes: &ExcludeRules) -> std::io::Result<()> {
     if !dst.exists() {
         fs::create_dir_all(dst)?;
     }
 
-    for entry in WalkDir::new(src) {
+    for entry in excludes.walk(src) {
         let entry = entry?;
         let path = entry.path();
 
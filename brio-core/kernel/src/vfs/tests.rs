@@ -73,6 +73,7 @@ fn test_begin_session_sandbox_violation() -> anyhow::Result<()> {
 
     let sandbox = SandboxSettings {
         allowed_paths: vec![allowed_path.to_string_lossy().to_string()],
+        ..Default::default()
     };
     let mut manager = SessionManager::new(sandbox).map_err(|e| anyhow::anyhow!(e))?;
 
@@ -95,6 +96,7 @@ fn test_begin_session_sandbox_ok() -> anyhow::Result<()> {
 
     let sandbox = SandboxSettings {
         allowed_paths: vec![allowed_path.to_string_lossy().to_string()],
+        ..Default::default()
     };
     let mut manager = SessionManager::new(sandbox).map_err(|e| anyhow::anyhow!(e))?;
 
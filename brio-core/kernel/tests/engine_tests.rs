@@ -1,9 +1,13 @@
 //! Tests for the WASM engine module.
 
 use anyhow::Result;
-use brio_kernel::engine::{WasmEngine, create_engine_config, create_linker};
+use brio_kernel::engine::budget::{BudgetKind, spawn_epoch_ticker};
+use brio_kernel::engine::{
+    BudgetExhausted, CpuBudget, ResourceLimits, WasmEngine, create_engine_config, create_linker,
+};
 use brio_kernel::host::BrioHostState;
 use brio_kernel::inference::{ChatRequest, ChatResponse, InferenceError, LLMProvider};
+use std::time::Duration;
 
 // =============================================================================
 // Mock Provider
@@ -65,7 +69,7 @@ async fn test_wasm_engine_prepare_store() -> Result<()> {
 
     let host_state =
         BrioHostState::with_provider("sqlite::memory:", Box::new(MockProvider)).await?;
-    let _store = wasm_engine.prepare_store(host_state);
+    let _store = wasm_engine.prepare_store(host_state)?;
 
     Ok(())
 }
@@ -109,7 +113,7 @@ async fn test_instantiate_empty_component() -> Result<()> {
 
     let host_state =
         BrioHostState::with_provider("sqlite::memory:", Box::new(MockProvider)).await?;
-    let mut store = wasm_engine.prepare_store(host_state);
+    let mut store = wasm_engine.prepare_store(host_state)?;
 
     // Should be able to instantiate
     let instance = wasm_engine
@@ -143,7 +147,176 @@ fn test_multiple_stores_from_same_engine() {
             .await
             .unwrap();
 
-        let _store1 = wasm_engine.prepare_store(host1);
-        let _store2 = wasm_engine.prepare_store(host2);
+        let _store1 = wasm_engine.prepare_store(host1).unwrap();
+        let _store2 = wasm_engine.prepare_store(host2).unwrap();
     });
 }
+
+// =============================================================================
+// CPU Budget Tests
+// =============================================================================
+
+/// A component exporting `spin`, which never returns.
+const SPIN_COMPONENT: &str = r#"
+    (component
+        (core module $m
+            (func (export "spin") (loop $l (br $l))))
+        (core instance $i (instantiate $m))
+        (func (export "spin") (canon lift (core func $i "spin"))))
+"#;
+
+async fn run_spin_component(budget: CpuBudget, engine: &wasmtime::Engine) -> anyhow::Error {
+    let linker = create_linker(engine).unwrap();
+    let wasm_engine = WasmEngine::new(linker).unwrap().with_budget(budget);
+    let component = wasmtime::component::Component::new(engine, SPIN_COMPONENT).unwrap();
+
+    let host_state = BrioHostState::with_provider("sqlite::memory:", Box::new(MockProvider))
+        .await
+        .unwrap();
+    let mut store = wasm_engine.prepare_store(host_state).unwrap();
+
+    let result = async {
+        let instance = wasm_engine
+            .linker()
+            .instantiate_async(&mut store, &component)
+            .await?;
+        let spin = instance.get_typed_func::<(), ()>(&mut store, "spin")?;
+        spin.call_async(&mut store, ()).await
+    }
+    .await;
+
+    let error = result.expect_err("spin must not return");
+    let consumed = budget.consumed(&store);
+    budget.classify(error, consumed)
+}
+
+#[tokio::test]
+async fn test_fuel_budget_interrupts_infinite_loop() {
+    let engine = wasmtime::Engine::new(&create_engine_config()).unwrap();
+    let budget = CpuBudget::unlimited().with_fuel(10_000);
+
+    let error = run_spin_component(budget, &engine).await;
+
+    let exhausted = error
+        .downcast_ref::<BudgetExhausted>()
+        .expect("expected BudgetExhausted");
+    assert_eq!(exhausted.kind, BudgetKind::Fuel);
+    assert_eq!(exhausted.fuel_consumed, 10_000);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_epoch_budget_interrupts_infinite_loop() {
+    let engine = wasmtime::Engine::new(&create_engine_config()).unwrap();
+    let ticker = spawn_epoch_ticker(engine.clone(), Duration::from_millis(10));
+    let budget = CpuBudget::unlimited().with_epoch_ticks(2);
+
+    let error = run_spin_component(budget, &engine).await;
+    ticker.abort();
+
+    let exhausted = error
+        .downcast_ref::<BudgetExhausted>()
+        .expect("expected BudgetExhausted");
+    assert_eq!(exhausted.kind, BudgetKind::Epoch);
+}
+
+/// A component exporting `run`, which awaits the host's `wait` and then loops
+/// a little, crossing an epoch check.
+const WAIT_COMPONENT: &str = r#"
+    (component
+        (import "wait" (func $wait))
+        (core func $wait_lowered (canon lower (func $wait)))
+        (core instance $host (export "wait" (func $wait_lowered)))
+        (core module $m
+            (import "host" "wait" (func $wait))
+            (func (export "run") (local $n i32)
+                (call $wait)
+                (local.set $n (i32.const 1000))
+                (loop $l
+                    (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                    (br_if $l (local.get $n)))))
+        (core instance $i (instantiate $m (with "host" (instance $host))))
+        (func (export "run") (canon lift (core func $i "run"))))
+"#;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_epoch_budget_pauses_during_host_calls() -> Result<()> {
+    let engine = wasmtime::Engine::new(&create_engine_config())?;
+    let ticker = spawn_epoch_ticker(engine.clone(), Duration::from_millis(10));
+    let budget = CpuBudget::unlimited().with_epoch_ticks(3);
+
+    let mut linker = wasmtime::component::Linker::<()>::new(&engine);
+    linker.root().func_wrap_async("wait", |_, ()| {
+        Box::new(async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok(())
+        })
+    })?;
+    let component = wasmtime::component::Component::new(&engine, WAIT_COMPONENT)?;
+    let mut store = wasmtime::Store::new(&engine, ());
+    budget.apply(&mut store)?;
+
+    let instance = linker.instantiate_async(&mut store, &component).await?;
+    let run = instance.get_typed_func::<(), ()>(&mut store, "run")?;
+    let result = run.call_async(&mut store, ()).await;
+    ticker.abort();
+
+    result.map_err(|e| budget.classify(e, budget.consumed(&store)))
+}
+
+// =============================================================================
+// Resource Limit Tests
+// =============================================================================
+
+/// A component exporting `grow`, which grows its memory by one page
+/// and returns the previous size in pages (or -1 on failure).
+const GROW_COMPONENT: &str = r#"
+    (component
+        (core module $m
+            (memory 1)
+            (func (export "grow") (result i32)
+                (memory.grow (i32.const 1))))
+        (core instance $i (instantiate $m))
+        (func (export "grow") (result s32) (canon lift (core func $i "grow"))))
+"#;
+
+const WASM_PAGE_SIZE: usize = 64 * 1024;
+
+async fn grow_once(limits: ResourceLimits) -> Result<i32> {
+    let engine = wasmtime::Engine::new(&create_engine_config())?;
+    let linker = create_linker(&engine)?;
+    let wasm_engine = WasmEngine::new(linker)?.with_limits(limits);
+    let component = wasmtime::component::Component::new(&engine, GROW_COMPONENT)?;
+
+    let host_state =
+        BrioHostState::with_provider("sqlite::memory:", Box::new(MockProvider)).await?;
+    let mut store = wasm_engine.prepare_store(host_state)?;
+
+    let instance = wasm_engine
+        .linker()
+        .instantiate_async(&mut store, &component)
+        .await?;
+    let grow = instance.get_typed_func::<(), (i32,)>(&mut store, "grow")?;
+    let (previous,) = grow.call_async(&mut store, ()).await?;
+    Ok(previous)
+}
+
+#[tokio::test]
+async fn test_memory_limit_denies_growth() -> Result<()> {
+    let limits = ResourceLimits::unlimited().with_max_memory_bytes(WASM_PAGE_SIZE);
+    assert_eq!(grow_once(limits).await?, -1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_memory_growth_allowed_within_limit() -> Result<()> {
+    let limits = ResourceLimits::unlimited().with_max_memory_bytes(2 * WASM_PAGE_SIZE);
+    assert_eq!(grow_once(limits).await?, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_memory_limit_rejects_instantiation() -> Result<()> {
+    let limits = ResourceLimits::unlimited().with_max_memory_bytes(WASM_PAGE_SIZE / 2);
+    assert!(grow_once(limits).await.is_err());
+    Ok(())
+}
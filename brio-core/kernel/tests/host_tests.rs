@@ -18,6 +18,7 @@ impl LLMProvider for MockProvider {
     async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse, InferenceError> {
         Ok(ChatResponse {
             content: "Mock response".to_string(),
+            tool_calls: Vec::new(),
             usage: None,
         })
     }
@@ -153,11 +154,91 @@ async fn test_register_multiple_components() -> Result<()> {
 #[tokio::test]
 async fn test_get_store() -> Result<()> {
     let host = BrioHostState::with_provider("sqlite::memory:", Box::new(MockProvider)).await?;
-    let _store = host.get_store("test_scope");
+    let _store = host.get_store();
     // Store should be created without error
     Ok(())
 }
 
+#[tokio::test]
+async fn test_sql_scope_isolates_plugins() -> Result<()> {
+    use brio_kernel::engine::brio::core::sql_state::Host as _;
+    use brio_kernel::store::TableGrant;
+    use std::collections::HashMap;
+
+    // A file database so every pooled connection sees the same tables
+    let dir = tempfile::tempdir()?;
+    let db_url = format!("sqlite://{}?mode=rwc", dir.path().join("brio.db").display());
+    let host = BrioHostState::with_provider(&db_url, Box::new(MockProvider))
+        .await?
+        .with_shared_tables(HashMap::from([(
+            "tasks".to_string(),
+            TableGrant {
+                read: vec!["reviewer".to_string()],
+                write: vec!["foreman".to_string()],
+            },
+        )]));
+    let storage = vec!["storage:read".to_string(), "storage:write".to_string()];
+
+    sqlx::query("CREATE TABLE tasks (id INTEGER PRIMARY KEY, content TEXT)")
+        .execute(host.db())
+        .await?;
+
+    let mut coder = host.with_plugin_context("coder".to_string(), storage.clone());
+    coder
+        .execute(
+            "CREATE TABLE coder_notes (id INTEGER PRIMARY KEY, note TEXT)".to_string(),
+            vec![],
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    assert!(
+        coder
+            .query("SELECT * FROM tasks".to_string(), vec![])
+            .await
+            .is_err()
+    );
+
+    // Another plugin cannot reach into coder's tables
+    let mut reviewer = host.with_plugin_context("reviewer".to_string(), storage.clone());
+    assert!(
+        reviewer
+            .query("SELECT * FROM coder_notes".to_string(), vec![])
+            .await
+            .is_err()
+    );
+
+    let mut foreman = host.with_plugin_context("foreman".to_string(), storage);
+    foreman
+        .execute(
+            "INSERT INTO tasks (content) VALUES (?)".to_string(),
+            vec!["refactor".to_string()],
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+    let rows = reviewer
+        .query("SELECT content FROM tasks".to_string(), vec![])
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    assert_eq!(rows[0].values, vec!["refactor"]);
+    assert!(
+        reviewer
+            .execute("DELETE FROM tasks".to_string(), vec![])
+            .await
+            .is_err()
+    );
+
+    // Host-level state has no plugin, hence no scope
+    assert!(
+        host.clone()
+            .query("SELECT 1".to_string(), vec![])
+            .await
+            .is_err()
+    );
+
+    Ok(())
+}
+
 // =============================================================================
 // Session Tests
 // =============================================================================
@@ -165,7 +246,9 @@ async fn test_get_store() -> Result<()> {
 #[tokio::test]
 async fn test_session_with_nonexistent_path() -> Result<()> {
     let host = BrioHostState::with_provider("sqlite::memory:", Box::new(MockProvider)).await?;
-    let result = host.begin_session("/nonexistent/path/12345".to_string());
+    let result = host
+        .begin_session("/nonexistent/path/12345".to_string())
+        .await;
 
     assert!(result.is_err());
     Ok(())
@@ -185,11 +268,12 @@ async fn test_session_begin_and_commit() -> Result<()> {
 
     let session_id = host
         .begin_session(temp.to_str().unwrap().to_string())
+        .await
         .unwrap();
     assert!(!session_id.is_empty());
 
     // Commit should succeed
-    let commit_result = host.commit_session(session_id);
+    let commit_result = host.commit_session(session_id).await;
     assert!(commit_result.is_ok());
 
     // Cleanup
@@ -198,6 +282,256 @@ async fn test_session_begin_and_commit() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_sessions_survive_host_restart() -> Result<()> {
+    use brio_kernel::vfs::persistence::SessionStore;
+
+    let base = tempfile::tempdir()?;
+    let db = tempfile::tempdir()?;
+    std::fs::write(base.path().join("test.txt"), "hello")?;
+    let db_url = format!("sqlite://{}?mode=rwc", db.path().join("brio.db").display());
+
+    let host = BrioHostState::with_provider(&db_url, Box::new(MockProvider)).await?;
+    let session_id = host
+        .with_plugin_context("coder".to_string(), vec![])
+        .with_task("task-7")
+        .begin_session(base.path().to_str().unwrap().to_string())
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    drop(host);
+
+    let session_path = std::env::temp_dir().join("brio").join(&session_id);
+    std::fs::write(session_path.join("test.txt"), "resumed")?;
+
+    let host = BrioHostState::with_provider(&db_url, Box::new(MockProvider)).await?;
+    let store = SessionStore::new(host.db().clone()).await?;
+    let records = store.load().await?;
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].owner_plugin.as_deref(), Some("coder"));
+    assert_eq!(records[0].task_id.as_deref(), Some("task-7"));
+
+    host.commit_session(session_id)
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    assert_eq!(
+        std::fs::read_to_string(base.path().join("test.txt"))?,
+        "resumed"
+    );
+    assert!(store.load().await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_guest_session_diff() -> Result<()> {
+    use brio_kernel::engine::brio::core::session_fs::{ChangeKind, Host as SessionFs};
+
+    let base = tempfile::tempdir()?;
+    std::fs::write(base.path().join("test.txt"), "hello\n")?;
+    let host = BrioHostState::with_provider("sqlite::memory:", Box::new(MockProvider)).await?;
+
+    let mut coder = host.with_plugin_context("coder".to_string(), vec!["fs:write".to_string()]);
+    let session_id =
+        SessionFs::begin_session(&mut coder, base.path().to_str().unwrap().to_string())
+            .await
+            .map_err(|e| anyhow::anyhow!(e))?;
+    let session_path = std::env::temp_dir().join("brio").join(&session_id);
+    std::fs::write(session_path.join("test.txt"), "hello, world\n")?;
+
+    let mut reviewer =
+        host.with_plugin_context("reviewer".to_string(), vec!["fs:read".to_string()]);
+    let diffs = SessionFs::diff(&mut reviewer, session_id.clone())
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    assert_eq!(diffs.len(), 1);
+    assert_eq!(diffs[0].path, "test.txt");
+    assert!(matches!(diffs[0].kind, ChangeKind::Modified));
+    assert!(diffs[0].patch.contains("-hello\n+hello, world\n"));
+
+    let mut outsider = host.with_plugin_context("outsider".to_string(), vec![]);
+    assert!(
+        SessionFs::diff(&mut outsider, session_id.clone())
+            .await
+            .is_err()
+    );
+
+    SessionFs::commit_session(&mut coder, session_id)
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_guest_session_file_operations() -> Result<()> {
+    use brio_kernel::engine::brio::core::session_fs::{EntryKind, Host as SessionFs};
+
+    let base = tempfile::tempdir()?;
+    std::fs::write(base.path().join("README.md"), "# Project\n")?;
+    let host = BrioHostState::with_provider("sqlite::memory:", Box::new(MockProvider)).await?;
+
+    let mut coder = host.with_plugin_context("coder".to_string(), vec!["fs:write".to_string()]);
+    let id = SessionFs::begin_session(&mut coder, base.path().to_str().unwrap().to_string())
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let fail = |e: String| anyhow::anyhow!(e);
+
+    SessionFs::write_file(
+        &mut coder,
+        id.clone(),
+        "src/lib.rs".into(),
+        b"fn a() {}\n".to_vec(),
+    )
+    .await
+    .map_err(fail)?;
+    SessionFs::rename(
+        &mut coder,
+        id.clone(),
+        "README.md".into(),
+        "docs/README.md".into(),
+    )
+    .await
+    .map_err(fail)?;
+    let listing: Vec<(String, bool)> = SessionFs::list_dir(&mut coder, id.clone(), ".".into())
+        .await
+        .map_err(fail)?
+        .into_iter()
+        .map(|e| (e.name, matches!(e.kind, EntryKind::Directory)))
+        .collect();
+    assert_eq!(
+        listing,
+        vec![("docs".to_string(), true), ("src".to_string(), true)]
+    );
+
+    // Reviewers may read but not write
+    let mut reviewer =
+        host.with_plugin_context("reviewer".to_string(), vec!["fs:read".to_string()]);
+    let content = SessionFs::read_file(&mut reviewer, id.clone(), "src/lib.rs".into())
+        .await
+        .map_err(fail)?;
+    assert_eq!(content, b"fn a() {}\n");
+    let stat = SessionFs::stat(&mut reviewer, id.clone(), "docs/README.md".into())
+        .await
+        .map_err(fail)?
+        .expect("renamed file exists");
+    assert_eq!(stat.size, 10);
+    assert!(
+        SessionFs::delete(&mut reviewer, id.clone(), "src/lib.rs".into())
+            .await
+            .unwrap_err()
+            .contains("Permission denied")
+    );
+
+    // Paths cannot leave the session
+    for path in ["../outside.txt", "/etc/passwd"] {
+        assert!(
+            SessionFs::read_file(&mut coder, id.clone(), path.into())
+                .await
+                .is_err()
+        );
+    }
+
+    SessionFs::delete(&mut coder, id.clone(), "src".into())
+        .await
+        .map_err(fail)?;
+    assert!(
+        SessionFs::stat(&mut coder, id.clone(), "src/lib.rs".into())
+            .await
+            .map_err(fail)?
+            .is_none()
+    );
+
+    SessionFs::commit_session(&mut coder, id)
+        .await
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    assert!(base.path().join("docs/README.md").exists());
+    assert!(!base.path().join("README.md").exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_guest_sessions_are_changed_by_their_owner_only() -> Result<()> {
+    use brio_kernel::engine::brio::core::session_fs::{CommitError, Host as SessionFs};
+
+    let base = tempfile::tempdir()?;
+    std::fs::write(base.path().join("lib.rs"), "fn a() {}\n")?;
+    let host = BrioHostState::with_provider("sqlite::memory:", Box::new(MockProvider)).await?;
+    let write = vec!["fs:write".to_string()];
+    let mut coder = host.with_plugin_context("coder".to_string(), write.clone());
+    let mut intruder = host.with_plugin_context("intruder".to_string(), write);
+
+    let id = SessionFs::begin_session(&mut coder, base.path().to_str().unwrap().to_string())
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+    let denied =
+        |result: Result<(), String>| result.unwrap_err().contains("belongs to another plugin");
+    assert!(denied(
+        SessionFs::write_file(&mut intruder, id.clone(), "lib.rs".into(), b"x".to_vec()).await
+    ));
+    assert!(denied(
+        SessionFs::delete(&mut intruder, id.clone(), "lib.rs".into()).await
+    ));
+    assert!(denied(
+        SessionFs::rename(&mut intruder, id.clone(), "lib.rs".into(), "main.rs".into()).await
+    ));
+    assert!(matches!(
+        SessionFs::commit_session(&mut intruder, id.clone()).await,
+        Err(CommitError::Failed(msg)) if msg.contains("belongs to another plugin")
+    ));
+
+    // Reads stay open, and the owner's session is untouched
+    let content = SessionFs::read_file(&mut intruder, id.clone(), "lib.rs".into())
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    assert_eq!(content, b"fn a() {}\n");
+    SessionFs::commit_session(&mut coder, id)
+        .await
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    Ok(())
+}
+
+fn preopened_dirs(host: &mut BrioHostState) -> Result<Vec<String>> {
+    use wasmtime_wasi::filesystem::WasiFilesystemView;
+    use wasmtime_wasi::p2::bindings::filesystem::preopens::Host;
+
+    let dirs = host.filesystem().get_directories()?;
+    Ok(dirs.into_iter().map(|(_, path)| path).collect())
+}
+
+#[tokio::test]
+async fn test_wasi_preopens_active_session_only_with_fs_permission() -> Result<()> {
+    use brio_kernel::engine::brio::core::session_fs::Host as SessionFs;
+
+    let temp = tempfile::tempdir()?;
+    std::fs::write(temp.path().join("test.txt"), "hello")?;
+    let base = temp.path().to_str().unwrap().to_string();
+
+    let host = BrioHostState::with_provider("sqlite::memory:", Box::new(MockProvider)).await?;
+
+    // A guest opening a session becomes scoped to it, even after its WASI
+    // context was first used without one
+    let mut guest = host.with_plugin_context("agent".to_string(), vec!["fs:write".to_string()]);
+    assert!(preopened_dirs(&mut guest)?.is_empty());
+    let session_id = SessionFs::begin_session(&mut guest, base)
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    assert_eq!(guest.active_session(), Some(session_id.as_str()));
+    assert_eq!(preopened_dirs(&mut guest)?, vec![".".to_string()]);
+
+    // Without filesystem permission nothing is preopened
+    let mut denied = host
+        .with_plugin_context("other".to_string(), vec![])
+        .with_active_session(session_id.clone());
+    assert!(preopened_dirs(&mut denied)?.is_empty());
+
+    SessionFs::commit_session(&mut guest, session_id)
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    assert_eq!(guest.active_session(), None);
+    assert!(preopened_dirs(&mut guest)?.is_empty());
+
+    Ok(())
+}
+
 // =============================================================================
 // Broadcast Patch Test
 // =============================================================================
@@ -256,3 +590,972 @@ async fn test_binary_payload_routing() -> Result<()> {
 
     Ok(())
 }
+
+/// An agent of the repo's `smart-agent` world whose `run` answers
+/// `Ok("done")` for an empty description and `Err("no")` otherwise.
+fn counting_agent() -> Result<Vec<u8>> {
+    use wit_component::{ComponentEncoder, StringEncoding};
+    use wit_parser::Resolve;
+
+    let mut resolve = Resolve::default();
+    let (package, _) = resolve.push_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/../wit"))?;
+    let world = resolve.select_world(&[package], Some("smart-agent"))?;
+    let mut module = wat::parse_str(
+        r#"(module
+            (memory (export "memory") 1)
+            (data (i32.const 16) "\00\00\00\00\40\00\00\00\04\00\00\00")
+            (data (i32.const 32) "\01\00\00\00\48\00\00\00\02\00\00\00")
+            (data (i32.const 64) "done")
+            (data (i32.const 72) "no")
+            (func (export "cabi_realloc") (param i32 i32 i32 i32) (result i32) (i32.const 1024))
+            (func (export "brio:core/agent-runner#run")
+                (param i32 i32 i32 i32 i32 i32) (result i32)
+                (if (result i32) (local.get 3)
+                    (then (i32.const 32))
+                    (else (i32.const 16))))
+            (func (export "brio:core/event-handler#handle-event")
+                (param i32 i32 i32 i32 i32)))"#,
+    )?;
+    wit_component::embed_component_metadata(&mut module, &resolve, world, StringEncoding::UTF8)?;
+    ComponentEncoder::default()
+        .module(&module)?
+        .validate(true)
+        .encode()
+}
+
+#[tokio::test]
+async fn test_mesh_call_reports_agent_fuel() -> Result<()> {
+    use brio_kernel::engine::brio::core::service_mesh::{
+        Host as ServiceMesh, Payload as WitPayload,
+    };
+    use brio_kernel::engine::budget::fuel_consumed;
+    use brio_kernel::inference::ProviderRegistry;
+    use brio_kernel::registry::PluginRegistry;
+
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("counter.wasm"), counting_agent()?)?;
+    let engine = wasmtime::Engine::new(&brio_kernel::engine::create_engine_config())?;
+    let plugins = PluginRegistry::new(engine);
+    plugins.load_from_directory(dir.path()).await?;
+    let host = BrioHostState::new(
+        "sqlite::memory:",
+        ProviderRegistry::new(),
+        Some(Arc::new(plugins)),
+        Default::default(),
+    )
+    .await?;
+
+    let task = |description: &str| {
+        Payload::Json(
+            serde_json::json!({
+                "task_id": "task-1",
+                "description": description,
+                "input_files": [],
+            })
+            .to_string(),
+        )
+    };
+    let reply = host.mesh_call_metered("counter", "run", task("")).await?;
+    assert!(matches!(reply.payload, Payload::Json(ref s) if s == "done"));
+    assert!(reply.fuel_consumed.is_some_and(|fuel| fuel > 0));
+
+    let error = host
+        .mesh_call_metered("counter", "run", task("refuse"))
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("no"), "{}", error);
+    assert!(fuel_consumed(&error).is_some_and(|fuel| fuel > 0));
+
+    // Guests see the fuel through `call-metered`
+    let mut caller = host.with_plugin_context("caller".to_string(), vec!["mesh:send".to_string()]);
+    let Payload::Json(context) = task("") else {
+        unreachable!()
+    };
+    let reply = ServiceMesh::call_metered(
+        &mut caller,
+        "counter".to_string(),
+        "run".to_string(),
+        WitPayload::Json(context),
+    )
+    .await
+    .map_err(|e| anyhow::anyhow!(e))?;
+    assert!(matches!(reply.payload, WitPayload::Json(ref s) if s == "done"));
+    assert!(reply.fuel_consumed.is_some_and(|fuel| fuel > 0));
+
+    // Routes without a local plugin have no fuel to report
+    let (tx, mut rx) = mpsc::channel::<MeshMessage>(1);
+    host.register_component("echo".to_string(), tx);
+    tokio::spawn(async move {
+        if let Some(msg) = rx.recv().await {
+            let _ = msg.reply_tx.send(Ok(msg.payload));
+        }
+    });
+    let reply = host.mesh_call_metered("echo", "ping", task("")).await?;
+    assert_eq!(reply.fuel_consumed, None);
+    Ok(())
+}
+
+// =============================================================================
+// Async Host Import Tests
+// =============================================================================
+
+// `#[tokio::test]` uses a current-thread runtime, where the former
+// `block_in_place` bridge would panic.
+#[tokio::test]
+async fn test_host_imports_run_on_current_thread_runtime() -> Result<()> {
+    use brio_kernel::engine::brio::ai::inference;
+    use brio_kernel::engine::brio::core::sql_state::Host as _;
+
+    let host = BrioHostState::with_provider("sqlite::memory:", Box::new(MockProvider)).await?;
+    let mut guest = host.with_plugin_context(
+        "agent".to_string(),
+        vec!["ai:inference".to_string(), "storage:read".to_string()],
+    );
+
+    let response = inference::Host::chat(
+        &mut guest,
+        "mock".to_string(),
+        vec![inference::Message {
+            role: inference::Role::User,
+            content: "hello".to_string(),
+            tool_calls: vec![],
+            tool_call_id: None,
+        }],
+    )
+    .await
+    .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    assert_eq!(response.content, "Mock response");
+
+    let denied = guest.execute("SELECT 1".to_string(), vec![]).await;
+    assert!(denied.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_guest_completion_stream() -> Result<()> {
+    use brio_kernel::engine::brio::ai::inference::{self, Host as _, HostCompletionStream};
+    use wasmtime::component::Resource;
+
+    let host = BrioHostState::with_provider("sqlite::memory:", Box::new(MockProvider)).await?;
+    let mut guest = host.with_plugin_context("agent".to_string(), vec!["ai:inference".to_string()]);
+
+    let stream = guest
+        .stream_chat(
+            "mock".to_string(),
+            vec![inference::Message {
+                role: inference::Role::User,
+                content: "hello".to_string(),
+                tool_calls: vec![],
+                tool_call_id: None,
+            }],
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    let rep = stream.rep();
+
+    // Providers without native streaming yield the whole response as one delta
+    let delta = guest
+        .next(Resource::new_borrow(rep))
+        .await
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    assert_eq!(delta.map(|d| d.content).as_deref(), Some("Mock response"));
+    let end = guest
+        .next(Resource::new_borrow(rep))
+        .await
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    assert!(end.is_none());
+
+    HostCompletionStream::drop(&mut guest, stream).await?;
+    assert!(guest.next(Resource::new_borrow(rep)).await.is_err());
+    Ok(())
+}
+
+/// Replies with its name and the model it was asked for.
+struct NamedProvider(&'static str);
+
+#[async_trait::async_trait]
+impl LLMProvider for NamedProvider {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        Ok(ChatResponse {
+            content: format!("{}:{}", self.0, request.model),
+            usage: None,
+            tool_calls: Vec::new(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_guest_model_routing() -> Result<()> {
+    use brio_kernel::engine::brio::ai::inference;
+    use brio_kernel::inference::ProviderRegistry;
+    use std::collections::HashMap;
+
+    let registry = ProviderRegistry::new();
+    registry.register("openai", NamedProvider("openai"));
+    registry.register("local", NamedProvider("local"));
+    registry.set_default("openai");
+    registry.add_alias("fast", "local/llama-3");
+
+    let host = BrioHostState::new("sqlite::memory:", registry, None, Default::default())
+        .await?
+        .with_allowed_models(HashMap::from([(
+            "restricted".to_string(),
+            vec!["local/*".to_string()],
+        )]));
+    let permissions = vec!["ai:inference".to_string()];
+    let mut agent = host.with_plugin_context("agent".to_string(), permissions.clone());
+    let mut restricted = host.with_plugin_context("restricted".to_string(), permissions);
+
+    let hello = || {
+        vec![inference::Message {
+            role: inference::Role::User,
+            content: "hello".to_string(),
+            tool_calls: vec![],
+            tool_call_id: None,
+        }]
+    };
+    let content = |result: Result<inference::CompletionResponse, inference::InferenceError>| {
+        result.map(|r| r.content).map_err(|e| format!("{:?}", e))
+    };
+
+    assert_eq!(
+        content(inference::Host::chat(&mut agent, "local/llama-3".to_string(), hello()).await),
+        Ok("local:llama-3".to_string())
+    );
+    assert_eq!(
+        content(inference::Host::chat(&mut agent, "gpt-4o".to_string(), hello()).await),
+        Ok("openai:gpt-4o".to_string())
+    );
+    assert_eq!(
+        content(inference::Host::chat(&mut agent, "fast".to_string(), hello()).await),
+        Ok("local:llama-3".to_string())
+    );
+    assert!(matches!(
+        inference::Host::chat(&mut agent, "anthropic/claude-x".to_string(), hello()).await,
+        Err(inference::InferenceError::ProviderNotFound(msg)) if msg.contains("anthropic")
+    ));
+
+    assert_eq!(
+        content(inference::Host::chat(&mut restricted, "fast".to_string(), hello()).await),
+        Ok("local:llama-3".to_string())
+    );
+    assert!(matches!(
+        inference::Host::chat(&mut restricted, "gpt-4o".to_string(), hello()).await,
+        Err(inference::InferenceError::ModelNotAllowed(msg)) if msg.contains("openai/gpt-4o")
+    ));
+    Ok(())
+}
+
+/// Calls the first offered tool, then answers with the tool's result.
+struct ToolCallingProvider;
+
+#[async_trait::async_trait]
+impl LLMProvider for ToolCallingProvider {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        use brio_kernel::inference::{Role, ToolCall};
+
+        if let Some(result) = request.messages.iter().find(|m| m.role == Role::Tool) {
+            return Ok(ChatResponse {
+                content: format!("Found: {}", result.content),
+                usage: None,
+                tool_calls: Vec::new(),
+            });
+        }
+        let tool = request
+            .tools
+            .first()
+            .ok_or_else(|| InferenceError::ProviderError("no tools offered".to_string()))?;
+        Ok(ChatResponse {
+            content: String::new(),
+            usage: None,
+            tool_calls: vec![ToolCall {
+                id: "call_1".to_string(),
+                name: tool.name.clone(),
+                arguments: tool.parameters["required"].to_string(),
+            }],
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_guest_tool_calling() -> Result<()> {
+    use brio_kernel::engine::brio::ai::inference::{self, Host as _};
+
+    let host =
+        BrioHostState::with_provider("sqlite::memory:", Box::new(ToolCallingProvider)).await?;
+    let mut guest = host.with_plugin_context("agent".to_string(), vec!["ai:inference".to_string()]);
+
+    let user = inference::Message {
+        role: inference::Role::User,
+        content: "Find TODOs".to_string(),
+        tool_calls: vec![],
+        tool_call_id: None,
+    };
+    let grep = inference::ToolDefinition {
+        name: "grep".to_string(),
+        description: "Search files".to_string(),
+        parameters: r#"{"type":"object","required":["pattern"]}"#.to_string(),
+    };
+
+    let response = guest
+        .chat_with_options(
+            "mock".to_string(),
+            vec![user.clone()],
+            tool_options(vec![grep.clone()]),
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    assert_eq!(response.tool_calls.len(), 1);
+    let call = response.tool_calls[0].clone();
+    assert_eq!(call.name, "grep");
+    assert_eq!(call.arguments, r#"["pattern"]"#);
+
+    let answer = guest
+        .chat_with_options(
+            "mock".to_string(),
+            vec![
+                user.clone(),
+                inference::Message {
+                    role: inference::Role::Assistant,
+                    content: String::new(),
+                    tool_calls: vec![call.clone()],
+                    tool_call_id: None,
+                },
+                inference::Message {
+                    role: inference::Role::Tool,
+                    content: "src/lib.rs:3".to_string(),
+                    tool_calls: vec![],
+                    tool_call_id: Some(call.id),
+                },
+            ],
+            tool_options(vec![grep.clone()]),
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    assert_eq!(answer.content, "Found: src/lib.rs:3");
+
+    let invalid = guest
+        .chat_with_options(
+            "mock".to_string(),
+            vec![user],
+            tool_options(vec![inference::ToolDefinition {
+                parameters: "not json".to_string(),
+                ..grep
+            }]),
+        )
+        .await;
+    assert!(matches!(
+        invalid,
+        Err(inference::InferenceError::ProviderError(msg)) if msg.contains("grep")
+    ));
+    Ok(())
+}
+
+fn tool_options(
+    tools: Vec<brio_kernel::engine::brio::ai::inference::ToolDefinition>,
+) -> brio_kernel::engine::brio::ai::inference::ChatOptions {
+    brio_kernel::engine::brio::ai::inference::ChatOptions {
+        tools,
+        temperature: None,
+        top_p: None,
+        stop: vec![],
+        max_tokens: None,
+        response_format: None,
+    }
+}
+
+#[tokio::test]
+async fn test_guest_structured_output() -> Result<()> {
+    use brio_kernel::engine::brio::ai::inference::{self, Host as _};
+    use brio_kernel::inference::ProviderRegistry;
+
+    // Echoes the last message, so the guest controls the answer
+    let registry = ProviderRegistry::new();
+    registry.register("mock", brio_kernel::inference::MockProvider::new());
+    let host = BrioHostState::new("sqlite::memory:", registry, None, Default::default()).await?;
+    let mut guest = host.with_plugin_context("agent".to_string(), vec!["ai:inference".to_string()]);
+
+    let ask = |content: &str| {
+        vec![inference::Message {
+            role: inference::Role::User,
+            content: content.to_string(),
+            tool_calls: vec![],
+            tool_call_id: None,
+        }]
+    };
+    let verdict = |schema: &str| inference::ChatOptions {
+        temperature: Some(0.0),
+        response_format: Some(inference::ResponseFormat::JsonSchema(
+            inference::JsonSchemaFormat {
+                name: "verdict".to_string(),
+                schema: schema.to_string(),
+            },
+        )),
+        ..tool_options(vec![])
+    };
+    let schema =
+        r#"{"type":"object","required":["approved"],"properties":{"approved":{"type":"boolean"}}}"#;
+
+    let answer = guest
+        .chat_with_options(
+            "mock".to_string(),
+            ask(r#"{"approved": true}"#),
+            verdict(schema),
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    assert_eq!(answer.content, r#"{"approved": true}"#);
+
+    let rejected = guest
+        .chat_with_options("mock".to_string(), ask("Looks good to me"), verdict(schema))
+        .await;
+    assert!(matches!(
+        rejected,
+        Err(inference::InferenceError::InvalidResponse(msg)) if msg.contains("not valid JSON")
+    ));
+    let rejected = guest
+        .chat_with_options(
+            "mock".to_string(),
+            ask(r#"{"approved": "yes"}"#),
+            verdict(schema),
+        )
+        .await;
+    assert!(matches!(
+        rejected,
+        Err(inference::InferenceError::InvalidResponse(msg)) if msg.contains("verdict")
+    ));
+
+    let invalid = guest
+        .chat_with_options("mock".to_string(), ask("{}"), verdict("not json"))
+        .await;
+    assert!(matches!(
+        invalid,
+        Err(inference::InferenceError::ProviderError(msg)) if msg.contains("verdict")
+    ));
+    let invalid = guest
+        .chat_with_options(
+            "mock".to_string(),
+            ask("{}"),
+            inference::ChatOptions {
+                temperature: Some(3.0),
+                ..tool_options(vec![])
+            },
+        )
+        .await;
+    assert!(matches!(
+        invalid,
+        Err(inference::InferenceError::ProviderError(msg)) if msg.contains("temperature")
+    ));
+    Ok(())
+}
+
+/// Always unavailable, as after a 5xx response.
+struct DownProvider;
+
+#[async_trait::async_trait]
+impl LLMProvider for DownProvider {
+    async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        Err(InferenceError::ProviderUnavailable("HTTP 503".to_string()))
+    }
+}
+
+#[tokio::test]
+async fn test_fallback_chain_accounts_serving_member() -> Result<()> {
+    use brio_kernel::inference::{ProviderRegistry, UsageFilter};
+    use std::collections::HashMap;
+
+    let registry = ProviderRegistry::new();
+    registry.register("down", DownProvider);
+    registry.register("openai", MeteredProvider);
+    registry.register("local", MeteredProvider);
+    registry.add_chain(
+        "resilient",
+        vec![
+            "down".to_string(),
+            "openai".to_string(),
+            "local/llama-3".to_string(),
+        ],
+    )?;
+
+    let host = BrioHostState::new("sqlite::memory:", registry, None, Default::default())
+        .await?
+        .with_allowed_models(HashMap::from([(
+            "restricted".to_string(),
+            vec!["local/*".to_string()],
+        )]));
+    let agent = host.with_plugin_context("agent".to_string(), vec![]);
+    let restricted = host.with_plugin_context("restricted".to_string(), vec![]);
+
+    // The unavailable member is skipped, the next one serves the call
+    agent
+        .chat(ChatRequest::new("resilient/gpt-4o", vec![]))
+        .await?;
+    // Members the plugin may not use are skipped too
+    restricted
+        .chat(ChatRequest::new("resilient/gpt-4o", vec![]))
+        .await?;
+
+    let summary = host.usage().summarize(&UsageFilter::default()).await?;
+    let served: Vec<_> = summary
+        .iter()
+        .map(|s| (s.plugin_id.as_str(), s.provider.as_str(), s.model.as_str()))
+        .collect();
+    assert_eq!(served.len(), 2);
+    assert!(served.contains(&("agent", "openai", "gpt-4o")));
+    assert!(served.contains(&("restricted", "local", "llama-3")));
+    Ok(())
+}
+
+/// Rejects prompts over 500 characters, answers summary requests with
+/// "summary", and otherwise reports how many messages it got.
+struct SmallWindowProvider;
+
+#[async_trait::async_trait]
+impl LLMProvider for SmallWindowProvider {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        if request.messages[0].content.starts_with("Summarize") {
+            return Ok(ChatResponse {
+                content: "summary".to_string(),
+                usage: None,
+                tool_calls: vec![],
+            });
+        }
+        let chars: usize = request.messages.iter().map(|m| m.content.len()).sum();
+        if chars > 500 {
+            return Err(InferenceError::ContextLengthExceeded);
+        }
+        Ok(ChatResponse {
+            content: request.messages.len().to_string(),
+            usage: None,
+            tool_calls: vec![],
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_guest_context_management() -> Result<()> {
+    use brio_kernel::engine::brio::ai::inference;
+    use brio_kernel::inference::{ContextStrategy, ProviderRegistry};
+    use brio_kernel::infrastructure::config::ContextSettings;
+
+    let host = |settings: ContextSettings| async move {
+        let registry = ProviderRegistry::new();
+        registry.register("local", SmallWindowProvider);
+        let host = BrioHostState::new("sqlite::memory:", registry, None, Default::default())
+            .await?
+            .with_context_management(settings);
+        Ok::<_, anyhow::Error>(
+            host.with_plugin_context("agent".to_string(), vec!["ai:inference".to_string()]),
+        )
+    };
+    let message = |role, content: String| inference::Message {
+        role,
+        content,
+        tool_calls: vec![],
+        tool_call_id: None,
+    };
+    // Six 100-character turns and a question
+    let mut history = vec![message(inference::Role::System, "Be brief".to_string())];
+    for i in 0..6 {
+        let role = if i % 2 == 0 {
+            inference::Role::User
+        } else {
+            inference::Role::Assistant
+        };
+        history.push(message(role, "x".repeat(100)));
+    }
+    history.push(message(inference::Role::User, "So?".to_string()));
+
+    let mut unmanaged = host(ContextSettings::default()).await?;
+    let overflow = inference::Host::chat(&mut unmanaged, "local/tiny".to_string(), history.clone())
+        .await
+        .map(|r| r.content);
+    assert!(matches!(
+        overflow,
+        Err(inference::InferenceError::ContextLengthExceeded)
+    ));
+
+    // Unknown window: the overflow is retried once with a shorter history
+    let mut managed = host(ContextSettings {
+        enabled: true,
+        ..ContextSettings::default()
+    })
+    .await?;
+    let response = inference::Host::chat(&mut managed, "local/tiny".to_string(), history.clone())
+        .await
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    let report = response.context.expect("history was compacted");
+    assert!(report.retried);
+    assert_eq!(report.dropped_messages, 2);
+    assert_eq!(response.content, "6");
+
+    // Known window: compacted before sending
+    let mut managed = host(ContextSettings {
+        enabled: true,
+        reserve_tokens: 0,
+        context_windows: [("local/tiny".to_string(), 100)].into(),
+        ..ContextSettings::default()
+    })
+    .await?;
+    let response = inference::Host::chat(&mut managed, "local/tiny".to_string(), history.clone())
+        .await
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    let report = response.context.expect("history was compacted");
+    assert!(!report.retried);
+    assert_eq!(report.dropped_messages, 3);
+    assert!(report.summary.is_none());
+
+    let summarize = || ContextSettings {
+        enabled: true,
+        strategy: ContextStrategy::Summarize,
+        summary_model: Some("local/big".to_string()),
+        ..ContextSettings::default()
+    };
+    let mut summarizing = host(summarize()).await?;
+    let response =
+        inference::Host::chat(&mut summarizing, "local/tiny".to_string(), history.clone())
+            .await
+            .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    let report = response.context.expect("history was compacted");
+    assert_eq!(report.summary.as_deref(), Some("summary"));
+    assert!(report.retried);
+
+    // A summary model off the caller's allow-list is refused: trimmed instead
+    let registry = ProviderRegistry::new();
+    registry.register("local", SmallWindowProvider);
+    let mut restricted = BrioHostState::new("sqlite::memory:", registry, None, Default::default())
+        .await?
+        .with_context_management(summarize())
+        .with_allowed_models([("agent".to_string(), vec!["local/tiny".to_string()])].into())
+        .with_plugin_context("agent".to_string(), vec!["ai:inference".to_string()]);
+    let response = inference::Host::chat(&mut restricted, "local/tiny".to_string(), history)
+        .await
+        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    let report = response.context.expect("history was compacted");
+    assert!(report.summary.is_none());
+    Ok(())
+}
+
+/// Reports 40 tokens per completion.
+struct MeteredProvider;
+
+#[async_trait::async_trait]
+impl LLMProvider for MeteredProvider {
+    async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        Ok(ChatResponse {
+            content: "ok".to_string(),
+            tool_calls: Vec::new(),
+            usage: Some(brio_kernel::inference::Usage {
+                prompt_tokens: 30,
+                completion_tokens: 10,
+                total_tokens: 40,
+            }),
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_guest_token_usage_and_budgets() -> Result<()> {
+    use brio_kernel::engine::brio::ai::inference;
+    use brio_kernel::inference::UsageFilter;
+    use brio_kernel::infrastructure::config::{BudgetSettings, TokenBudget};
+
+    let host = BrioHostState::with_provider("sqlite::memory:", Box::new(MeteredProvider))
+        .await?
+        .with_token_budgets(BudgetSettings {
+            task: Some(TokenBudget {
+                soft_limit: None,
+                hard_limit: Some(80),
+            }),
+            ..Default::default()
+        });
+    let mut agent = host
+        .with_plugin_context("coder".to_string(), vec!["ai:inference".to_string()])
+        .with_task("task-1");
+
+    let hello = || {
+        vec![inference::Message {
+            role: inference::Role::User,
+            content: "hello".to_string(),
+            tool_calls: vec![],
+            tool_call_id: None,
+        }]
+    };
+    for _ in 0..2 {
+        inference::Host::chat(&mut agent, "gpt-4o".to_string(), hello())
+            .await
+            .expect("within budget");
+    }
+    assert!(matches!(
+        inference::Host::chat(&mut agent, "gpt-4o".to_string(), hello()).await,
+        Err(inference::InferenceError::BudgetExceeded(msg)) if msg.contains("task-1")
+    ));
+
+    let summary = host.usage().summarize(&UsageFilter::default()).await?;
+    assert_eq!(summary.len(), 1);
+    assert_eq!(summary[0].plugin_id, "coder");
+    assert_eq!(summary[0].task_id.as_deref(), Some("task-1"));
+    assert_eq!(summary[0].provider, "default");
+    assert_eq!(summary[0].model, "gpt-4o");
+    assert_eq!(summary[0].requests, 2);
+    assert_eq!(summary[0].total_tokens, 80);
+    Ok(())
+}
+
+/// Embeds each input as `[length, index]`.
+struct EmbeddingProvider;
+
+#[async_trait::async_trait]
+impl LLMProvider for EmbeddingProvider {
+    async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        Err(InferenceError::ProviderError(
+            "chat unsupported".to_string(),
+        ))
+    }
+
+    async fn embed(
+        &self,
+        request: brio_kernel::inference::EmbeddingRequest,
+    ) -> Result<brio_kernel::inference::EmbeddingResponse, InferenceError> {
+        Ok(brio_kernel::inference::EmbeddingResponse {
+            embeddings: request
+                .inputs
+                .iter()
+                .enumerate()
+                .map(|(i, input)| vec![input.len() as f32, i as f32])
+                .collect(),
+            usage: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_guest_embeddings() -> Result<()> {
+    use brio_kernel::engine::brio::ai::inference;
+    use brio_kernel::inference::ProviderRegistry;
+
+    let registry = ProviderRegistry::new();
+    registry.register("openai", EmbeddingProvider);
+    registry.register("chat-only", MockProvider);
+    registry.set_default("openai");
+
+    let host = BrioHostState::new("sqlite::memory:", registry, None, Default::default()).await?;
+    let mut agent = host.with_plugin_context("agent".to_string(), vec!["ai:inference".to_string()]);
+    let mut denied = host.with_plugin_context("denied".to_string(), vec![]);
+
+    let inputs = vec!["fn".to_string(), "struct".to_string()];
+    let vectors = inference::Host::embed(
+        &mut agent,
+        "text-embedding-3-small".to_string(),
+        inputs.clone(),
+    )
+    .await
+    .map_err(|e| format!("{:?}", e));
+    assert_eq!(vectors, Ok(vec![vec![2.0, 0.0], vec![6.0, 1.0]]));
+
+    assert!(matches!(
+        inference::Host::embed(&mut agent, "chat-only/any".to_string(), inputs.clone()).await,
+        Err(inference::InferenceError::ProviderError(msg)) if msg.contains("embeddings")
+    ));
+    assert!(matches!(
+        inference::Host::embed(&mut denied, "text-embedding-3-small".to_string(), inputs).await,
+        Err(inference::InferenceError::ProviderError(msg)) if msg.contains("ai:inference")
+    ));
+    Ok(())
+}
+
+// =============================================================================
+// Plugin Manifest Tests
+// =============================================================================
+
+#[tokio::test]
+async fn test_manifest_subscriptions_registered_on_startup() -> Result<()> {
+    use brio_kernel::inference::ProviderRegistry;
+    use brio_kernel::registry::PluginRegistry;
+
+    let dir = tempfile::tempdir()?;
+    // Classified by its export name alone; never invoked here
+    std::fs::write(
+        dir.path().join("listener.wasm"),
+        r#"(component (instance $h) (export "brio:core/event-handler" (instance $h)))"#,
+    )?;
+    std::fs::write(
+        dir.path().join("listener.toml"),
+        "id = \"listener\"\nversion = \"0.1.0\"\nsubscriptions = [\"task.created\"]",
+    )?;
+
+    let engine = wasmtime::Engine::new(&brio_kernel::engine::create_engine_config())?;
+    let plugins = PluginRegistry::new(engine);
+    plugins.load_from_directory(dir.path()).await?;
+
+    let host = BrioHostState::new(
+        "sqlite::memory:",
+        ProviderRegistry::new(),
+        Some(Arc::new(plugins)),
+        Default::default(),
+    )
+    .await?;
+
+    assert_eq!(
+        host.event_bus().subscribers("task.created"),
+        vec!["listener"]
+    );
+    Ok(())
+}
+
+// =============================================================================
+// Planner Tests
+// =============================================================================
+
+struct FixedPlanner;
+
+#[async_trait::async_trait]
+impl brio_kernel::planner::Planner for FixedPlanner {
+    async fn decompose(
+        &self,
+        _inference: &dyn LLMProvider,
+        objective: &str,
+    ) -> Result<brio_kernel::planner::Plan, brio_kernel::planner::PlannerError> {
+        Ok(brio_kernel::planner::Plan {
+            steps: vec![brio_kernel::planner::Subtask {
+                id: "only".to_string(),
+                description: objective.to_string(),
+            }],
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_planner_import_uses_host_planner() -> Result<()> {
+    use brio_kernel::engine::brio::core::planner::Host as PlannerHost;
+
+    let host = BrioHostState::with_provider("sqlite::memory:", Box::new(MockProvider))
+        .await?
+        .with_planner(Arc::new(FixedPlanner));
+
+    let mut denied = host.with_plugin_context("supervisor".to_string(), vec![]);
+    // The inherent `decompose` bypasses the permission check; the import does not
+    assert!(
+        PlannerHost::decompose(&mut denied, "ship it".to_string())
+            .await
+            .is_err()
+    );
+
+    let mut supervisor =
+        host.with_plugin_context("supervisor".to_string(), vec!["ai:inference".to_string()]);
+    let plan = PlannerHost::decompose(&mut supervisor, "ship it".to_string())
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    assert_eq!(plan.steps.len(), 1);
+    assert_eq!(plan.steps[0].id, "only");
+    assert_eq!(plan.steps[0].description, "ship it");
+    Ok(())
+}
+
+/// Answers every prompt with an empty plan, reporting 40 tokens.
+struct PlanningProvider;
+
+#[async_trait::async_trait]
+impl LLMProvider for PlanningProvider {
+    async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse, InferenceError> {
+        Ok(ChatResponse {
+            content: r#"{"steps": []}"#.to_string(),
+            tool_calls: Vec::new(),
+            usage: Some(brio_kernel::inference::Usage {
+                prompt_tokens: 30,
+                completion_tokens: 10,
+                total_tokens: 40,
+            }),
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_host_planner_is_accounted_to_the_caller() -> Result<()> {
+    use brio_kernel::inference::UsageFilter;
+    use brio_kernel::planner::PlannerError;
+
+    let host = BrioHostState::with_provider("sqlite::memory:", Box::new(PlanningProvider))
+        .await?
+        .with_allowed_models(std::collections::HashMap::from([(
+            "reviewer".to_string(),
+            vec!["default/gpt-4o".to_string()],
+        )]));
+
+    let coder = host
+        .with_plugin_context("coder".to_string(), vec!["ai:inference".to_string()])
+        .with_task("task-9");
+    assert!(coder.decompose("ship it").await?.steps.is_empty());
+    let summary = host.usage().summarize(&UsageFilter::default()).await?;
+    assert_eq!(summary.len(), 1);
+    assert_eq!(summary[0].plugin_id, "coder");
+    assert_eq!(summary[0].task_id.as_deref(), Some("task-9"));
+    assert_eq!(summary[0].total_tokens, 40);
+
+    // The planner model is subject to the caller's allow-list
+    let reviewer = host.with_plugin_context("reviewer".to_string(), vec![]);
+    assert!(matches!(
+        reviewer.decompose("ship it").await,
+        Err(PlannerError::Inference(InferenceError::ModelNotAllowed(_)))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_planner_plugin_takes_over() -> Result<()> {
+    use brio_kernel::inference::ProviderRegistry;
+    use brio_kernel::registry::{PluginRegistry, PluginWorld};
+
+    // Exports `brio:core/planner`; `decompose` returns an empty plan
+    let dir = tempfile::tempdir()?;
+    std::fs::write(
+        dir.path().join("planner.wasm"),
+        r#"(component
+            (core module $m
+                (memory (export "memory") 1)
+                (func (export "realloc") (param i32 i32 i32 i32) (result i32) (i32.const 64))
+                (func (export "decompose") (param i32 i32) (result i32) (i32.const 16)))
+            (core instance $i (instantiate $m))
+            (type $subtask' (record (field "id" string) (field "description" string)))
+            (export $subtask "subtask" (type $subtask'))
+            (type $plan' (record (field "steps" (list $subtask))))
+            (export $plan "plan" (type $plan'))
+            (func $decompose (param "objective" string) (result (result $plan (error string)))
+                (canon lift (core func $i "decompose") (memory $i "memory") (realloc (func $i "realloc"))))
+            (instance $planner
+                (export "subtask" (type $subtask))
+                (export "plan" (type $plan))
+                (export "decompose" (func $decompose)))
+            (export "brio:core/planner" (instance $planner)))"#,
+    )?;
+
+    let engine = wasmtime::Engine::new(&brio_kernel::engine::create_engine_config())?;
+    let plugins = PluginRegistry::new(engine);
+    plugins.load_from_directory(dir.path()).await?;
+    assert_eq!(
+        plugins.get("planner").map(|p| p.world),
+        Some(PluginWorld::Planner)
+    );
+
+    let host = BrioHostState::new(
+        "sqlite::memory:",
+        ProviderRegistry::new(),
+        Some(Arc::new(plugins)),
+        Default::default(),
+    )
+    .await?
+    .with_planner(Arc::new(FixedPlanner));
+
+    // Delegated to the plugin rather than `FixedPlanner`
+    let plan = host.decompose("ship it").await?;
+    assert!(plan.steps.is_empty());
+
+    // The plugin itself falls back to the host planner
+    let plan = host
+        .with_plugin_context("planner".to_string(), vec![])
+        .decompose("ship it")
+        .await?;
+    assert_eq!(plan.steps.len(), 1);
+    Ok(())
+}
@@ -21,10 +21,7 @@ fn test_role_variants() {
 
 #[test]
 fn test_message_construction() {
-    let msg = Message {
-        role: Role::User,
-        content: "Hello, world!".to_string(),
-    };
+    let msg = Message::new(Role::User, "Hello, world!".to_string());
 
     assert!(matches!(msg.role, Role::User));
     assert_eq!(msg.content, "Hello, world!");
@@ -45,19 +42,13 @@ fn test_usage_struct() {
 
 #[test]
 fn test_chat_request_construction() {
-    let request = ChatRequest {
-        model: "gpt-4".to_string(),
-        messages: vec![
-            Message {
-                role: Role::System,
-                content: "You are helpful.".to_string(),
-            },
-            Message {
-                role: Role::User,
-                content: "Hi!".to_string(),
-            },
+    let request = ChatRequest::new(
+        "gpt-4",
+        vec![
+            Message::new(Role::System, "You are helpful.".to_string()),
+            Message::new(Role::User, "Hi!".to_string()),
         ],
-    };
+    );
 
     assert_eq!(request.model, "gpt-4");
     assert_eq!(request.messages.len(), 2);
@@ -67,6 +58,7 @@ fn test_chat_request_construction() {
 fn test_chat_response_with_usage() {
     let response = ChatResponse {
         content: "Hello!".to_string(),
+        tool_calls: Vec::new(),
         usage: Some(Usage {
             prompt_tokens: 5,
             completion_tokens: 1,
@@ -83,6 +75,7 @@ fn test_chat_response_with_usage() {
 fn test_chat_response_without_usage() {
     let response = ChatResponse {
         content: "Response".to_string(),
+        tool_calls: Vec::new(),
         usage: None,
     };
 
@@ -153,10 +146,7 @@ fn test_role_serialization() {
 
 #[test]
 fn test_message_serialization() {
-    let msg = Message {
-        role: Role::User,
-        content: "Test message".to_string(),
-    };
+    let msg = Message::new(Role::User, "Test message".to_string());
 
     let json = serde_json::to_string(&msg).unwrap();
     assert!(json.contains(r#""role":"user""#));
@@ -201,6 +191,7 @@ impl LLMProvider for TestMockProvider {
     async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse, InferenceError> {
         Ok(ChatResponse {
             content: self.response.clone(),
+            tool_calls: Vec::new(),
             usage: None,
         })
     }
@@ -212,13 +203,10 @@ async fn test_mock_provider_returns_configured_response() {
         response: "Mocked response".to_string(),
     };
 
-    let request = ChatRequest {
-        model: "test-model".to_string(),
-        messages: vec![Message {
-            role: Role::User,
-            content: "Hello".to_string(),
-        }],
-    };
+    let request = ChatRequest::new(
+        "test-model",
+        vec![Message::new(Role::User, "Hello".to_string())],
+    );
 
     let response = provider.chat(request).await.unwrap();
     assert_eq!(response.content, "Mocked response");
@@ -239,10 +227,7 @@ impl LLMProvider for FailingMockProvider {
 async fn test_failing_provider_returns_error() {
     let provider = FailingMockProvider;
 
-    let request = ChatRequest {
-        model: "test-model".to_string(),
-        messages: vec![],
-    };
+    let request = ChatRequest::new("test-model", vec![]);
 
     let result = provider.chat(request).await;
     assert!(result.is_err());
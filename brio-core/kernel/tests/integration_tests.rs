@@ -10,6 +10,7 @@ impl LLMProvider for MockProvider {
     async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse, InferenceError> {
         Ok(ChatResponse {
             content: "Mock response".to_string(),
+            tool_calls: Vec::new(),
             usage: None,
         })
     }
@@ -28,7 +29,7 @@ async fn test_basic_host_interaction() -> Result<()> {
     let wasm_engine = WasmEngine::new(linker)?;
 
     // 3. Prepare store (this injects host state)
-    let mut store = wasm_engine.prepare_store(host_state);
+    let mut store = wasm_engine.prepare_store(host_state)?;
 
     // 4. Create a dummy component that does nothing just to verify instantiation
     let component = wasmtime::component::Component::new(&engine, r#"(component)"#)?;
@@ -12,6 +12,7 @@ impl brio_kernel::inference::LLMProvider for DummyProvider {
     ) -> Result<brio_kernel::inference::ChatResponse, brio_kernel::inference::InferenceError> {
         Ok(brio_kernel::inference::ChatResponse {
             content: "".to_string(),
+            tool_calls: Vec::new(),
             usage: None,
         })
     }
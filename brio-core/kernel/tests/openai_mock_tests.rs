@@ -20,13 +20,7 @@ async fn create_provider_with_mock_server(server: &MockServer) -> OpenAIProvider
 }
 
 fn create_test_request() -> ChatRequest {
-    ChatRequest {
-        model: "gpt-4".to_string(),
-        messages: vec![Message {
-            role: Role::User,
-            content: "Hello".to_string(),
-        }],
-    }
+    ChatRequest::new("gpt-4", vec![Message::new(Role::User, "Hello".to_string())])
 }
 
 // =============================================================================
@@ -57,7 +51,7 @@ async fn test_rate_limit_returns_rate_limit_error() {
 // =============================================================================
 
 #[tokio::test]
-async fn test_server_error_returns_provider_error() {
+async fn test_server_error_returns_provider_unavailable() {
     let server = MockServer::start().await;
 
     Mock::given(method("POST"))
@@ -72,15 +66,15 @@ async fn test_server_error_returns_provider_error() {
     let result = provider.chat(request).await;
 
     assert!(result.is_err());
-    if let InferenceError::ProviderError(msg) = result.unwrap_err() {
+    if let InferenceError::ProviderUnavailable(msg) = result.unwrap_err() {
         assert!(msg.contains("500"));
     } else {
-        panic!("Expected ProviderError");
+        panic!("Expected ProviderUnavailable");
     }
 }
 
 #[tokio::test]
-async fn test_service_unavailable_returns_provider_error() {
+async fn test_service_unavailable_returns_provider_unavailable() {
     let server = MockServer::start().await;
 
     Mock::given(method("POST"))
@@ -95,10 +89,10 @@ async fn test_service_unavailable_returns_provider_error() {
     let result = provider.chat(request).await;
 
     assert!(result.is_err());
-    if let InferenceError::ProviderError(msg) = result.unwrap_err() {
+    if let InferenceError::ProviderUnavailable(msg) = result.unwrap_err() {
         assert!(msg.contains("503"));
     } else {
-        panic!("Expected ProviderError");
+        panic!("Expected ProviderUnavailable");
     }
 }
 
@@ -228,3 +222,245 @@ async fn test_successful_response_parses_correctly() {
     assert_eq!(usage.completion_tokens, 8);
     assert_eq!(usage.total_tokens, 18);
 }
+
+// =============================================================================
+// Tool Calling Tests
+// =============================================================================
+
+#[tokio::test]
+async fn test_tool_calls_round_trip() {
+    use brio_kernel::inference::{ToolCall, ToolDefinition};
+    use wiremock::matchers::body_partial_json;
+
+    let server = MockServer::start().await;
+
+    let response_body = r#"{
+        "choices": [
+            {
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [
+                        {
+                            "id": "call_1",
+                            "type": "function",
+                            "function": { "name": "grep", "arguments": "{\"pattern\":\"TODO\"}" }
+                        }
+                    ]
+                },
+                "finish_reason": "tool_calls"
+            }
+        ]
+    }"#;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(serde_json::json!({
+            "tools": [{
+                "type": "function",
+                "function": { "name": "grep", "parameters": { "type": "object" } }
+            }]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(response_body))
+        .mount(&server)
+        .await;
+
+    let provider = create_provider_with_mock_server(&server).await;
+    let request = create_test_request().with_tools(vec![ToolDefinition {
+        name: "grep".to_string(),
+        description: "Search files for a pattern".to_string(),
+        parameters: serde_json::json!({ "type": "object" }),
+    }]);
+
+    let response = provider.chat(request).await.unwrap();
+    assert_eq!(response.content, "");
+    assert_eq!(
+        response.tool_calls,
+        vec![ToolCall {
+            id: "call_1".to_string(),
+            name: "grep".to_string(),
+            arguments: r#"{"pattern":"TODO"}"#.to_string(),
+        }]
+    );
+}
+
+// =============================================================================
+// Streaming Tests
+// =============================================================================
+
+#[tokio::test]
+async fn test_stream_yields_deltas_and_usage() {
+    use futures_util::StreamExt;
+    use wiremock::matchers::body_partial_json;
+
+    let server = MockServer::start().await;
+
+    let body = concat!(
+        "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+        "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n",
+        ": keep-alive\n\n",
+        "data: {\"choices\":[{\"delta\":{\"content\":\"lo\"}}]}\n\n",
+        "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":4,\"completion_tokens\":2,\"total_tokens\":6}}\n\n",
+        "data: [DONE]\n\n",
+    );
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(serde_json::json!({
+            "stream": true,
+            "stream_options": { "include_usage": true }
+        })))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(body),
+        )
+        .mount(&server)
+        .await;
+
+    let provider = create_provider_with_mock_server(&server).await;
+    let stream = provider.chat_stream(create_test_request()).await.unwrap();
+    let deltas: Vec<_> = stream.map(|d| d.unwrap()).collect().await;
+
+    let content: String = deltas.iter().map(|d| d.content.as_str()).collect();
+    assert_eq!(content, "Hello");
+    assert_eq!(
+        deltas.last().unwrap().usage.as_ref().unwrap().total_tokens,
+        6
+    );
+}
+
+#[tokio::test]
+async fn test_stream_rate_limit_fails_before_streaming() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(429))
+        .mount(&server)
+        .await;
+
+    let provider = create_provider_with_mock_server(&server).await;
+    let result = provider.chat_stream(create_test_request()).await;
+    assert!(matches!(result, Err(InferenceError::RateLimit)));
+}
+
+// =============================================================================
+// Embeddings Tests
+// =============================================================================
+
+#[tokio::test]
+async fn test_embeddings_follow_input_order() {
+    use brio_kernel::inference::EmbeddingRequest;
+    use wiremock::matchers::body_partial_json;
+
+    let server = MockServer::start().await;
+
+    // Out of order, as the API only guarantees `index`
+    let response_body = r#"{
+        "data": [
+            { "object": "embedding", "index": 1, "embedding": [0.5, 0.25] },
+            { "object": "embedding", "index": 0, "embedding": [1.0, -1.0] }
+        ],
+        "usage": { "prompt_tokens": 6, "total_tokens": 6 }
+    }"#;
+
+    Mock::given(method("POST"))
+        .and(path("/embeddings"))
+        .and(body_partial_json(serde_json::json!({
+            "model": "text-embedding-3-small",
+            "input": ["fn main", "struct Foo"]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_string(response_body))
+        .mount(&server)
+        .await;
+
+    let provider = create_provider_with_mock_server(&server).await;
+    let request = EmbeddingRequest::new(
+        "text-embedding-3-small",
+        vec!["fn main".to_string(), "struct Foo".to_string()],
+    );
+
+    let response = provider.embed(request).await.unwrap();
+    assert_eq!(response.embeddings, vec![vec![1.0, -1.0], vec![0.5, 0.25]]);
+    let usage = response.usage.unwrap();
+    assert_eq!(usage.prompt_tokens, 6);
+    assert_eq!(usage.completion_tokens, 0);
+}
+
+#[tokio::test]
+async fn test_embeddings_rate_limit_returns_rate_limit_error() {
+    use brio_kernel::inference::EmbeddingRequest;
+
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/embeddings"))
+        .respond_with(ResponseTemplate::new(429))
+        .mount(&server)
+        .await;
+
+    let provider = create_provider_with_mock_server(&server).await;
+    let request = EmbeddingRequest::new("text-embedding-3-small", vec!["x".to_string()]);
+    assert!(matches!(
+        provider.embed(request).await,
+        Err(InferenceError::RateLimit)
+    ));
+}
+
+// =============================================================================
+// Failover Tests
+// =============================================================================
+
+#[tokio::test]
+async fn test_server_error_after_retries_fails_over_and_trips_breaker() {
+    use brio_kernel::inference::breaker::GuardedProvider;
+    use brio_kernel::inference::failover::ChainMember;
+    use brio_kernel::inference::{
+        BreakerConfig, BreakerState, CircuitBreaker, FailoverChain, MockProvider,
+    };
+    use std::sync::Arc;
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(503).set_body_string("Service Unavailable"))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let config = OpenAIConfig::new(
+        SecretString::new("test-api-key".into()),
+        Url::parse(&format!("{}/", server.uri())).unwrap(),
+    )
+    .with_max_retries(1)
+    .with_base_delay_ms(1);
+    let breaker = Arc::new(CircuitBreaker::new(
+        "openai",
+        BreakerConfig {
+            failure_threshold: 1,
+            ..BreakerConfig::default()
+        },
+    ));
+    let guarded = GuardedProvider::new(Arc::new(OpenAIProvider::new(config)), breaker.clone());
+    let chain = FailoverChain::new(
+        "main",
+        vec![
+            ChainMember {
+                provider_name: "openai".to_string(),
+                provider: Arc::new(guarded),
+                model: None,
+            },
+            ChainMember {
+                provider_name: "backup".to_string(),
+                provider: Arc::new(MockProvider::new().with_response("from backup")),
+                model: None,
+            },
+        ],
+    );
+
+    let response = chain.chat(create_test_request()).await.unwrap();
+
+    assert_eq!(response.content, "from backup");
+    assert_eq!(breaker.state(), BreakerState::Open);
+}
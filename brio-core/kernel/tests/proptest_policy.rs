@@ -31,7 +31,7 @@ proptest! {
     ) {
         let table = format!("{}_data", scope);
         let sql = format!("SELECT * FROM {}", table);
-        let policy = PrefixPolicy;
+        let policy = PrefixPolicy::new();
 
         prop_assert!(policy.authorize(&scope, &sql).is_ok());
     }
@@ -48,7 +48,7 @@ proptest! {
         }
 
         let sql = format!("SELECT * FROM {}", bad_table);
-        let policy = PrefixPolicy;
+        let policy = PrefixPolicy::new();
 
         prop_assert!(policy.authorize(&scope, &sql).is_err());
     }
@@ -60,7 +60,7 @@ proptest! {
     ) {
         let table = format!("{}_records", scope);
         let sql = format!("INSERT INTO {} (name) VALUES ('test')", table);
-        let policy = PrefixPolicy;
+        let policy = PrefixPolicy::new();
 
         prop_assert!(policy.authorize(&scope, &sql).is_ok());
     }
@@ -72,7 +72,7 @@ proptest! {
     ) {
         let table = format!("{}_items", scope);
         let sql = format!("UPDATE {} SET status = 'done' WHERE id = 1", table);
-        let policy = PrefixPolicy;
+        let policy = PrefixPolicy::new();
 
         prop_assert!(policy.authorize(&scope, &sql).is_ok());
     }
@@ -84,7 +84,7 @@ proptest! {
     ) {
         let table = format!("{}_logs", scope);
         let sql = format!("DELETE FROM {} WHERE id > 100", table);
-        let policy = PrefixPolicy;
+        let policy = PrefixPolicy::new();
 
         prop_assert!(policy.authorize(&scope, &sql).is_ok());
     }
@@ -105,7 +105,7 @@ proptest! {
             "SELECT * FROM {} JOIN {} ON {}.id = {}.id",
             good_table, bad_table, good_table, bad_table
         );
-        let policy = PrefixPolicy;
+        let policy = PrefixPolicy::new();
 
         prop_assert!(policy.authorize(&scope, &sql).is_err());
     }
@@ -121,7 +121,7 @@ proptest! {
             "SELECT * FROM {} JOIN {} ON {}.order_id = {}.id",
             table1, table2, table1, table2
         );
-        let policy = PrefixPolicy;
+        let policy = PrefixPolicy::new();
 
         prop_assert!(policy.authorize(&scope, &sql).is_ok());
     }
@@ -136,7 +136,7 @@ proptest! {
             "SELECT * FROM {} WHERE id IN (SELECT id FROM system_admin)",
             good_table
         );
-        let policy = PrefixPolicy;
+        let policy = PrefixPolicy::new();
 
         prop_assert!(policy.authorize(&scope, &sql).is_err());
     }
@@ -1,9 +1,26 @@
 use brio_kernel::engine::linker::create_engine_config;
-use brio_kernel::registry::PluginRegistry;
+use brio_kernel::registry::{PluginRegistry, PluginWorld};
 use std::fs::File;
 use tempfile::tempdir;
 use wasmtime::Engine;
 
+/// A minimal component exporting `brio:core/tool-read-file`. `marker` varies
+/// the bytes so tests can produce distinct builds of the same plugin.
+fn tool_component(marker: i32) -> String {
+    format!(
+        r#"(component
+            (core module $m
+                (memory (export "memory") 1)
+                (func (export "realloc") (param i32 i32 i32 i32) (result i32) (i32.const {marker}))
+                (func (export "read-file") (param i32 i32) (result i32) (i32.const 0)))
+            (core instance $i (instantiate $m))
+            (func $read (param "path" string) (result (result string (error string)))
+                (canon lift (core func $i "read-file") (memory $i "memory") (realloc (func $i "realloc"))))
+            (instance $tool (export "read-file" (func $read)))
+            (export "brio:core/tool-read-file" (instance $tool)))"#
+    )
+}
+
 #[tokio::test]
 async fn test_registry_scanning() -> anyhow::Result<()> {
     // Setup
@@ -11,14 +28,14 @@ async fn test_registry_scanning() -> anyhow::Result<()> {
     let plugins_path = dir.path();
 
     // Create dummy plugins
-    File::create(plugins_path.join("agent_alpha.wasm"))?;
-    File::create(plugins_path.join("agent_beta.wasm"))?;
+    std::fs::write(plugins_path.join("agent_alpha.wasm"), tool_component(16))?;
+    std::fs::write(plugins_path.join("agent_beta.wasm"), tool_component(16))?;
     File::create(plugins_path.join("README.txt"))?; // Should be ignored
 
     // Initialize Registry
     let config = create_engine_config();
     let engine = Engine::new(&config)?;
-    let mut registry = PluginRegistry::new(engine);
+    let registry = PluginRegistry::new(engine);
 
     // Test load
     registry.load_from_directory(plugins_path).await?;
@@ -32,3 +49,271 @@ async fn test_registry_scanning() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_registry_reuses_instance_pre() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let cache_dir = dir.path().join("cache");
+    std::fs::write(dir.path().join("empty_agent.wasm"), tool_component(16))?;
+
+    let engine = Engine::new(&create_engine_config())?;
+    let registry = PluginRegistry::new(engine).with_component_cache_dir(&cache_dir);
+    registry.load_from_directory(dir.path()).await?;
+
+    registry.instance_pre("empty_agent")?;
+    registry.instance_pre("empty_agent")?;
+
+    // Compiled exactly once and persisted for the next kernel start
+    assert_eq!(std::fs::read_dir(&cache_dir)?.count(), 1);
+    assert!(registry.instance_pre("missing").is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_registry_loads_manifests() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let plugins_path = dir.path();
+
+    // Sidecar manifest next to a bare component
+    std::fs::write(plugins_path.join("coder.wasm"), tool_component(16))?;
+    std::fs::write(
+        plugins_path.join("coder.toml"),
+        r#"
+        id = "coder"
+        version = "0.1.0"
+        permissions = ["ai:inference", "fs:write"]
+        exports = ["tool"]
+        "#,
+    )?;
+
+    // Plugin directory with plugin.toml
+    let council = plugins_path.join("council");
+    std::fs::create_dir(&council)?;
+    std::fs::write(council.join("council_agent.wasm"), tool_component(16))?;
+    std::fs::write(
+        council.join("plugin.toml"),
+        r#"
+        id = "council"
+        version = "1.2.0"
+        component = "council_agent.wasm"
+        permissions = ["ai:inference"]
+        subscriptions = ["task.created"]
+        "#,
+    )?;
+
+    let engine = Engine::new(&create_engine_config())?;
+    let registry = PluginRegistry::new(engine);
+    registry.load_from_directory(plugins_path).await?;
+
+    let coder = registry.get("coder").expect("coder loaded");
+    assert_eq!(coder.permissions, vec!["ai:inference", "fs:write"]);
+    assert_eq!(coder.version.as_deref(), Some("0.1.0"));
+    assert_eq!(coder.world, PluginWorld::Tool);
+
+    let council = registry.get("council").expect("council loaded");
+    assert!(council.path.ends_with("council/council_agent.wasm"));
+    assert_eq!(council.subscriptions, vec!["task.created"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_registry_refuses_invalid_manifest() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let plugins_path = dir.path();
+
+    std::fs::write(plugins_path.join("good.wasm"), tool_component(16))?;
+    std::fs::write(plugins_path.join("evil.wasm"), tool_component(16))?;
+    std::fs::write(
+        plugins_path.join("evil.toml"),
+        "id = \"evil\"\nversion = \"1.0.0\"\npermissions = [\"root\"]",
+    )?;
+
+    // Manifest pointing at a missing component
+    let ghost = plugins_path.join("ghost");
+    std::fs::create_dir(&ghost)?;
+    std::fs::write(
+        ghost.join("plugin.toml"),
+        "id = \"ghost\"\nversion = \"1.0.0\"",
+    )?;
+
+    let engine = Engine::new(&create_engine_config())?;
+    let registry = PluginRegistry::new(engine);
+    registry.load_from_directory(plugins_path).await?;
+
+    let plugins = registry.list_plugins();
+    assert_eq!(plugins.len(), 1);
+    assert_eq!(plugins[0].id, "good");
+    assert!(plugins[0].permissions.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_registry_refuses_invalid_components() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let plugins_path = dir.path();
+
+    std::fs::write(plugins_path.join("good.wasm"), tool_component(16))?;
+    // Imports an interface the kernel does not provide
+    std::fs::write(
+        plugins_path.join("foreign.wasm"),
+        r#"(component
+            (import "acme:widgets/store" (instance))
+            (instance $tool)
+            (export "brio:core/tool-noop" (instance $tool)))"#,
+    )?;
+    // Exports nothing the kernel knows how to call
+    std::fs::write(plugins_path.join("inert.wasm"), "(component)")?;
+    // Declares an export it does not provide
+    std::fs::write(plugins_path.join("liar.wasm"), tool_component(16))?;
+    std::fs::write(
+        plugins_path.join("liar.toml"),
+        "id = \"liar\"\nversion = \"1.0.0\"\nexports = [\"agent-runner\"]",
+    )?;
+    std::fs::write(plugins_path.join("broken.wasm"), "not a component")?;
+
+    let engine = Engine::new(&create_engine_config())?;
+    let registry = PluginRegistry::new(engine);
+    registry.load_from_directory(plugins_path).await?;
+
+    let ids: Vec<String> = registry.list_plugins().into_iter().map(|p| p.id).collect();
+    assert_eq!(ids, vec!["good"]);
+
+    // Rejected files are not retried until they change
+    assert!(registry.sync_directory(plugins_path).await?.is_empty());
+
+    Ok(())
+}
+
+/// A component of the repo's `smart-agent` world (`wit/`), importing every
+/// interface a guest built from it may import.
+fn repo_wit_agent() -> anyhow::Result<Vec<u8>> {
+    use wit_component::{ComponentEncoder, StringEncoding};
+    use wit_parser::{LiftLowerAbi, ManglingAndAbi, Resolve};
+
+    let mut resolve = Resolve::default();
+    let (package, _) = resolve.push_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/../wit"))?;
+    let world = resolve.select_world(&[package], Some("smart-agent"))?;
+    let mut module =
+        wit_component::dummy_module(&resolve, world, ManglingAndAbi::Legacy(LiftLowerAbi::Sync));
+    wit_component::embed_component_metadata(&mut module, &resolve, world, StringEncoding::UTF8)?;
+    ComponentEncoder::default()
+        .module(&module)?
+        .validate(true)
+        .encode()
+}
+
+#[tokio::test]
+async fn test_registry_loads_component_built_from_repo_wit() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    std::fs::write(dir.path().join("smart.wasm"), repo_wit_agent()?)?;
+
+    let engine = Engine::new(&create_engine_config())?;
+    let registry = PluginRegistry::new(engine);
+    registry.load_from_directory(dir.path()).await?;
+
+    let plugins = registry.list_plugins();
+    assert_eq!(plugins.len(), 1, "repo WIT imports must all be linked");
+    assert_eq!(plugins[0].world, PluginWorld::Agent);
+    registry.instance_pre("smart")?;
+    Ok(())
+}
+
+// =============================================================================
+// Hot Reload Tests
+// =============================================================================
+
+use brio_kernel::registry::PluginChange;
+use std::time::Duration;
+
+fn change_ids(changes: &[PluginChange]) -> Vec<String> {
+    changes
+        .iter()
+        .map(|c| match c {
+            PluginChange::Loaded(m) => format!("loaded:{}", m.id),
+            PluginChange::Reloaded(m) => format!("reloaded:{}", m.id),
+            PluginChange::Unloaded(m) => format!("unloaded:{}", m.id),
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_sync_loads_reloads_and_unloads() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let agent = dir.path().join("agent.wasm");
+    std::fs::write(&agent, tool_component(16))?;
+
+    let engine = Engine::new(&create_engine_config())?;
+    let registry = PluginRegistry::new(engine);
+
+    let changes = registry.sync_directory(dir.path()).await?;
+    assert_eq!(change_ids(&changes), vec!["loaded:agent"]);
+
+    // Nothing changed on disk
+    assert!(registry.sync_directory(dir.path()).await?.is_empty());
+
+    // A new build of the component is swapped in
+    std::fs::write(&agent, tool_component(32))?;
+    let changes = registry.sync_directory(dir.path()).await?;
+    assert_eq!(change_ids(&changes), vec!["reloaded:agent"]);
+    registry.instance_pre("agent")?;
+
+    std::fs::remove_file(&agent)?;
+    let changes = registry.sync_directory(dir.path()).await?;
+    assert_eq!(change_ids(&changes), vec!["unloaded:agent"]);
+    assert!(registry.get("agent").is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sync_keeps_previous_version_when_update_is_broken() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let agent = dir.path().join("agent.wasm");
+    std::fs::write(&agent, tool_component(16))?;
+
+    let engine = Engine::new(&create_engine_config())?;
+    let registry = PluginRegistry::new(engine);
+    registry.load_from_directory(dir.path()).await?;
+
+    std::fs::write(&agent, "not a component")?;
+    assert!(registry.sync_directory(dir.path()).await?.is_empty());
+    registry.instance_pre("agent")?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unload_drains_inflight_invocations() -> anyhow::Result<()> {
+    let dir = tempdir()?;
+    let agent = dir.path().join("agent.wasm");
+    std::fs::write(&agent, tool_component(16))?;
+
+    let engine = Engine::new(&create_engine_config())?;
+    let registry = std::sync::Arc::new(
+        PluginRegistry::new(engine).with_drain_timeout(Duration::from_secs(10)),
+    );
+    registry.load_from_directory(dir.path()).await?;
+
+    let lease = registry.acquire("agent")?;
+    std::fs::remove_file(&agent)?;
+
+    let sync = tokio::spawn({
+        let registry = registry.clone();
+        let path = dir.path().to_path_buf();
+        async move { registry.sync_directory(path).await }
+    });
+
+    // The old version stays alive while the lease is held, but is no longer routable
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!sync.is_finished());
+    assert!(registry.get("agent").is_none());
+
+    drop(lease);
+    let changes = tokio::time::timeout(Duration::from_secs(5), sync).await???;
+    assert_eq!(change_ids(&changes), vec!["unloaded:agent"]);
+
+    Ok(())
+}
@@ -23,7 +23,7 @@ fn test_commit_nonexistent_session() -> anyhow::Result<()> {
     let result = manager.commit_session("fake-session-id-12345".to_string());
 
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("not found"));
+    assert!(result.unwrap_err().to_string().contains("not found"));
     Ok(())
 }
 
@@ -211,6 +211,483 @@ fn test_session_delete_file() -> anyhow::Result<()> {
     Ok(())
 }
 
+// =============================================================================
+// Restart Tests
+// =============================================================================
+
+#[test]
+fn test_restored_sessions_survive_orphan_cleanup() -> anyhow::Result<()> {
+    let base = tempfile::tempdir()?;
+    let root = tempfile::tempdir()?;
+    fs::write(base.path().join("file.txt"), "original")?;
+
+    let mut before = SessionManager::default().with_root_dir(root.path());
+    let kept = before
+        .begin_session_for(
+            base.path().to_string_lossy().to_string(),
+            Some("coder".to_string()),
+            Some("task-1".to_string()),
+        )
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let lost = before
+        .begin_session(base.path().to_string_lossy().to_string())
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let abandoned = before
+        .begin_session(base.path().to_string_lossy().to_string())
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let records = vec![
+        before.session(&kept).cloned().unwrap(),
+        before.session(&lost).cloned().unwrap(),
+    ];
+    fs::write(root.path().join(&kept).join("file.txt"), "agent work")?;
+    fs::remove_dir_all(root.path().join(&lost))?;
+
+    // A restart knows only the persisted sessions
+    let mut after = SessionManager::default().with_root_dir(root.path());
+    assert_eq!(after.restore_sessions(records), vec![lost.clone()]);
+    assert_eq!(after.active_session_count(), 1);
+    assert_eq!(
+        after.session(&kept).unwrap().owner_plugin.as_deref(),
+        Some("coder")
+    );
+
+    // Only the session nobody persisted is reaped (directory and snapshot)
+    assert_eq!(
+        after
+            .cleanup_orphaned_sessions()
+            .map_err(|e| anyhow::anyhow!(e))?,
+        2
+    );
+    assert!(!root.path().join(&abandoned).exists());
+    assert!(!root.path().join(".snapshots").join(&lost).exists());
+
+    after.commit_session(kept).map_err(|e| anyhow::anyhow!(e))?;
+    assert_eq!(
+        fs::read_to_string(base.path().join("file.txt"))?,
+        "agent work"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_unused_sessions_expire() -> anyhow::Result<()> {
+    use brio_kernel::infrastructure::config::SandboxSettings;
+
+    let base = tempfile::tempdir()?;
+    let root = tempfile::tempdir()?;
+    fs::write(base.path().join("file.txt"), "original")?;
+
+    let mut before = SessionManager::default().with_root_dir(root.path());
+    let mut begin = |owner: &str| {
+        before
+            .begin_session_for(
+                base.path().to_string_lossy().to_string(),
+                Some(owner.to_string()),
+                None,
+            )
+            .map_err(|e| anyhow::anyhow!(e))
+    };
+    let (busy, idle, stale) = (begin("busy")?, begin("idle")?, begin("crashed")?);
+    // All began two hours ago; only `busy` has been used since
+    let records: Vec<_> = [&busy, &idle, &stale]
+        .into_iter()
+        .map(|id| {
+            let mut record = before.session(id).cloned().unwrap();
+            record.created_at -= 7200;
+            if *id != busy {
+                record.last_active -= 7200;
+            }
+            record
+        })
+        .collect();
+
+    let sandbox = SandboxSettings {
+        session_ttl_secs: Some(3600),
+        ..SandboxSettings::default()
+    };
+    let mut after = SessionManager::new(sandbox)
+        .map_err(|e| anyhow::anyhow!(e))?
+        .with_root_dir(root.path());
+    assert!(after.restore_sessions(records).is_empty());
+    assert!(after.touch_session(&idle).is_some());
+    assert!(after.touch_session(&idle).is_none());
+
+    assert_eq!(after.expire_sessions(), vec![stale.clone()]);
+    assert_eq!(after.active_session_count(), 2);
+    assert!(after.session(&busy).is_some() && after.session(&idle).is_some());
+    assert!(!root.path().join(&stale).exists());
+    assert!(!root.path().join(".snapshots").join(&stale).exists());
+
+    // Without a TTL sessions never expire
+    let mut manager = SessionManager::default().with_root_dir(root.path());
+    let mut old = after.session(&busy).cloned().unwrap();
+    old.last_active = 0;
+    manager.restore_sessions(vec![old]);
+    assert!(manager.expire_sessions().is_empty());
+    Ok(())
+}
+
+// =============================================================================
+// Diff Tests
+// =============================================================================
+
+#[test]
+fn test_diff_session_reports_unified_diffs() -> anyhow::Result<()> {
+    use brio_kernel::vfs::diff::ChangeKind;
+
+    let base = tempfile::tempdir()?;
+    fs::create_dir_all(base.path().join("src"))?;
+    fs::write(base.path().join("src/lib.rs"), "fn a() {}\nfn b() {}\n")?;
+    fs::write(base.path().join("old_name.md"), "# Notes\n")?;
+    fs::write(base.path().join("obsolete.txt"), "bye\n")?;
+    fs::write(base.path().join("logo.png"), b"\x89PNG\0\x01")?;
+
+    let root = tempfile::tempdir()?;
+    let mut manager = SessionManager::default().with_root_dir(root.path());
+    let session_id = manager
+        .begin_session(base.path().to_string_lossy().to_string())
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let session = manager.get_session_path(&session_id).unwrap();
+    assert!(
+        manager
+            .diff_session(&session_id)
+            .map_err(|e| anyhow::anyhow!(e))?
+            .is_empty()
+    );
+
+    fs::write(session.join("src/lib.rs"), "fn a() {}\nfn c() {}\n")?;
+    fs::rename(session.join("old_name.md"), session.join("NOTES.md"))?;
+    fs::remove_file(session.join("obsolete.txt"))?;
+    fs::write(session.join("logo.png"), b"\x89PNG\0\x02")?;
+    fs::write(session.join("new.txt"), "hello\n")?;
+
+    let diffs = manager
+        .diff_session(&session_id)
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let summary: Vec<(&str, ChangeKind, bool)> = diffs
+        .iter()
+        .map(|d| (d.path.to_str().unwrap(), d.kind, d.binary))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("NOTES.md", ChangeKind::Renamed, false),
+            ("logo.png", ChangeKind::Modified, true),
+            ("new.txt", ChangeKind::Added, false),
+            ("obsolete.txt", ChangeKind::Deleted, false),
+            ("src/lib.rs", ChangeKind::Modified, false),
+        ]
+    );
+    assert_eq!(
+        diffs[0].old_path.as_deref(),
+        Some(std::path::Path::new("old_name.md"))
+    );
+    assert!(diffs[1].patch.is_empty());
+    assert!(diffs[2].patch.starts_with("--- /dev/null\n+++ b/new.txt\n"));
+    assert!(diffs[3].patch.contains("-bye"));
+    assert!(
+        diffs[4]
+            .patch
+            .contains("--- a/src/lib.rs\n+++ b/src/lib.rs\n")
+    );
+    assert!(diffs[4].patch.contains("-fn b() {}\n+fn c() {}\n"));
+
+    // Diffing commits nothing
+    assert!(base.path().join("obsolete.txt").exists());
+    manager
+        .rollback_session(session_id)
+        .map_err(|e| anyhow::anyhow!(e))?;
+    Ok(())
+}
+
+// =============================================================================
+// Exclusion Tests
+// =============================================================================
+
+#[test]
+fn test_sessions_skip_excluded_paths() -> anyhow::Result<()> {
+    use brio_kernel::infrastructure::config::SandboxSettings;
+
+    let base = tempfile::tempdir()?;
+    let root = tempfile::tempdir()?;
+    for (path, content) in [
+        (".gitignore", "target/\n"),
+        (".brioignore", "*.secret\n"),
+        ("src/main.rs", "fn main() {}\n"),
+        ("target/debug/app", "binary"),
+        ("node_modules/left-pad/index.js", "module.exports = 1;"),
+        (".git/HEAD", "ref: refs/heads/main\n"),
+        ("api.secret", "hunter2"),
+    ] {
+        fs::create_dir_all(base.path().join(path).parent().unwrap())?;
+        fs::write(base.path().join(path), content)?;
+    }
+
+    let sandbox = SandboxSettings {
+        exclude: vec!["node_modules".to_string()],
+        ..SandboxSettings::default()
+    };
+    let mut manager = SessionManager::new(sandbox)
+        .map_err(|e| anyhow::anyhow!(e))?
+        .with_root_dir(root.path());
+    let session_id = manager
+        .begin_session(base.path().to_string_lossy().to_string())
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let session = manager.get_session_path(&session_id).unwrap();
+
+    assert!(session.join("src/main.rs").exists());
+    assert!(session.join(".gitignore").exists());
+    for excluded in ["target", "node_modules", ".git", "api.secret"] {
+        assert!(!session.join(excluded).exists(), "{} was copied", excluded);
+    }
+
+    // The agent builds in the session; a human rebuilds in the base
+    fs::create_dir_all(session.join("target/release"))?;
+    fs::write(session.join("target/release/app"), "agent build")?;
+    fs::write(session.join("src/main.rs"), "fn main() { run() }\n")?;
+    fs::write(base.path().join("target/debug/app"), "human build")?;
+
+    let diffs = manager
+        .diff_session(&session_id)
+        .map_err(|e| anyhow::anyhow!(e))?;
+    assert_eq!(diffs.len(), 1);
+    assert_eq!(diffs[0].path, std::path::Path::new("src/main.rs"));
+
+    manager
+        .commit_session(session_id)
+        .map_err(|e| anyhow::anyhow!(e))?;
+    assert_eq!(
+        fs::read_to_string(base.path().join("src/main.rs"))?,
+        "fn main() { run() }\n"
+    );
+    assert!(!base.path().join("target/release").exists());
+    assert_eq!(
+        fs::read_to_string(base.path().join("target/debug/app"))?,
+        "human build"
+    );
+    assert!(base.path().join("node_modules/left-pad/index.js").exists());
+    assert!(base.path().join(".git/HEAD").exists());
+    assert!(base.path().join("api.secret").exists());
+    Ok(())
+}
+
+#[test]
+fn test_commit_never_replaces_excluded_directories() -> anyhow::Result<()> {
+    use brio_kernel::infrastructure::config::SandboxSettings;
+    use brio_kernel::vfs::diff::{FileChange, apply_changes};
+    use std::path::PathBuf;
+
+    let base = tempfile::tempdir()?;
+    let root = tempfile::tempdir()?;
+    fs::write(base.path().join(".gitignore"), "target/\n")?;
+    fs::create_dir_all(base.path().join("target/debug"))?;
+    fs::write(base.path().join("target/debug/app"), "build")?;
+    fs::write(base.path().join("lib.rs"), "fn a() {}\n")?;
+
+    let mut manager = SessionManager::new(SandboxSettings::default())
+        .map_err(|e| anyhow::anyhow!(e))?
+        .with_root_dir(root.path());
+    let session_id = manager
+        .begin_session(base.path().to_string_lossy().to_string())
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let session = manager.get_session_path(&session_id).unwrap();
+
+    // A file where the base keeps an ignored directory stays in the session
+    fs::write(session.join("target"), "not a directory\n")?;
+    fs::write(session.join("lib.rs"), "fn b() {}\n")?;
+    manager
+        .commit_session(session_id)
+        .map_err(|e| anyhow::anyhow!(e))?;
+    assert_eq!(
+        fs::read_to_string(base.path().join("lib.rs"))?,
+        "fn b() {}\n"
+    );
+    assert_eq!(
+        fs::read_to_string(base.path().join("target/debug/app"))?,
+        "build"
+    );
+
+    // Applying changes only ever replaces directories that hold no files
+    let staged = tempfile::tempdir()?;
+    fs::write(staged.path().join("target"), "file")?;
+    let result = apply_changes(
+        staged.path(),
+        base.path(),
+        &[FileChange::Added(PathBuf::from("target"))],
+    );
+    assert!(result.is_err());
+    assert!(base.path().join("target/debug/app").exists());
+
+    fs::remove_file(base.path().join("target/debug/app"))?;
+    apply_changes(
+        staged.path(),
+        base.path(),
+        &[FileChange::Added(PathBuf::from("target"))],
+    )?;
+    assert_eq!(fs::read_to_string(base.path().join("target"))?, "file");
+    Ok(())
+}
+
+// =============================================================================
+// Merge Tests
+// =============================================================================
+
+#[test]
+fn test_commit_merges_unrelated_base_edits() -> anyhow::Result<()> {
+    let temp = tempfile::tempdir()?;
+    fs::write(temp.path().join("README.md"), "# Project\n")?;
+    fs::write(temp.path().join("lib.rs"), "fn one() {}\n\nfn two() {}\n")?;
+
+    let mut manager = SessionManager::new(Default::default()).map_err(|e| anyhow::anyhow!(e))?;
+    let session_id = manager
+        .begin_session(temp.path().to_string_lossy().to_string())
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let session_path = std::env::temp_dir().join("brio").join(&session_id);
+
+    // A human edits the README and the top of lib.rs meanwhile
+    fs::write(
+        temp.path().join("README.md"),
+        "# Project\n\nNow documented.\n",
+    )?;
+    fs::write(
+        temp.path().join("lib.rs"),
+        "/// One.\nfn one() {}\n\nfn two() {}\n",
+    )?;
+    fs::write(
+        session_path.join("lib.rs"),
+        "fn one() {}\n\nfn two() { todo!() }\n",
+    )?;
+
+    manager
+        .commit_session(session_id)
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+    assert_eq!(
+        fs::read_to_string(temp.path().join("README.md"))?,
+        "# Project\n\nNow documented.\n"
+    );
+    assert_eq!(
+        fs::read_to_string(temp.path().join("lib.rs"))?,
+        "/// One.\nfn one() {}\n\nfn two() { todo!() }\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_commit_reports_conflicts_until_resolved() -> anyhow::Result<()> {
+    use brio_kernel::vfs::merge::{CommitError, ConflictKind};
+
+    let temp = tempfile::tempdir()?;
+    fs::write(temp.path().join("config.toml"), "port = 3000\n")?;
+    fs::write(temp.path().join("untouched.txt"), "same\n")?;
+
+    let mut manager = SessionManager::new(Default::default()).map_err(|e| anyhow::anyhow!(e))?;
+    let session_id = manager
+        .begin_session(temp.path().to_string_lossy().to_string())
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let session_path = std::env::temp_dir().join("brio").join(&session_id);
+
+    fs::write(temp.path().join("config.toml"), "port = 8080\n")?;
+    fs::write(session_path.join("config.toml"), "port = 4000\n")?;
+    fs::write(session_path.join("new.txt"), "new\n")?;
+
+    let report = match manager.commit_session(session_id.clone()) {
+        Err(CommitError::Conflict(report)) => report,
+        other => anyhow::bail!("expected a conflict, got {:?}", other),
+    };
+    assert_eq!(report.conflicts.len(), 1);
+    assert_eq!(
+        report.conflicts[0].path,
+        std::path::PathBuf::from("config.toml")
+    );
+    assert_eq!(report.conflicts[0].kind, ConflictKind::Content);
+    assert!(report.to_string().contains("config.toml (content)"));
+
+    // Nothing was applied, and the session shows both sides
+    assert_eq!(
+        fs::read_to_string(temp.path().join("config.toml"))?,
+        "port = 8080\n"
+    );
+    assert!(!temp.path().join("new.txt").exists());
+    let marked = fs::read_to_string(session_path.join("config.toml"))?;
+    assert!(marked.contains("port = 8080") && marked.contains("port = 4000"));
+
+    // Retrying without resolving, or leaving markers behind, is refused
+    for _ in 0..2 {
+        let report = match manager.commit_session(session_id.clone()) {
+            Err(CommitError::Conflict(report)) => report,
+            other => anyhow::bail!("expected a conflict, got {:?}", other),
+        };
+        assert_eq!(report.conflicts[0].kind, ConflictKind::Content);
+        assert_eq!(
+            fs::read_to_string(temp.path().join("config.toml"))?,
+            "port = 8080\n"
+        );
+        fs::write(
+            session_path.join("config.toml"),
+            format!("# note\n{}", marked),
+        )?;
+    }
+    assert!(!temp.path().join("new.txt").exists());
+
+    // The resolved session commits on top of the base's change
+    fs::write(session_path.join("config.toml"), "port = 8081\n")?;
+    manager
+        .commit_session(session_id)
+        .map_err(|e| anyhow::anyhow!(e))?;
+    assert_eq!(
+        fs::read_to_string(temp.path().join("config.toml"))?,
+        "port = 8081\n"
+    );
+    assert_eq!(fs::read_to_string(temp.path().join("new.txt"))?, "new\n");
+    assert!(!session_path.exists());
+    Ok(())
+}
+
+#[test]
+fn test_deleted_conflicts_need_a_fresh_decision() -> anyhow::Result<()> {
+    use brio_kernel::vfs::merge::{CommitError, ConflictKind};
+
+    let base = tempfile::tempdir()?;
+    let root = tempfile::tempdir()?;
+    fs::write(base.path().join("old.txt"), "v1\n")?;
+
+    let mut manager = SessionManager::default().with_root_dir(root.path());
+    let session_id = manager
+        .begin_session(base.path().to_string_lossy().to_string())
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let session_path = root.path().join(&session_id);
+    fs::remove_file(session_path.join("old.txt"))?;
+    fs::write(base.path().join("old.txt"), "v2\n")?;
+
+    // The deleted file comes back as the base has it, and stays until the
+    // session deletes it again
+    for _ in 0..2 {
+        match manager.commit_session(session_id.clone()) {
+            Err(CommitError::Conflict(report)) => {
+                assert_eq!(report.conflicts[0].kind, ConflictKind::Deleted)
+            }
+            other => anyhow::bail!("expected a conflict, got {:?}", other),
+        }
+        assert_eq!(fs::read_to_string(session_path.join("old.txt"))?, "v2\n");
+        assert_eq!(fs::read_to_string(base.path().join("old.txt"))?, "v2\n");
+    }
+    assert!(
+        !manager
+            .session(&session_id)
+            .unwrap()
+            .pending_conflicts
+            .is_empty()
+    );
+
+    fs::remove_file(session_path.join("old.txt"))?;
+    manager
+        .commit_session(session_id)
+        .map_err(|e| anyhow::anyhow!(e))?;
+    assert!(!base.path().join("old.txt").exists());
+    Ok(())
+}
+
 // =============================================================================
 // SessionManager Default Trait Test
 // =============================================================================